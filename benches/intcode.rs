@@ -0,0 +1,95 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use advent_of_code_2019::input::parse_list;
+use advent_of_code_2019::intcode::{Machine, ProgramMachine, StepResult};
+
+const ITERATIONS: u32 = 10;
+
+fn main() {
+    bench(
+        "day9 (BOOST sensor mode)",
+        include_str!("../src/bin/day9/input"),
+        |machine| {
+            machine.add_input(2);
+            machine.run();
+        },
+    );
+    bench(
+        "day19 (50x50 beam scan)",
+        include_str!("../src/bin/day19/input"),
+        |machine| {
+            for y in 0..50 {
+                for x in 0..50 {
+                    let mut drone = machine.clone();
+                    drone.add_input(x);
+                    drone.add_input(y);
+                    drone.run_to_output();
+                }
+            }
+        },
+    );
+    bench(
+        "day23 (first packet to 255)",
+        include_str!("../src/bin/day23/input"),
+        |machine| {
+            let mut computers = (0..50)
+                .map(|address| {
+                    let mut computer = machine.clone();
+                    computer.add_input(address);
+                    (computer, VecDeque::new())
+                })
+                .collect::<Vec<_>>();
+            'outer: loop {
+                for i in 0..computers.len() {
+                    let (computer, queue) = &mut computers[i];
+                    match computer.step() {
+                        StepResult::NeedInput => match queue.pop_front() {
+                            Some((x, y)) => {
+                                computer.add_input(x);
+                                computer.add_input(y);
+                            }
+                            None => computer.add_input(-1),
+                        },
+                        StepResult::Output(address) => {
+                            let x = computer.run_to_output().unwrap();
+                            let y = computer.run_to_output().unwrap();
+                            if address == 255 {
+                                break 'outer;
+                            }
+                            computers[address as usize].1.push_back((x, y));
+                        }
+                        StepResult::Ok | StepResult::Halt => {}
+                    }
+                }
+            }
+        },
+    );
+}
+
+fn bench(name: &str, input: &str, run: impl Fn(&mut ProgramMachine)) {
+    let program: Vec<i64> = parse_list(input, ',');
+    let uncached = measure(&program, false, &run);
+    let cached = measure(&program, true, &run);
+    println!(
+        "{:<32} uncached {:>10.3?}  cached {:>10.3?}  speedup {:.2}x",
+        name,
+        uncached,
+        cached,
+        uncached.as_secs_f64() / cached.as_secs_f64()
+    );
+}
+
+fn measure(program: &[i64], use_cache: bool, run: &impl Fn(&mut ProgramMachine)) -> Duration {
+    let mut machine = ProgramMachine::new(program.to_vec(), vec![]);
+    machine.set_decode_cache(use_cache);
+    // the fastest run, since anything slower was disturbed by something else
+    (0..ITERATIONS)
+        .map(|_| {
+            let start = Instant::now();
+            run(&mut machine.clone());
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
@@ -1,7 +1,7 @@
-use advent_of_code_2019::input::parse_list;
+use advent_of_code_2019::input::{input_from_args, parse_list};
 
 fn main() {
-    let input: Vec<u32> = parse_list(include_str!("input"), '\n');
+    let input: Vec<u32> = parse_list(&input_from_args(include_str!("input")), '\n');
     println!("Answer to part 1: {}", part1(&input));
     println!("Answer to part 2: {}", part2(&input));
 }
@@ -1,11 +1,12 @@
 use std::collections::HashSet;
 use std::f64::consts::PI;
 
+use advent_of_code_2019::input::input_from_args;
 use advent_of_code_2019::math::gcd;
 use advent_of_code_2019::vector2d::Vector2D;
 
 fn main() {
-    let grid: Grid = parse_input(include_str!("input"));
+    let grid: Grid = parse_input(&input_from_args(include_str!("input")));
     println!("Answer to part 1: {}", part1(&grid));
     println!("Answer to part 2: {}", part2(&grid));
 }
@@ -1,11 +1,12 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
 
-use advent_of_code_2019::input::parse_list;
+use advent_of_code_2019::input::intcode_from_args;
 use advent_of_code_2019::intcode::*;
 use advent_of_code_2019::vector2d::Vector2D;
 
 fn main() {
-    let input: Vec<i64> = parse_list(include_str!("input"), ',');
+    let input: Vec<i64> = intcode_from_args(include_str!("input"));
     println!("Answer to part 1: {}", part1(&input));
     println!("Answer to part 2:");
     part2(&input);
@@ -76,38 +77,52 @@ impl Direction {
     }
 }
 
-fn run(program: &Vec<i64>, start_color: Color) -> HashMap<Vector2D, Color> {
-    let mut machine = ProgramMachine::new(program.clone(), vec![]);
-    let mut grid: HashMap<Vector2D, Color> = HashMap::new();
-    let mut pos = Vector2D::zero();
-    let mut dir = Direction::Up;
-    grid.insert(pos, start_color);
-    loop {
-        let color = grid.get(&pos).unwrap_or(&Color::BLACK);
-        machine.add_input(color.to_number());
-        match machine.run_to_output() {
-            Some(value) => {
-                grid.insert(pos, Color::parse(value));
-            }
-            None => {
-                break;
-            }
-        }
-        match machine.run_to_output() {
-            Some(value) => {
-                dir = match value {
-                    0 => dir.rotate_left(),
-                    1 => dir.rotate_right(),
-                    _ => panic!("unexpected rotation"),
-                }
-            }
-            None => {
-                break;
-            }
+struct Robot {
+    grid: HashMap<Vector2D, Color>,
+    pos: Vector2D,
+    dir: Direction,
+    painted: bool,
+}
+
+impl Robot {
+    fn camera(&self) -> i64 {
+        self.grid
+            .get(&self.pos)
+            .unwrap_or(&Color::BLACK)
+            .to_number()
+    }
+
+    fn command(&mut self, value: i64) {
+        if !self.painted {
+            self.grid.insert(self.pos, Color::parse(value));
+        } else {
+            self.dir = match value {
+                0 => self.dir.rotate_left(),
+                1 => self.dir.rotate_right(),
+                _ => panic!("unexpected rotation"),
+            };
+            self.pos += self.dir.step();
         }
-        pos += dir.step();
+        self.painted = !self.painted;
     }
-    grid
+}
+
+fn run(program: &Vec<i64>, start_color: Color) -> HashMap<Vector2D, Color> {
+    let mut machine = ProgramMachine::new(program.clone(), vec![]);
+    let robot = RefCell::new(Robot {
+        grid: HashMap::new(),
+        pos: Vector2D::zero(),
+        dir: Direction::Up,
+        painted: false,
+    });
+    robot
+        .borrow_mut()
+        .grid
+        .insert(Vector2D::zero(), start_color);
+    machine.run_with(&mut || Some(robot.borrow().camera()), &mut |value| {
+        robot.borrow_mut().command(value)
+    });
+    robot.into_inner().grid
 }
 
 fn part1(program: &Vec<i64>) -> usize {
@@ -134,7 +149,15 @@ fn part2(program: &Vec<i64>) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use advent_of_code_2019::input::parse_list;
 
     #[test]
     fn test_part1() {}
+
+    #[test]
+    fn test_run_with_callbacks() {
+        // the robot reads its camera and takes commands through the machine's callbacks
+        let input: Vec<i64> = parse_list(include_str!("input"), ',');
+        assert_eq!(run(&input, Color::BLACK).len(), 2415);
+    }
 }
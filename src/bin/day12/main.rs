@@ -3,11 +3,12 @@ use std::collections::HashSet;
 
 use regex::Regex;
 
+use advent_of_code_2019::input::input_from_args;
 use advent_of_code_2019::math::lcm_64;
 use advent_of_code_2019::vector3d::Vector3D;
 
 fn main() {
-    let moons: Vec<Moon> = parse_input(include_str!("input"));
+    let moons: Vec<Moon> = parse_input(&input_from_args(include_str!("input")));
     println!("Answer to part 1: {}", part1(&moons));
     println!("Answer to part 2: {}", part2(&moons));
 }
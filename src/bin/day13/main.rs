@@ -1,14 +1,15 @@
+use std::cell::RefCell;
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::io;
 use std::io::BufRead;
 
-use advent_of_code_2019::input::parse_list;
+use advent_of_code_2019::input::intcode_from_args;
 use advent_of_code_2019::intcode::*;
 use advent_of_code_2019::vector2d::Vector2D;
 
 fn main() {
-    let program: Vec<i64> = parse_list(include_str!("input"), ',');
+    let program: Vec<i64> = intcode_from_args(include_str!("input"));
     println!("Answer to part 1: {}", part1(&program));
     println!("Answer to part 2: {}", part2(&program, false));
 }
@@ -48,63 +49,65 @@ impl Tile {
 type Screen = HashMap<Vector2D, Tile>;
 
 fn part1(program: &Vec<i64>) -> usize {
-    let mut screen: Screen = HashMap::new();
     let mut machine = ProgramMachine::new(program.clone(), vec![]);
-    loop {
-        let x = match machine.run_to_output() {
-            Some(value) => value,
-            None => {
-                break;
+    let mut game = Game::new();
+    machine.run_with(&mut || None, &mut |value| game.output(value));
+    game.screen
+        .values()
+        .filter(|&tile| tile == &Tile::BLOCK)
+        .count()
+}
+
+struct Game {
+    screen: Screen,
+    score: i64,
+    pending: Vec<i64>,
+}
+
+impl Game {
+    fn new() -> Game {
+        Game {
+            screen: HashMap::new(),
+            score: 0,
+            pending: Vec::new(),
+        }
+    }
+
+    fn output(&mut self, value: i64) {
+        self.pending.push(value);
+        if let [x, y, z] = self.pending[..] {
+            if x == -1 && y == 0 {
+                self.score = z;
+            } else {
+                self.screen
+                    .insert(Vector2D::new(x as i32, y as i32), Tile::from_id(z as i32));
             }
-        };
-        let y = machine.run_to_output().unwrap();
-        let tile_id = machine.run_to_output().unwrap();
-        screen.insert(
-            Vector2D::new(x as i32, y as i32),
-            Tile::from_id(tile_id as i32),
-        );
+            self.pending.clear();
+        }
+    }
+
+    fn joystick(&self, interactive: bool) -> i64 {
+        if interactive {
+            print_screen(&self.screen);
+            println!("Score: {}", self.score);
+            read_joystick()
+        } else {
+            compute_joystick(&self.screen)
+        }
     }
-    screen.values().filter(|&tile| tile == &Tile::BLOCK).count()
 }
 
 fn part2(program: &Vec<i64>, interactive: bool) -> i64 {
     let mut program = program.clone();
     program[0] = 2;
 
-    let mut screen: Screen = HashMap::new();
-    let mut score: i64 = 0;
     let mut machine = ProgramMachine::new(program, vec![]);
-    'outer: loop {
-        let x: i64 = loop {
-            match machine.step() {
-                StepResult::NeedInput => {
-                    machine.add_input(if interactive {
-                        print_screen(&screen);
-                        println!("Score: {}", score);
-                        read_joystick()
-                    } else {
-                        compute_joystick(&screen)
-                    });
-                }
-                StepResult::Output(value) => {
-                    break value;
-                }
-                StepResult::Halt => {
-                    break 'outer;
-                }
-                StepResult::Ok => {}
-            }
-        };
-        let y = machine.run_to_output().unwrap();
-        let z = machine.run_to_output().unwrap();
-        if x == -1 && y == 0 {
-            score = z;
-        } else {
-            screen.insert(Vector2D::new(x as i32, y as i32), Tile::from_id(z as i32));
-        }
-    }
-
-    score
+    let game = RefCell::new(Game::new());
+    machine.run_with(
+        &mut || Some(game.borrow().joystick(interactive)),
+        &mut |value| game.borrow_mut().output(value),
+    );
+    game.into_inner().score
 }
 
 fn print_screen(screen: &Screen) {
@@ -157,3 +160,21 @@ fn compute_joystick(screen: &Screen) -> i64 {
         Ordering::Greater => 1, // right
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use advent_of_code_2019::input::parse_list;
+
+    #[test]
+    fn test_part1() {
+        let program: Vec<i64> = parse_list(include_str!("input"), ',');
+        assert_eq!(part1(&program), 333);
+    }
+
+    #[test]
+    fn test_part2() {
+        let program: Vec<i64> = parse_list(include_str!("input"), ',');
+        assert_eq!(part2(&program, false), 16539);
+    }
+}
@@ -2,10 +2,10 @@ use std::collections::HashMap;
 use std::fmt::{Display, Error, Formatter};
 use std::str::FromStr;
 
-use advent_of_code_2019::input::parse_list;
+use advent_of_code_2019::input::{input_from_args, parse_list};
 
 fn main() {
-    let input: Vec<Reaction> = parse_list(include_str!("input"), '\n');
+    let input: Vec<Reaction> = parse_list(&input_from_args(include_str!("input")), '\n');
     println!("Answer to part 1: {}", part1(&input));
     println!("Answer to part 2: {}", part2(&input));
 }
@@ -4,12 +4,12 @@ use std::collections::HashMap;
 use pathfinding::directed::astar::*;
 use pathfinding::directed::dijkstra::*;
 
-use advent_of_code_2019::input::parse_list;
+use advent_of_code_2019::input::intcode_from_args;
 use advent_of_code_2019::intcode::{Machine, ProgramMachine};
 use advent_of_code_2019::vector2d::Vector2D;
 
 fn main() {
-    let program: Vec<i64> = parse_list(include_str!("input"), ',');
+    let program: Vec<i64> = intcode_from_args(include_str!("input"));
     println!("Answer to part 1: {}", part1(&program));
     println!("Answer to part 2: {}", part2(&program));
 }
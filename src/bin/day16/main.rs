@@ -1,8 +1,10 @@
 use std::char::from_digit;
 use std::iter::*;
 
+use advent_of_code_2019::input::input_from_args;
+
 fn main() {
-    let input: Vec<i32> = parse_input(include_str!("input"));
+    let input: Vec<i32> = parse_input(&input_from_args(include_str!("input")));
     println!("Answer to part 1: {}", part1(&input));
     println!("Answer to part 2: {}", part2(&input));
 }
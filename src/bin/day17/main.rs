@@ -1,11 +1,12 @@
 use std::collections::HashMap;
 
-use advent_of_code_2019::input::parse_list;
+use advent_of_code_2019::input::intcode_from_args;
+use advent_of_code_2019::intcode::ascii::{AsciiError, AsciiMachine};
 use advent_of_code_2019::intcode::{Machine, ProgramMachine};
 use advent_of_code_2019::vector2d::Vector2D;
 
 fn main() {
-    let program: Vec<i64> = parse_list(include_str!("input"), ',');
+    let program: Vec<i64> = intcode_from_args(include_str!("input"));
     println!("Answer to part 1: {}", part1(&program));
     println!("Answer to part 2: {}", part2(&program));
 }
@@ -194,10 +195,14 @@ fn print_grid(grid: &Grid) {
 fn part2(program: &Vec<i64>) -> i64 {
     let mut program = program.clone();
     program[0] = 2;
-    let mut machine = ProgramMachine::new(program, vec![]);
+    let mut machine = AsciiMachine::new(ProgramMachine::new(program, vec![]));
+    collect_dust(&mut machine).unwrap_or_else(|err| panic!("{}", err))
+}
 
+fn collect_dust(machine: &mut AsciiMachine<ProgramMachine>) -> Result<i64, AsciiError> {
     // start grid
-    let grid: Grid = read_grid(&mut machine);
+    let lines = machine.expect_prompt("Main:")?;
+    let grid: Grid = parse_grid(lines.join("\n").trim_end());
     // print_grid(&grid);
 
     // compute the path
@@ -208,25 +213,19 @@ fn part2(program: &Vec<i64>) -> i64 {
     let main = build_main(&path, &functions);
 
     // fill in the prompts
-    assert_eq!(machine.read_line(), "Main:");
-    machine.add_line(&main);
-    assert_eq!(machine.read_line(), "Function A:");
-    machine.add_line(&commands_to_string(&functions.a));
-    assert_eq!(machine.read_line(), "Function B:");
-    machine.add_line(&commands_to_string(&functions.b));
-    assert_eq!(machine.read_line(), "Function C:");
-    machine.add_line(&commands_to_string(&functions.c));
-    assert_eq!(machine.read_line(), "Continuous video feed?");
-    machine.add_line("n");
-
-    // final grid
-    let grid: Grid = read_grid(&mut machine);
-    // print_grid(&grid);
-
-    // collected dust
-    let output = machine.run();
-    assert_eq!(output.len(), 1);
-    output[0]
+    machine.send_line(&main);
+    machine.expect_prompt("Function A:")?;
+    machine.send_line(&commands_to_string(&functions.a));
+    machine.expect_prompt("Function B:")?;
+    machine.send_line(&commands_to_string(&functions.b));
+    machine.expect_prompt("Function C:")?;
+    machine.send_line(&commands_to_string(&functions.c));
+    machine.expect_prompt("Continuous video feed?")?;
+    machine.send_line("n");
+
+    // final grid, then the collected dust
+    let (_grid, dust) = machine.expect_value()?;
+    Ok(dust)
 }
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
@@ -2,10 +2,11 @@ use std::collections::HashMap;
 
 use pathfinding::directed::bfs::bfs;
 
+use advent_of_code_2019::input::input_from_args;
 use advent_of_code_2019::vector2d::Vector2D;
 
 fn main() {
-    let (grid, starts) = parse_grid(include_str!("input"));
+    let (grid, starts) = parse_grid(&input_from_args(include_str!("input")));
     println!("Answer to part 1: {}", part1(&grid, &starts[0]));
 
     let (grid, starts) = split_grid(&grid, starts[0]);
@@ -1,23 +1,38 @@
 use std::collections::HashSet;
 
-use advent_of_code_2019::input::parse_list;
+use advent_of_code_2019::input::{exit_with, load_intcode, source_from_args};
 use advent_of_code_2019::intcode::{Machine, ProgramMachine};
 use advent_of_code_2019::vector2d::Vector2D;
 
+// The drone program, translated with `intcode-aot src/bin/day19/input Probe`, since it
+// runs once for every position
+#[rustfmt::skip]
+mod probe;
+
+use probe::Probe;
+
 fn main() {
-    let program: Vec<i64> = parse_list(include_str!("input"), ',');
-    println!("Answer to part 1: {}", part1(&program));
-    println!("Answer to part 2: {}", part2(&program));
+    if let Some(source) = source_from_args() {
+        // the translated probe only knows the built-in program
+        let program = load_intcode(&source).unwrap_or_else(|err| exit_with(err));
+        let drone = ProgramMachine::new(program, vec![]);
+        println!("Answer to part 1: {}", part1(&drone));
+        println!("Answer to part 2: {}", part2(&drone));
+    } else {
+        let drone = Probe::new(vec![]);
+        println!("Answer to part 1: {}", part1(&drone));
+        println!("Answer to part 2: {}", part2(&drone));
+    }
 }
 
 type Beam = HashSet<Vector2D>;
 
-fn part1(program: &Vec<i64>) -> usize {
+fn part1(drone: &(impl Machine + Clone)) -> usize {
     let mut beam: Beam = HashSet::new();
     for y in 0..50 {
         for x in 0..50 {
             let pos = Vector2D::new(x, y);
-            if in_beam(&program, &pos) {
+            if in_beam(drone, &pos) {
                 beam.insert(pos);
             }
         }
@@ -25,8 +40,8 @@ fn part1(program: &Vec<i64>) -> usize {
     beam.len()
 }
 
-fn in_beam(program: &Vec<i64>, pos: &Vector2D) -> bool {
-    let mut machine = ProgramMachine::new(program.clone(), vec![]);
+fn in_beam(drone: &(impl Machine + Clone), pos: &Vector2D) -> bool {
+    let mut machine = drone.clone();
     machine.add_input(pos.x as i64);
     machine.add_input(pos.y as i64);
     match machine.run_to_output() {
@@ -58,7 +73,7 @@ fn print_beam(beam: &Beam) {
     }
 }
 
-fn part2(program: &Vec<i64>) -> i32 {
+fn part2(drone: &(impl Machine + Clone)) -> i32 {
     let size = 100;
     let mut beam: Beam = HashSet::new();
     let mut min_x = 0;
@@ -67,7 +82,7 @@ fn part2(program: &Vec<i64>) -> i32 {
     'outer: for limit in 1.. {
         for x in min_x..=limit {
             let pos = Vector2D::new(x, limit);
-            if in_beam(&program, &pos) {
+            if in_beam(drone, &pos) {
                 break;
             } else {
                 min_x += 1;
@@ -75,7 +90,7 @@ fn part2(program: &Vec<i64>) -> i32 {
         }
         for x in min_x..=limit {
             let pos = Vector2D::new(x, limit);
-            if in_beam(&program, &pos) {
+            if in_beam(drone, &pos) {
                 beam.insert(pos);
                 if fits_square(&beam, size, &pos) {
                     corner_br = Some(pos);
@@ -85,7 +100,7 @@ fn part2(program: &Vec<i64>) -> i32 {
         }
         for y in min_y..limit {
             let pos = Vector2D::new(limit, y);
-            if in_beam(&program, &pos) {
+            if in_beam(drone, &pos) {
                 break;
             } else {
                 min_y += 1;
@@ -93,7 +108,7 @@ fn part2(program: &Vec<i64>) -> i32 {
         }
         for y in min_y..limit {
             let pos = Vector2D::new(limit, y);
-            if in_beam(&program, &pos) {
+            if in_beam(drone, &pos) {
                 beam.insert(pos);
                 if fits_square(&beam, size, &pos) {
                     corner_br = Some(pos);
@@ -121,6 +136,33 @@ fn fits_square(beam: &Beam, size: i32, &corner: &Vector2D) -> bool {
 mod tests {
     use super::*;
 
+    use advent_of_code_2019::input::parse_list;
+    use advent_of_code_2019::intcode::ProgramMachine;
+
     #[test]
     fn test_part1_example1() {}
+
+    #[test]
+    fn test_same_as_interpreter() {
+        let program: Vec<i64> = parse_list(include_str!("input"), ',');
+        let interpreter = ProgramMachine::new(program, vec![]);
+        let translated = Probe::new(vec![]);
+        for y in 0..20 {
+            for x in 0..20 {
+                let pos = Vector2D::new(x, y);
+                assert_eq!(in_beam(&translated, &pos), in_beam(&interpreter, &pos));
+            }
+        }
+        // the probe patches a jump target, which doesn't need the interpreter
+        let mut probe = translated.clone();
+        probe.add_input(10);
+        probe.add_input(10);
+        probe.run();
+        assert!(!probe.runtime().is_interpreted());
+    }
+
+    #[test]
+    fn test_part1() {
+        assert_eq!(part1(&Probe::new(vec![])), 164);
+    }
 }
@@ -0,0 +1,704 @@
+// Generated by intcode-aot. Do not edit.
+
+use advent_of_code_2019::intcode::aot::Runtime;
+use advent_of_code_2019::intcode::{IntcodeError, Machine, StepResult};
+
+const PROGRAM: &[i64] = &[
+    109, 424, 203, 1, 21102, 11, 1, 0, 1105, 1, 282, 21102,
+    1, 18, 0, 1106, 0, 259, 2101, 0, 1, 221, 203, 1,
+    21102, 1, 31, 0, 1106, 0, 282, 21102, 38, 1, 0, 1105,
+    1, 259, 20101, 0, 23, 2, 22101, 0, 1, 3, 21101, 1,
+    0, 1, 21101, 57, 0, 0, 1105, 1, 303, 2101, 0, 1,
+    222, 21001, 221, 0, 3, 21002, 221, 1, 2, 21101, 0, 259,
+    1, 21102, 80, 1, 0, 1106, 0, 225, 21102, 89, 1, 2,
+    21102, 91, 1, 0, 1105, 1, 303, 2101, 0, 1, 223, 20101,
+    0, 222, 4, 21101, 0, 259, 3, 21102, 1, 225, 2, 21102,
+    225, 1, 1, 21102, 118, 1, 0, 1106, 0, 225, 20101, 0,
+    222, 3, 21101, 136, 0, 2, 21101, 133, 0, 0, 1106, 0,
+    303, 21202, 1, -1, 1, 22001, 223, 1, 1, 21101, 148, 0,
+    0, 1105, 1, 259, 1202, 1, 1, 223, 20102, 1, 221, 4,
+    21001, 222, 0, 3, 21102, 18, 1, 2, 1001, 132, -2, 224,
+    1002, 224, 2, 224, 1001, 224, 3, 224, 1002, 132, -1, 132,
+    1, 224, 132, 224, 21001, 224, 1, 1, 21102, 195, 1, 0,
+    106, 0, 108, 20207, 1, 223, 2, 20102, 1, 23, 1, 21101,
+    -1, 0, 3, 21101, 214, 0, 0, 1105, 1, 303, 22101, 1,
+    1, 1, 204, 1, 99, 0, 0, 0, 0, 109, 5, 1202,
+    -4, 1, 249, 21201, -3, 0, 1, 22102, 1, -2, 2, 21202,
+    -1, 1, 3, 21102, 1, 250, 0, 1105, 1, 225, 21201, 1,
+    0, -4, 109, -5, 2105, 1, 0, 109, 3, 22107, 0, -2,
+    -1, 21202, -1, 2, -1, 21201, -1, -1, -1, 22202, -1, -2,
+    -2, 109, -3, 2105, 1, 0, 109, 3, 21207, -2, 0, -1,
+    1206, -1, 294, 104, 0, 99, 22102, 1, -2, -2, 109, -3,
+    2105, 1, 0, 109, 5, 22207, -3, -4, -1, 1206, -1, 346,
+    22201, -4, -3, -4, 21202, -3, -1, -1, 22201, -4, -1, 2,
+    21202, 2, -1, -1, 22201, -4, -1, 1, 21201, -2, 0, 3,
+    21102, 343, 1, 0, 1106, 0, 303, 1105, 1, 415, 22207, -2,
+    -3, -1, 1206, -1, 387, 22201, -3, -2, -3, 21202, -2, -1,
+    -1, 22201, -3, -1, 3, 21202, 3, -1, -1, 22201, -3, -1,
+    2, 21202, -4, 1, 1, 21102, 384, 1, 0, 1105, 1, 303,
+    1106, 0, 415, 21202, -4, -1, -4, 22201, -4, -3, -4, 22202,
+    -3, -2, -2, 22202, -2, -4, -4, 22202, -3, -2, -3, 21202,
+    -4, -1, -2, 22201, -3, -2, 1, 21202, 1, 1, -4, 109,
+    -5, 2106, 0, 0,
+];
+
+const CODE: &[(usize, usize)] = &[
+    (0, 132), (133, 221), (225, 249), (250, 424),
+];
+
+#[derive(Debug, Clone)]
+pub struct Probe(Runtime);
+
+impl Probe {
+    pub fn new(input: Vec<i64>) -> Probe {
+        Probe(Runtime::new(PROGRAM, CODE, input))
+    }
+
+    #[allow(dead_code)]
+    pub fn runtime(&self) -> &Runtime {
+        &self.0
+    }
+}
+
+impl Machine for Probe {
+    fn add_input(&mut self, value: i64) {
+        self.0.add_input(value);
+    }
+
+    fn try_step(&mut self) -> Result<StepResult, IntcodeError> {
+        if self.0.is_interpreted() {
+            return self.0.try_step();
+        }
+        match self.0.pc() {
+            0 => self.block_0(),
+            2 => self.block_2(),
+            11 => self.block_11(),
+            18 => self.block_18(),
+            22 => self.block_22(),
+            31 => self.block_31(),
+            38 => self.block_38(),
+            57 => self.block_57(),
+            80 => self.block_80(),
+            91 => self.block_91(),
+            118 => self.block_118(),
+            133 => self.block_133(),
+            148 => self.block_148(),
+            195 => self.block_195(),
+            214 => self.block_214(),
+            220 => self.block_220(),
+            225 => self.block_225(),
+            250 => self.block_250(),
+            259 => self.block_259(),
+            282 => self.block_282(),
+            291 => self.block_291(),
+            293 => self.block_293(),
+            294 => self.block_294(),
+            303 => self.block_303(),
+            312 => self.block_312(),
+            343 => self.block_343(),
+            346 => self.block_346(),
+            353 => self.block_353(),
+            384 => self.block_384(),
+            387 => self.block_387(),
+            415 => self.block_415(),
+            _ => self.0.try_step(),
+        }
+    }
+}
+
+impl Probe {
+    fn block_0(&mut self) -> Result<StepResult, IntcodeError> {
+        // 0: arb #424
+        self.0.offset_base(0, 424)?;
+        Ok(self.0.goto(2))
+    }
+
+    fn block_2(&mut self) -> Result<StepResult, IntcodeError> {
+        // 2: in rb+1
+        let value = match self.0.input(2) {
+            Some(value) => value,
+            None => return Ok(StepResult::NeedInput),
+        };
+        if self.0.write(2, self.0.relative(2, 1)?, value)? {
+            return Ok(self.0.fall_back(4));
+        }
+        // 4: mul #11, #1, rb+0
+        let value = self.0.mul(4, 11, 1)?;
+        if self.0.write(4, self.0.relative(4, 0)?, value)? {
+            return Ok(self.0.fall_back(8));
+        }
+        // 8: jt #1, #282
+        self.0.jump(8, 282)
+    }
+
+    fn block_11(&mut self) -> Result<StepResult, IntcodeError> {
+        // 11: mul #1, #18, rb+0
+        let value = self.0.mul(11, 1, 18)?;
+        if self.0.write(11, self.0.relative(11, 0)?, value)? {
+            return Ok(self.0.fall_back(15));
+        }
+        // 15: jf #0, #259
+        self.0.jump(15, 259)
+    }
+
+    fn block_18(&mut self) -> Result<StepResult, IntcodeError> {
+        // 18: add #0, rb+1, [221]
+        let value = self.0.add(18, 0, self.0.read(18, self.0.relative(18, 1)?)?)?;
+        if self.0.write(18, 221, value)? {
+            return Ok(self.0.fall_back(22));
+        }
+        Ok(self.0.goto(22))
+    }
+
+    fn block_22(&mut self) -> Result<StepResult, IntcodeError> {
+        // 22: in rb+1
+        let value = match self.0.input(22) {
+            Some(value) => value,
+            None => return Ok(StepResult::NeedInput),
+        };
+        if self.0.write(22, self.0.relative(22, 1)?, value)? {
+            return Ok(self.0.fall_back(24));
+        }
+        // 24: mul #1, #31, rb+0
+        let value = self.0.mul(24, 1, 31)?;
+        if self.0.write(24, self.0.relative(24, 0)?, value)? {
+            return Ok(self.0.fall_back(28));
+        }
+        // 28: jf #0, #282
+        self.0.jump(28, 282)
+    }
+
+    fn block_31(&mut self) -> Result<StepResult, IntcodeError> {
+        // 31: mul #38, #1, rb+0
+        let value = self.0.mul(31, 38, 1)?;
+        if self.0.write(31, self.0.relative(31, 0)?, value)? {
+            return Ok(self.0.fall_back(35));
+        }
+        // 35: jt #1, #259
+        self.0.jump(35, 259)
+    }
+
+    fn block_38(&mut self) -> Result<StepResult, IntcodeError> {
+        // 38: add #0, [23], rb+2
+        let value = self.0.add(38, 0, self.0.read(38, 23)?)?;
+        if self.0.write(38, self.0.relative(38, 2)?, value)? {
+            return Ok(self.0.fall_back(42));
+        }
+        // 42: add #0, rb+1, rb+3
+        let value = self.0.add(42, 0, self.0.read(42, self.0.relative(42, 1)?)?)?;
+        if self.0.write(42, self.0.relative(42, 3)?, value)? {
+            return Ok(self.0.fall_back(46));
+        }
+        // 46: add #1, #0, rb+1
+        let value = self.0.add(46, 1, 0)?;
+        if self.0.write(46, self.0.relative(46, 1)?, value)? {
+            return Ok(self.0.fall_back(50));
+        }
+        // 50: add #57, #0, rb+0
+        let value = self.0.add(50, 57, 0)?;
+        if self.0.write(50, self.0.relative(50, 0)?, value)? {
+            return Ok(self.0.fall_back(54));
+        }
+        // 54: jt #1, #303
+        self.0.jump(54, 303)
+    }
+
+    fn block_57(&mut self) -> Result<StepResult, IntcodeError> {
+        // 57: add #0, rb+1, [222]
+        let value = self.0.add(57, 0, self.0.read(57, self.0.relative(57, 1)?)?)?;
+        if self.0.write(57, 222, value)? {
+            return Ok(self.0.fall_back(61));
+        }
+        // 61: add [221], #0, rb+3
+        let value = self.0.add(61, self.0.read(61, 221)?, 0)?;
+        if self.0.write(61, self.0.relative(61, 3)?, value)? {
+            return Ok(self.0.fall_back(65));
+        }
+        // 65: mul [221], #1, rb+2
+        let value = self.0.mul(65, self.0.read(65, 221)?, 1)?;
+        if self.0.write(65, self.0.relative(65, 2)?, value)? {
+            return Ok(self.0.fall_back(69));
+        }
+        // 69: add #0, #259, rb+1
+        let value = self.0.add(69, 0, 259)?;
+        if self.0.write(69, self.0.relative(69, 1)?, value)? {
+            return Ok(self.0.fall_back(73));
+        }
+        // 73: mul #80, #1, rb+0
+        let value = self.0.mul(73, 80, 1)?;
+        if self.0.write(73, self.0.relative(73, 0)?, value)? {
+            return Ok(self.0.fall_back(77));
+        }
+        // 77: jf #0, #225
+        self.0.jump(77, 225)
+    }
+
+    fn block_80(&mut self) -> Result<StepResult, IntcodeError> {
+        // 80: mul #89, #1, rb+2
+        let value = self.0.mul(80, 89, 1)?;
+        if self.0.write(80, self.0.relative(80, 2)?, value)? {
+            return Ok(self.0.fall_back(84));
+        }
+        // 84: mul #91, #1, rb+0
+        let value = self.0.mul(84, 91, 1)?;
+        if self.0.write(84, self.0.relative(84, 0)?, value)? {
+            return Ok(self.0.fall_back(88));
+        }
+        // 88: jt #1, #303
+        self.0.jump(88, 303)
+    }
+
+    fn block_91(&mut self) -> Result<StepResult, IntcodeError> {
+        // 91: add #0, rb+1, [223]
+        let value = self.0.add(91, 0, self.0.read(91, self.0.relative(91, 1)?)?)?;
+        if self.0.write(91, 223, value)? {
+            return Ok(self.0.fall_back(95));
+        }
+        // 95: add #0, [222], rb+4
+        let value = self.0.add(95, 0, self.0.read(95, 222)?)?;
+        if self.0.write(95, self.0.relative(95, 4)?, value)? {
+            return Ok(self.0.fall_back(99));
+        }
+        // 99: add #0, #259, rb+3
+        let value = self.0.add(99, 0, 259)?;
+        if self.0.write(99, self.0.relative(99, 3)?, value)? {
+            return Ok(self.0.fall_back(103));
+        }
+        // 103: mul #1, #225, rb+2
+        let value = self.0.mul(103, 1, 225)?;
+        if self.0.write(103, self.0.relative(103, 2)?, value)? {
+            return Ok(self.0.fall_back(107));
+        }
+        // 107: mul #225, #1, rb+1
+        let value = self.0.mul(107, 225, 1)?;
+        if self.0.write(107, self.0.relative(107, 1)?, value)? {
+            return Ok(self.0.fall_back(111));
+        }
+        // 111: mul #118, #1, rb+0
+        let value = self.0.mul(111, 118, 1)?;
+        if self.0.write(111, self.0.relative(111, 0)?, value)? {
+            return Ok(self.0.fall_back(115));
+        }
+        // 115: jf #0, #225
+        self.0.jump(115, 225)
+    }
+
+    fn block_118(&mut self) -> Result<StepResult, IntcodeError> {
+        // 118: add #0, [222], rb+3
+        let value = self.0.add(118, 0, self.0.read(118, 222)?)?;
+        if self.0.write(118, self.0.relative(118, 3)?, value)? {
+            return Ok(self.0.fall_back(122));
+        }
+        // 122: add #136, #0, rb+2
+        let value = self.0.add(122, 136, 0)?;
+        if self.0.write(122, self.0.relative(122, 2)?, value)? {
+            return Ok(self.0.fall_back(126));
+        }
+        // 126: add #133, #0, rb+0
+        let value = self.0.add(126, 133, 0)?;
+        if self.0.write(126, self.0.relative(126, 0)?, value)? {
+            return Ok(self.0.fall_back(130));
+        }
+        // 130: jf #0, #303
+        self.0.jump(130, self.0.read(130, 132)?)
+    }
+
+    fn block_133(&mut self) -> Result<StepResult, IntcodeError> {
+        // 133: mul rb+1, #-1, rb+1
+        let value = self.0.mul(133, self.0.read(133, self.0.relative(133, 1)?)?, -1)?;
+        if self.0.write(133, self.0.relative(133, 1)?, value)? {
+            return Ok(self.0.fall_back(137));
+        }
+        // 137: add [223], rb+1, rb+1
+        let value = self.0.add(137, self.0.read(137, 223)?, self.0.read(137, self.0.relative(137, 1)?)?)?;
+        if self.0.write(137, self.0.relative(137, 1)?, value)? {
+            return Ok(self.0.fall_back(141));
+        }
+        // 141: add #148, #0, rb+0
+        let value = self.0.add(141, 148, 0)?;
+        if self.0.write(141, self.0.relative(141, 0)?, value)? {
+            return Ok(self.0.fall_back(145));
+        }
+        // 145: jt #1, #259
+        self.0.jump(145, 259)
+    }
+
+    fn block_148(&mut self) -> Result<StepResult, IntcodeError> {
+        // 148: mul rb+1, #1, [223]
+        let value = self.0.mul(148, self.0.read(148, self.0.relative(148, 1)?)?, 1)?;
+        if self.0.write(148, 223, value)? {
+            return Ok(self.0.fall_back(152));
+        }
+        // 152: mul #1, [221], rb+4
+        let value = self.0.mul(152, 1, self.0.read(152, 221)?)?;
+        if self.0.write(152, self.0.relative(152, 4)?, value)? {
+            return Ok(self.0.fall_back(156));
+        }
+        // 156: add [222], #0, rb+3
+        let value = self.0.add(156, self.0.read(156, 222)?, 0)?;
+        if self.0.write(156, self.0.relative(156, 3)?, value)? {
+            return Ok(self.0.fall_back(160));
+        }
+        // 160: mul #18, #1, rb+2
+        let value = self.0.mul(160, 18, 1)?;
+        if self.0.write(160, self.0.relative(160, 2)?, value)? {
+            return Ok(self.0.fall_back(164));
+        }
+        // 164: add [132], #-2, [224]
+        let value = self.0.add(164, self.0.read(164, 132)?, -2)?;
+        if self.0.write(164, 224, value)? {
+            return Ok(self.0.fall_back(168));
+        }
+        // 168: mul [224], #2, [224]
+        let value = self.0.mul(168, self.0.read(168, 224)?, 2)?;
+        if self.0.write(168, 224, value)? {
+            return Ok(self.0.fall_back(172));
+        }
+        // 172: add [224], #3, [224]
+        let value = self.0.add(172, self.0.read(172, 224)?, 3)?;
+        if self.0.write(172, 224, value)? {
+            return Ok(self.0.fall_back(176));
+        }
+        // 176: mul [132], #-1, [132]
+        let value = self.0.mul(176, self.0.read(176, 132)?, -1)?;
+        if self.0.write(176, 132, value)? {
+            return Ok(self.0.fall_back(180));
+        }
+        // 180: add [224], [132], [224]
+        let value = self.0.add(180, self.0.read(180, 224)?, self.0.read(180, 132)?)?;
+        if self.0.write(180, 224, value)? {
+            return Ok(self.0.fall_back(184));
+        }
+        // 184: add [224], #1, rb+1
+        let value = self.0.add(184, self.0.read(184, 224)?, 1)?;
+        if self.0.write(184, self.0.relative(184, 1)?, value)? {
+            return Ok(self.0.fall_back(188));
+        }
+        // 188: mul #195, #1, rb+0
+        let value = self.0.mul(188, 195, 1)?;
+        if self.0.write(188, self.0.relative(188, 0)?, value)? {
+            return Ok(self.0.fall_back(192));
+        }
+        // 192: jf #0, [108]
+        self.0.jump(192, self.0.read(192, 108)?)
+    }
+
+    fn block_195(&mut self) -> Result<StepResult, IntcodeError> {
+        // 195: lt rb+1, [223], rb+2
+        let value = (self.0.read(195, self.0.relative(195, 1)?)? < self.0.read(195, 223)?) as i64;
+        if self.0.write(195, self.0.relative(195, 2)?, value)? {
+            return Ok(self.0.fall_back(199));
+        }
+        // 199: mul #1, [23], rb+1
+        let value = self.0.mul(199, 1, self.0.read(199, 23)?)?;
+        if self.0.write(199, self.0.relative(199, 1)?, value)? {
+            return Ok(self.0.fall_back(203));
+        }
+        // 203: add #-1, #0, rb+3
+        let value = self.0.add(203, -1, 0)?;
+        if self.0.write(203, self.0.relative(203, 3)?, value)? {
+            return Ok(self.0.fall_back(207));
+        }
+        // 207: add #214, #0, rb+0
+        let value = self.0.add(207, 214, 0)?;
+        if self.0.write(207, self.0.relative(207, 0)?, value)? {
+            return Ok(self.0.fall_back(211));
+        }
+        // 211: jt #1, #303
+        self.0.jump(211, 303)
+    }
+
+    fn block_214(&mut self) -> Result<StepResult, IntcodeError> {
+        // 214: add #1, rb+1, rb+1
+        let value = self.0.add(214, 1, self.0.read(214, self.0.relative(214, 1)?)?)?;
+        if self.0.write(214, self.0.relative(214, 1)?, value)? {
+            return Ok(self.0.fall_back(218));
+        }
+        // 218: out rb+1
+        Ok(self.0.output(220, self.0.read(218, self.0.relative(218, 1)?)?))
+    }
+
+    fn block_220(&mut self) -> Result<StepResult, IntcodeError> {
+        // 220: hlt
+        Ok(self.0.halt(220))
+    }
+
+    fn block_225(&mut self) -> Result<StepResult, IntcodeError> {
+        // 225: arb #5
+        self.0.offset_base(225, 5)?;
+        // 227: mul rb-4, #1, [249]
+        let value = self.0.mul(227, self.0.read(227, self.0.relative(227, -4)?)?, 1)?;
+        if self.0.write(227, 249, value)? {
+            return Ok(self.0.fall_back(231));
+        }
+        // 231: add rb-3, #0, rb+1
+        let value = self.0.add(231, self.0.read(231, self.0.relative(231, -3)?)?, 0)?;
+        if self.0.write(231, self.0.relative(231, 1)?, value)? {
+            return Ok(self.0.fall_back(235));
+        }
+        // 235: mul #1, rb-2, rb+2
+        let value = self.0.mul(235, 1, self.0.read(235, self.0.relative(235, -2)?)?)?;
+        if self.0.write(235, self.0.relative(235, 2)?, value)? {
+            return Ok(self.0.fall_back(239));
+        }
+        // 239: mul rb-1, #1, rb+3
+        let value = self.0.mul(239, self.0.read(239, self.0.relative(239, -1)?)?, 1)?;
+        if self.0.write(239, self.0.relative(239, 3)?, value)? {
+            return Ok(self.0.fall_back(243));
+        }
+        // 243: mul #1, #250, rb+0
+        let value = self.0.mul(243, 1, 250)?;
+        if self.0.write(243, self.0.relative(243, 0)?, value)? {
+            return Ok(self.0.fall_back(247));
+        }
+        // 247: jt #1, #225
+        self.0.jump(247, self.0.read(247, 249)?)
+    }
+
+    fn block_250(&mut self) -> Result<StepResult, IntcodeError> {
+        // 250: add rb+1, #0, rb-4
+        let value = self.0.add(250, self.0.read(250, self.0.relative(250, 1)?)?, 0)?;
+        if self.0.write(250, self.0.relative(250, -4)?, value)? {
+            return Ok(self.0.fall_back(254));
+        }
+        // 254: arb #-5
+        self.0.offset_base(254, -5)?;
+        // 256: jt #1, rb+0
+        self.0.jump(256, self.0.read(256, self.0.relative(256, 0)?)?)
+    }
+
+    fn block_259(&mut self) -> Result<StepResult, IntcodeError> {
+        // 259: arb #3
+        self.0.offset_base(259, 3)?;
+        // 261: lt #0, rb-2, rb-1
+        let value = (0 < self.0.read(261, self.0.relative(261, -2)?)?) as i64;
+        if self.0.write(261, self.0.relative(261, -1)?, value)? {
+            return Ok(self.0.fall_back(265));
+        }
+        // 265: mul rb-1, #2, rb-1
+        let value = self.0.mul(265, self.0.read(265, self.0.relative(265, -1)?)?, 2)?;
+        if self.0.write(265, self.0.relative(265, -1)?, value)? {
+            return Ok(self.0.fall_back(269));
+        }
+        // 269: add rb-1, #-1, rb-1
+        let value = self.0.add(269, self.0.read(269, self.0.relative(269, -1)?)?, -1)?;
+        if self.0.write(269, self.0.relative(269, -1)?, value)? {
+            return Ok(self.0.fall_back(273));
+        }
+        // 273: mul rb-1, rb-2, rb-2
+        let value = self.0.mul(273, self.0.read(273, self.0.relative(273, -1)?)?, self.0.read(273, self.0.relative(273, -2)?)?)?;
+        if self.0.write(273, self.0.relative(273, -2)?, value)? {
+            return Ok(self.0.fall_back(277));
+        }
+        // 277: arb #-3
+        self.0.offset_base(277, -3)?;
+        // 279: jt #1, rb+0
+        self.0.jump(279, self.0.read(279, self.0.relative(279, 0)?)?)
+    }
+
+    fn block_282(&mut self) -> Result<StepResult, IntcodeError> {
+        // 282: arb #3
+        self.0.offset_base(282, 3)?;
+        // 284: lt rb-2, #0, rb-1
+        let value = (self.0.read(284, self.0.relative(284, -2)?)? < 0) as i64;
+        if self.0.write(284, self.0.relative(284, -1)?, value)? {
+            return Ok(self.0.fall_back(288));
+        }
+        // 288: jf rb-1, #294
+        if self.0.read(288, self.0.relative(288, -1)?)? == 0 {
+            return self.0.jump(288, 294);
+        }
+        Ok(self.0.goto(291))
+    }
+
+    fn block_291(&mut self) -> Result<StepResult, IntcodeError> {
+        // 291: out #0
+        Ok(self.0.output(293, 0))
+    }
+
+    fn block_293(&mut self) -> Result<StepResult, IntcodeError> {
+        // 293: hlt
+        Ok(self.0.halt(293))
+    }
+
+    fn block_294(&mut self) -> Result<StepResult, IntcodeError> {
+        // 294: mul #1, rb-2, rb-2
+        let value = self.0.mul(294, 1, self.0.read(294, self.0.relative(294, -2)?)?)?;
+        if self.0.write(294, self.0.relative(294, -2)?, value)? {
+            return Ok(self.0.fall_back(298));
+        }
+        // 298: arb #-3
+        self.0.offset_base(298, -3)?;
+        // 300: jt #1, rb+0
+        self.0.jump(300, self.0.read(300, self.0.relative(300, 0)?)?)
+    }
+
+    fn block_303(&mut self) -> Result<StepResult, IntcodeError> {
+        // 303: arb #5
+        self.0.offset_base(303, 5)?;
+        // 305: lt rb-3, rb-4, rb-1
+        let value = (self.0.read(305, self.0.relative(305, -3)?)? < self.0.read(305, self.0.relative(305, -4)?)?) as i64;
+        if self.0.write(305, self.0.relative(305, -1)?, value)? {
+            return Ok(self.0.fall_back(309));
+        }
+        // 309: jf rb-1, #346
+        if self.0.read(309, self.0.relative(309, -1)?)? == 0 {
+            return self.0.jump(309, 346);
+        }
+        Ok(self.0.goto(312))
+    }
+
+    fn block_312(&mut self) -> Result<StepResult, IntcodeError> {
+        // 312: add rb-4, rb-3, rb-4
+        let value = self.0.add(312, self.0.read(312, self.0.relative(312, -4)?)?, self.0.read(312, self.0.relative(312, -3)?)?)?;
+        if self.0.write(312, self.0.relative(312, -4)?, value)? {
+            return Ok(self.0.fall_back(316));
+        }
+        // 316: mul rb-3, #-1, rb-1
+        let value = self.0.mul(316, self.0.read(316, self.0.relative(316, -3)?)?, -1)?;
+        if self.0.write(316, self.0.relative(316, -1)?, value)? {
+            return Ok(self.0.fall_back(320));
+        }
+        // 320: add rb-4, rb-1, rb+2
+        let value = self.0.add(320, self.0.read(320, self.0.relative(320, -4)?)?, self.0.read(320, self.0.relative(320, -1)?)?)?;
+        if self.0.write(320, self.0.relative(320, 2)?, value)? {
+            return Ok(self.0.fall_back(324));
+        }
+        // 324: mul rb+2, #-1, rb-1
+        let value = self.0.mul(324, self.0.read(324, self.0.relative(324, 2)?)?, -1)?;
+        if self.0.write(324, self.0.relative(324, -1)?, value)? {
+            return Ok(self.0.fall_back(328));
+        }
+        // 328: add rb-4, rb-1, rb+1
+        let value = self.0.add(328, self.0.read(328, self.0.relative(328, -4)?)?, self.0.read(328, self.0.relative(328, -1)?)?)?;
+        if self.0.write(328, self.0.relative(328, 1)?, value)? {
+            return Ok(self.0.fall_back(332));
+        }
+        // 332: add rb-2, #0, rb+3
+        let value = self.0.add(332, self.0.read(332, self.0.relative(332, -2)?)?, 0)?;
+        if self.0.write(332, self.0.relative(332, 3)?, value)? {
+            return Ok(self.0.fall_back(336));
+        }
+        // 336: mul #343, #1, rb+0
+        let value = self.0.mul(336, 343, 1)?;
+        if self.0.write(336, self.0.relative(336, 0)?, value)? {
+            return Ok(self.0.fall_back(340));
+        }
+        // 340: jf #0, #303
+        self.0.jump(340, 303)
+    }
+
+    fn block_343(&mut self) -> Result<StepResult, IntcodeError> {
+        // 343: jt #1, #415
+        self.0.jump(343, 415)
+    }
+
+    fn block_346(&mut self) -> Result<StepResult, IntcodeError> {
+        // 346: lt rb-2, rb-3, rb-1
+        let value = (self.0.read(346, self.0.relative(346, -2)?)? < self.0.read(346, self.0.relative(346, -3)?)?) as i64;
+        if self.0.write(346, self.0.relative(346, -1)?, value)? {
+            return Ok(self.0.fall_back(350));
+        }
+        // 350: jf rb-1, #387
+        if self.0.read(350, self.0.relative(350, -1)?)? == 0 {
+            return self.0.jump(350, 387);
+        }
+        Ok(self.0.goto(353))
+    }
+
+    fn block_353(&mut self) -> Result<StepResult, IntcodeError> {
+        // 353: add rb-3, rb-2, rb-3
+        let value = self.0.add(353, self.0.read(353, self.0.relative(353, -3)?)?, self.0.read(353, self.0.relative(353, -2)?)?)?;
+        if self.0.write(353, self.0.relative(353, -3)?, value)? {
+            return Ok(self.0.fall_back(357));
+        }
+        // 357: mul rb-2, #-1, rb-1
+        let value = self.0.mul(357, self.0.read(357, self.0.relative(357, -2)?)?, -1)?;
+        if self.0.write(357, self.0.relative(357, -1)?, value)? {
+            return Ok(self.0.fall_back(361));
+        }
+        // 361: add rb-3, rb-1, rb+3
+        let value = self.0.add(361, self.0.read(361, self.0.relative(361, -3)?)?, self.0.read(361, self.0.relative(361, -1)?)?)?;
+        if self.0.write(361, self.0.relative(361, 3)?, value)? {
+            return Ok(self.0.fall_back(365));
+        }
+        // 365: mul rb+3, #-1, rb-1
+        let value = self.0.mul(365, self.0.read(365, self.0.relative(365, 3)?)?, -1)?;
+        if self.0.write(365, self.0.relative(365, -1)?, value)? {
+            return Ok(self.0.fall_back(369));
+        }
+        // 369: add rb-3, rb-1, rb+2
+        let value = self.0.add(369, self.0.read(369, self.0.relative(369, -3)?)?, self.0.read(369, self.0.relative(369, -1)?)?)?;
+        if self.0.write(369, self.0.relative(369, 2)?, value)? {
+            return Ok(self.0.fall_back(373));
+        }
+        // 373: mul rb-4, #1, rb+1
+        let value = self.0.mul(373, self.0.read(373, self.0.relative(373, -4)?)?, 1)?;
+        if self.0.write(373, self.0.relative(373, 1)?, value)? {
+            return Ok(self.0.fall_back(377));
+        }
+        // 377: mul #384, #1, rb+0
+        let value = self.0.mul(377, 384, 1)?;
+        if self.0.write(377, self.0.relative(377, 0)?, value)? {
+            return Ok(self.0.fall_back(381));
+        }
+        // 381: jt #1, #303
+        self.0.jump(381, 303)
+    }
+
+    fn block_384(&mut self) -> Result<StepResult, IntcodeError> {
+        // 384: jf #0, #415
+        self.0.jump(384, 415)
+    }
+
+    fn block_387(&mut self) -> Result<StepResult, IntcodeError> {
+        // 387: mul rb-4, #-1, rb-4
+        let value = self.0.mul(387, self.0.read(387, self.0.relative(387, -4)?)?, -1)?;
+        if self.0.write(387, self.0.relative(387, -4)?, value)? {
+            return Ok(self.0.fall_back(391));
+        }
+        // 391: add rb-4, rb-3, rb-4
+        let value = self.0.add(391, self.0.read(391, self.0.relative(391, -4)?)?, self.0.read(391, self.0.relative(391, -3)?)?)?;
+        if self.0.write(391, self.0.relative(391, -4)?, value)? {
+            return Ok(self.0.fall_back(395));
+        }
+        // 395: mul rb-3, rb-2, rb-2
+        let value = self.0.mul(395, self.0.read(395, self.0.relative(395, -3)?)?, self.0.read(395, self.0.relative(395, -2)?)?)?;
+        if self.0.write(395, self.0.relative(395, -2)?, value)? {
+            return Ok(self.0.fall_back(399));
+        }
+        // 399: mul rb-2, rb-4, rb-4
+        let value = self.0.mul(399, self.0.read(399, self.0.relative(399, -2)?)?, self.0.read(399, self.0.relative(399, -4)?)?)?;
+        if self.0.write(399, self.0.relative(399, -4)?, value)? {
+            return Ok(self.0.fall_back(403));
+        }
+        // 403: mul rb-3, rb-2, rb-3
+        let value = self.0.mul(403, self.0.read(403, self.0.relative(403, -3)?)?, self.0.read(403, self.0.relative(403, -2)?)?)?;
+        if self.0.write(403, self.0.relative(403, -3)?, value)? {
+            return Ok(self.0.fall_back(407));
+        }
+        // 407: mul rb-4, #-1, rb-2
+        let value = self.0.mul(407, self.0.read(407, self.0.relative(407, -4)?)?, -1)?;
+        if self.0.write(407, self.0.relative(407, -2)?, value)? {
+            return Ok(self.0.fall_back(411));
+        }
+        // 411: add rb-3, rb-2, rb+1
+        let value = self.0.add(411, self.0.read(411, self.0.relative(411, -3)?)?, self.0.read(411, self.0.relative(411, -2)?)?)?;
+        if self.0.write(411, self.0.relative(411, 1)?, value)? {
+            return Ok(self.0.fall_back(415));
+        }
+        Ok(self.0.goto(415))
+    }
+
+    fn block_415(&mut self) -> Result<StepResult, IntcodeError> {
+        // 415: mul rb+1, #1, rb-4
+        let value = self.0.mul(415, self.0.read(415, self.0.relative(415, 1)?)?, 1)?;
+        if self.0.write(415, self.0.relative(415, -4)?, value)? {
+            return Ok(self.0.fall_back(419));
+        }
+        // 419: arb #-5
+        self.0.offset_base(419, -5)?;
+        // 421: jf #0, rb+0
+        self.0.jump(421, self.0.read(421, self.0.relative(421, 0)?)?)
+    }
+}
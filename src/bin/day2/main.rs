@@ -1,8 +1,9 @@
-use advent_of_code_2019::input::parse_list;
+use advent_of_code_2019::input::intcode_from_args;
+use advent_of_code_2019::intcode::symbolic::{Goal, Symbolic};
 use advent_of_code_2019::intcode::*;
 
 fn main() {
-    let input: Vec<i64> = parse_list(include_str!("input"), ',');
+    let input: Vec<i64> = intcode_from_args(include_str!("input"));
     println!("Answer to part 1: {}", part1(&input));
     println!("Answer to part 2: {}", part2(&input));
 }
@@ -26,19 +27,22 @@ fn run_with_noun_and_verb(program: &Vec<i64>, noun: i64, verb: i64) -> i64 {
 }
 
 fn solve_for(program: &Vec<i64>, target: i64) -> (i64, i64) {
-    for noun in 0..=99 {
-        for verb in 0..=99 {
-            if run_with_noun_and_verb(program, noun, verb) == target {
-                return (noun, verb);
-            }
-        }
-    }
-    panic!("no solution found");
+    // the result is linear in the noun and verb, so solve for them rather than trying every pair
+    let mut symbolic = Symbolic::new(program);
+    let noun = symbolic.symbol_at(1, 0..=99);
+    let verb = symbolic.symbol_at(2, 0..=99);
+    let goal = Goal::Halt {
+        address: 0,
+        value: target,
+    };
+    let solution = symbolic.solve(goal).unwrap().expect("no solution found");
+    (solution[noun], solution[verb])
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use advent_of_code_2019::input::parse_list;
 
     fn run_and_return(program: Vec<i64>) -> Vec<i64> {
         let mut machine = ProgramMachine::new(program, vec![]);
@@ -63,4 +67,11 @@ mod tests {
             vec!(30, 1, 1, 4, 2, 5, 6, 0, 99)
         );
     }
+
+    #[test]
+    fn test_part2() {
+        let input: Vec<i64> = parse_list(include_str!("input"), ',');
+        let (noun, verb) = solve_for(&input, 19690720);
+        assert_eq!(run_with_noun_and_verb(&input, noun, verb), 19690720);
+    }
 }
@@ -3,10 +3,11 @@ use std::iter::FromIterator;
 
 use pathfinding::directed::bfs::bfs;
 
+use advent_of_code_2019::input::input_from_args;
 use advent_of_code_2019::vector2d::Vector2D;
 
 fn main() {
-    let maze: Maze = parse_input(include_str!("input"));
+    let maze: Maze = parse_input(&input_from_args(include_str!("input")));
     println!("Answer to part 1: {}", part1(&maze));
     println!("Answer to part 2: {}", part2(&maze));
 }
@@ -1,15 +1,15 @@
-use advent_of_code_2019::input::parse_list;
-use advent_of_code_2019::intcode::{Machine, ProgramMachine};
+use advent_of_code_2019::input::intcode_from_args;
+use advent_of_code_2019::intcode::ascii::{AsciiError, AsciiMachine};
+use advent_of_code_2019::intcode::ProgramMachine;
 
 fn main() {
-    let program: Vec<i64> = parse_list(include_str!("input"), ',');
+    let program: Vec<i64> = intcode_from_args(include_str!("input"));
     println!("Answer to part 1: {}", part1(&program));
     println!("Answer to part 2: {}", part2(&program));
 }
 
-fn part1(program: &Vec<i64>) -> i64 {
-    let mut machine = ProgramMachine::new(program.clone(), vec![]);
-    assert_eq!(machine.read_line(), "Input instructions:");
+fn part1(program: &[i64]) -> i64 {
+    let mut script = Vec::new();
 
     // Jump if there is a hole at A, B or C and ground at D
     // J = (!A | !B | !C) & D
@@ -19,29 +19,21 @@ fn part1(program: &Vec<i64>) -> i64 {
     // J = J & B
     // J = J & C
     // (J == A & B & C)
-    machine.add_line("OR A J");
-    machine.add_line("AND B J");
-    machine.add_line("AND C J");
+    script.push("OR A J");
+    script.push("AND B J");
+    script.push("AND C J");
     // J = !J
     // J = J & D
     // (J == !(A & B & C) & D)
-    machine.add_line("NOT J J");
-    machine.add_line("AND D J");
-    machine.add_line("WALK");
+    script.push("NOT J J");
+    script.push("AND D J");
+    script.push("WALK");
 
-    assert_eq!(machine.read_line(), "");
-    assert_eq!(machine.read_line(), "Walking...");
-    assert_eq!(machine.read_line(), "");
-
-    let output = machine.run_to_output().unwrap();
-    assert!(output > 255);
-
-    output
+    run_springscript(program, &script).unwrap_or_else(|err| panic!("{}", err))
 }
 
-fn part2(program: &Vec<i64>) -> i64 {
-    let mut machine = ProgramMachine::new(program.clone(), vec![]);
-    assert_eq!(machine.read_line(), "Input instructions:");
+fn part2(program: &[i64]) -> i64 {
+    let mut script = Vec::new();
 
     // If there's ground at D (=4) but a hole at E (=5) and H (=8), then we won't be able
     // to move or jump from D.
@@ -53,30 +45,61 @@ fn part2(program: &Vec<i64>) -> i64 {
     // J = J & B
     // J = J & C
     // (J == A & B & C)
-    machine.add_line("OR A J");
-    machine.add_line("AND B J");
-    machine.add_line("AND C J");
+    script.push("OR A J");
+    script.push("AND B J");
+    script.push("AND C J");
     // J = !J
     // J = J & D
     // (J == !(A & B & C) & D)
-    machine.add_line("NOT J J");
-    machine.add_line("AND D J");
+    script.push("NOT J J");
+    script.push("AND D J");
     // T = E
     // T = T | H
     // (T == E | H)
-    machine.add_line("OR E T");
-    machine.add_line("OR H T");
+    script.push("OR E T");
+    script.push("OR H T");
     // J = J & T
     // (J == !(A & B & C) & D & (E | H))
-    machine.add_line("AND T J");
-    machine.add_line("RUN");
+    script.push("AND T J");
+    script.push("RUN");
+
+    run_springscript(program, &script).unwrap_or_else(|err| panic!("{}", err))
+}
+
+// Returns the hull damage, or an error showing how the droid fell into space.
+fn run_springscript(program: &[i64], script: &[&str]) -> Result<i64, AsciiError> {
+    let mut machine = AsciiMachine::new(ProgramMachine::new(program.to_vec(), vec![]));
+    machine.expect_prompt("Input instructions:")?;
+    for line in script {
+        machine.send_line(line);
+    }
+    let (_, damage) = machine.expect_value()?;
+    Ok(damage)
+}
 
-    assert_eq!(machine.read_line(), "");
-    assert_eq!(machine.read_line(), "Running...");
-    assert_eq!(machine.read_line(), "");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use advent_of_code_2019::input::parse_list;
+    use advent_of_code_2019::intcode::ascii::Event;
 
-    let output = machine.run_to_output().unwrap();
-    assert!(output > 255);
+    #[test]
+    fn test_part1() {
+        let program: Vec<i64> = parse_list(include_str!("input"), ',');
+        assert_eq!(part1(&program), 19357534);
+    }
 
-    output
+    #[test]
+    fn test_falling_droid() {
+        let program: Vec<i64> = parse_list(include_str!("input"), ',');
+        match run_springscript(&program, &["WALK"]) {
+            Err(AsciiError::Unexpected { event, output, .. }) => {
+                assert_eq!(event, Event::Halt);
+                assert!(output
+                    .iter()
+                    .any(|line| line.contains("Didn't make it across")));
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
 }
@@ -2,12 +2,12 @@ use std::str::FromStr;
 
 use regex::Regex;
 
-use advent_of_code_2019::input::parse_list;
+use advent_of_code_2019::input::{input_from_args, parse_list};
 use lazy_static::lazy_static;
 use modinverse::modinverse;
 
 fn main() {
-    let input: Vec<Shuffle> = parse_input(include_str!("input"));
+    let input: Vec<Shuffle> = parse_input(&input_from_args(include_str!("input")));
     println!("Answer to part 1: {}", part1(&input));
     println!("Answer to part 2: {}", part2(&input));
 }
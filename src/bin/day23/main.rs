@@ -1,114 +1,80 @@
-use advent_of_code_2019::input::parse_list;
-use advent_of_code_2019::intcode::{Machine, ProgramMachine, StepResult};
-use std::collections::VecDeque;
+use advent_of_code_2019::input::intcode_from_args;
+use advent_of_code_2019::intcode::packet::{
+    Action, Config, FirstPacket, Nat, Packet, PacketNetwork,
+};
+use advent_of_code_2019::intcode::ProgramMachine;
+
+const NAT_ADDRESS: i64 = 255;
 
 fn main() {
-    let program: Vec<i64> = parse_list(include_str!("input"), ',');
+    let program: Vec<i64> = intcode_from_args(include_str!("input"));
     println!("Answer to part 1: {}", part1(&program));
     println!("Answer to part 2: {}", part2(&program));
 }
 
-#[derive(Debug)]
-struct Message {
-    address: i64,
-    x: i64,
-    y: i64,
+fn boot(program: &[i64]) -> PacketNetwork<ProgramMachine> {
+    PacketNetwork::new(Config::default(), |_| {
+        ProgramMachine::new(program.to_vec(), vec![])
+    })
 }
 
-struct Computer {
-    address: i64,
-    machine: ProgramMachine,
-    queue: VecDeque<Message>,
-    waiting_for_input: bool,
+// Remembers the last packet sent to it, and sends it to computer 0 when the network is idle.
+#[derive(Default)]
+struct Restarter {
+    packet: Option<Packet>,
+    last_y: Option<i64>,
 }
 
-impl Computer {
-    fn new(address: i64, program: Vec<i64>) -> Computer {
-        Computer {
-            address,
-            machine: ProgramMachine::new(program, vec![address]),
-            queue: VecDeque::new(),
-            waiting_for_input: false,
-        }
+impl Nat for Restarter {
+    fn receive(&mut self, packet: &Packet) -> Action {
+        self.packet = Some(packet.clone());
+        Action::Continue
     }
 
-    fn step(&mut self) -> Option<Message> {
-        match self.machine.step() {
-            StepResult::Ok => None,
-            StepResult::NeedInput => {
-                if let Some(message) = self.queue.pop_front() {
-                    self.waiting_for_input = false;
-                    self.machine.add_input(message.x);
-                    self.machine.add_input(message.y);
-                } else {
-                    self.waiting_for_input = true;
-                    self.machine.add_input(-1);
-                }
-                None
-            }
-            StepResult::Output(address) => {
-                self.waiting_for_input = false;
-                let x = self.machine.run_to_output().unwrap();
-                let y = self.machine.run_to_output().unwrap();
-                Some(Message { address, x, y })
-            }
-            StepResult::Halt => panic!("computer {} halted unexpectedly", self.address),
+    fn idle(&mut self) -> Action {
+        let packet = self
+            .packet
+            .as_ref()
+            .expect("network is idle, but the NAT has nothing to send");
+        let y = packet.payload[1];
+        if self.last_y == Some(y) {
+            return Action::Stop(y);
         }
+        self.last_y = Some(y);
+        Action::Send(Packet {
+            source: NAT_ADDRESS,
+            destination: 0,
+            payload: packet.payload.clone(),
+        })
     }
+}
 
-    fn send(&mut self, message: Message) {
-        self.queue.push_back(message);
-    }
+fn part1(program: &[i64]) -> i64 {
+    let mut network = boot(program);
+    network.attach(NAT_ADDRESS, FirstPacket);
+    network.run().unwrap_or_else(|err| panic!("{}", err))
+}
 
-    fn is_idle(&self) -> bool {
-        self.waiting_for_input && self.queue.is_empty()
-    }
+fn part2(program: &[i64]) -> i64 {
+    let mut network = boot(program);
+    network.attach(NAT_ADDRESS, Restarter::default());
+    network.run().unwrap_or_else(|err| panic!("{}", err))
 }
 
-fn part1(program: &Vec<i64>) -> i64 {
-    let mut computers = (0..50)
-        .map(|address| Computer::new(address, program.clone()))
-        .collect::<Vec<_>>();
-    loop {
-        for i in 0..computers.len() {
-            if let Some(message) = computers[i].step() {
-                if message.address == 255 {
-                    return message.y;
-                } else {
-                    computers[message.address as usize].send(message);
-                }
-            }
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use advent_of_code_2019::input::parse_list;
+
+    #[test]
+    fn test_part1() {
+        let program: Vec<i64> = parse_list(include_str!("input"), ',');
+        assert_eq!(part1(&program), 22829);
     }
-}
 
-fn part2(program: &Vec<i64>) -> i64 {
-    let mut computers = (0..50)
-        .map(|address| Computer::new(address, program.clone()))
-        .collect::<Vec<_>>();
-    let mut nat_message: Option<Message> = None;
-    let mut last_nat_y: Option<i64> = None;
-    loop {
-        for i in 0..computers.len() {
-            if let Some(message) = computers[i].step() {
-                if message.address == 255 {
-                    nat_message = Some(message);
-                } else {
-                    computers[message.address as usize].send(message);
-                }
-            }
-        }
-        let all_idle = computers.iter().all(|x| x.is_idle());
-        if all_idle {
-            if let Some(message) = nat_message.take() {
-                if let Some(last_nat_y) = last_nat_y {
-                    if last_nat_y == message.y {
-                        return message.y;
-                    }
-                }
-                last_nat_y = Some(message.y);
-                computers[0].send(message);
-            }
-        }
+    #[test]
+    fn test_part2() {
+        let program: Vec<i64> = parse_list(include_str!("input"), ',');
+        assert_eq!(part2(&program), 15678);
     }
 }
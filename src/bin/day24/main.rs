@@ -1,7 +1,9 @@
 use std::collections::{HashMap, HashSet};
 
+use advent_of_code_2019::input::input_from_args;
+
 fn main() {
-    let grid: Grid = Grid::parse(include_str!("input"));
+    let grid: Grid = Grid::parse(&input_from_args(include_str!("input")));
     println!("Answer to part 1: {}", part1(grid.clone()));
     println!("Answer to part 2: {}", part2(grid.clone()));
 }
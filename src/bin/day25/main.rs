@@ -3,13 +3,22 @@ use std::io::{stdin, BufRead};
 
 use regex::Regex;
 
-use advent_of_code_2019::input::parse_list;
-use advent_of_code_2019::intcode::{Machine, ProgramMachine};
+use advent_of_code_2019::input::{exit_with, load_intcode, source_from_args, Source};
+use advent_of_code_2019::intcode::{Budget, Machine, Outcome, ProgramMachine};
 use advent_of_code_2019::vector2d::Vector2D;
 use lazy_static::lazy_static;
 
+// taking an item normally needs a few thousand instructions
+const TAKE_BUDGET: u64 = 1_000_000;
+
 fn main() {
-    let program: Vec<i64> = parse_list(include_str!("input"), ',');
+    let source = match source_from_args() {
+        // commands are read from stdin
+        Some(Source::Stdin) => exit_with("day25 can't read the program from stdin"),
+        Some(source) => source,
+        None => Source::Text(include_str!("input").to_string()),
+    };
+    let program = load_intcode(&source).unwrap_or_else(|err| exit_with(err));
     play(&program);
 }
 
@@ -97,12 +106,14 @@ fn print_grid(grid: &Grid, droid_pos: &Vector2D) {
     }
 }
 
-fn read_line() -> String {
+// The next command, or `None` once stdin is closed.
+fn read_line() -> Option<String> {
     let stdin = stdin();
     let mut lock = stdin.lock();
     let mut line = String::new();
     match lock.read_line(&mut line) {
-        Ok(_) => line.trim().into(),
+        Ok(0) => None,
+        Ok(_) => Some(line.trim().into()),
         Err(err) => panic!("input error: {}", err),
     }
 }
@@ -183,7 +194,6 @@ fn go_to_checkpoint(
 ) -> bool {
     lazy_static! {
         static ref BAD_ITEMS: HashSet<&'static str> = vec![
-            "infinite loop",
             "molten lava",
             "escape pod",
             "giant electromagnet",
@@ -205,8 +215,16 @@ fn go_to_checkpoint(
             if !BAD_ITEMS.contains(&item[..]) {
                 let command = format!("take {}", item);
                 println!("{}", &command);
-                machine.add_line(&command);
-                print!("{}", machine.read_string());
+                // some items make the program hang, so try them on a copy first
+                let mut attempt = machine.clone();
+                attempt.add_line(&command);
+                match attempt.read_string_within(&mut Budget::steps(TAKE_BUDGET)) {
+                    Outcome::Done(output) => {
+                        print!("{}", output);
+                        *machine = attempt;
+                    }
+                    Outcome::BudgetExhausted(_) => println!("Not taking {}, it hangs.", item),
+                }
             }
         }
     }
@@ -293,7 +311,10 @@ fn play(program: &Vec<i64>) {
         // Print the command's output
         println!("{}", output.trim());
 
-        let input = read_line();
+        let input = match read_line() {
+            Some(input) => input,
+            None => break,
+        };
         machine.add_line(&input);
 
         // Update position
@@ -303,3 +324,27 @@ fn play(program: &Vec<i64>) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use advent_of_code_2019::input::parse_list;
+
+    #[test]
+    fn test_go_to_checkpoint() {
+        let program: Vec<i64> = parse_list(include_str!("input"), ',');
+        let mut machine = ProgramMachine::new(program, vec![]);
+        let mut grid: Grid = HashMap::new();
+        let mut pos = Vector2D::zero();
+        assert!(go_to_checkpoint(
+            &mut machine,
+            &mut grid,
+            &mut pos,
+            Direction::North
+        ));
+        machine.add_line("inv");
+        let inventory = parse_inventory(&machine.read_string());
+        assert!(!inventory.is_empty());
+        assert!(!inventory.iter().any(|item| item == "infinite loop"));
+    }
+}
@@ -3,11 +3,11 @@ use std::collections::HashSet;
 use std::iter::FromIterator;
 use std::str::FromStr;
 
-use advent_of_code_2019::input::parse_list;
+use advent_of_code_2019::input::{input_from_args, parse_list};
 use advent_of_code_2019::vector2d::Vector2D;
 
 fn main() {
-    let paths: Vec<Path> = parse_list(include_str!("input"), '\n');
+    let paths: Vec<Path> = parse_list(&input_from_args(include_str!("input")), '\n');
     assert_eq!(paths.len(), 2);
 
     let trace1 = paths[0].trace();
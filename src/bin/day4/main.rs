@@ -1,12 +1,25 @@
 use std::cmp::Ordering;
 
+use advent_of_code_2019::input::input_from_args;
+
 fn main() {
-    let start = 372037;
-    let end = 905157;
+    // the puzzle input is a range, like 372037-905157
+    let (start, end) = parse_range(&input_from_args("372037-905157"));
     println!("Answer to part 1: {}", part1(start, end));
     println!("Answer to part 2: {}", part2(start, end));
 }
 
+fn parse_range(input: &str) -> (u32, u32) {
+    let mut bounds = input
+        .trim()
+        .split('-')
+        .map(|x| x.parse().expect("invalid input"));
+    match (bounds.next(), bounds.next(), bounds.next()) {
+        (Some(start), Some(end), None) => (start, end),
+        _ => panic!("invalid input"),
+    }
+}
+
 fn part1(start: u32, end: u32) -> usize {
     solve(start, end, is_password_part1)
 }
@@ -1,8 +1,8 @@
-use advent_of_code_2019::input::parse_list;
+use advent_of_code_2019::input::intcode_from_args;
 use advent_of_code_2019::intcode::*;
 
 fn main() {
-    let input: Vec<i64> = parse_list(include_str!("input"), ',');
+    let input: Vec<i64> = intcode_from_args(include_str!("input"));
     println!("Answer to part 1: {}", part1(&input));
     println!("Answer to part 2: {}", part2(&input));
 }
@@ -1,7 +1,9 @@
 use std::collections::HashMap;
 
+use advent_of_code_2019::input::input_from_args;
+
 fn main() {
-    let input = parse_input();
+    let input = parse_input(&input_from_args(include_str!("input")));
     println!("Answer to part 1: {}", input.total_orbits());
     println!(
         "Answer to part 2: {}",
@@ -16,12 +18,8 @@ fn parse_orbit(s: &str) -> Orbit {
     (parts[0].into(), parts[1].into())
 }
 
-fn parse_input() -> OrbitMap {
-    let orbits = include_str!("input")
-        .trim()
-        .split('\n')
-        .map(parse_orbit)
-        .collect();
+fn parse_input(input: &str) -> OrbitMap {
+    let orbits = input.trim().split('\n').map(parse_orbit).collect();
     OrbitMap::from_orbits(orbits)
 }
 
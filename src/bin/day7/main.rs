@@ -1,13 +1,13 @@
 use std::cmp::max;
-use std::collections::VecDeque;
 
 use permutohedron::Heap;
 
-use advent_of_code_2019::input::parse_list;
+use advent_of_code_2019::input::intcode_from_args;
+use advent_of_code_2019::intcode::threads::Runtime;
 use advent_of_code_2019::intcode::*;
 
 fn main() {
-    let input: Vec<i64> = parse_list(include_str!("input"), ',');
+    let input: Vec<i64> = intcode_from_args(include_str!("input"));
     println!("Answer to part 1: {}", part1(&input));
     println!("Answer to part 2: {}", part2(&input));
 }
@@ -33,35 +33,24 @@ fn part1(program: &Vec<i64>) -> i64 {
 }
 
 fn run_feedback_loop(program: &Vec<i64>, phase_settings: &Vec<i64>) -> i64 {
-    let machines: Vec<Box<dyn Machine>> = phase_settings
+    let mut runtime = Runtime::new(phase_settings.len());
+    let amplifiers = phase_settings
         .iter()
-        .map(|&setting| {
-            let machine = ProgramMachine::new(program.clone(), vec![setting]);
-            Box::new(machine) as Box<dyn Machine>
-        })
-        .collect();
-    let mut chain = make_chain(VecDeque::from(machines));
+        .map(|&setting| runtime.add(ProgramMachine::new(program.clone(), vec![setting])))
+        .collect::<Vec<_>>();
+    for (index, &amplifier) in amplifiers.iter().enumerate() {
+        runtime.connect(amplifier, amplifiers[(index + 1) % amplifiers.len()]);
+    }
 
     // To start the process, a 0 signal is sent to amplifier A's input exactly once.
-    let mut signal = 0;
-    chain.add_input(signal);
+    runtime.machine_mut(amplifiers[0]).add_input(0);
 
-    loop {
-        match chain.step() {
-            StepResult::Ok | StepResult::NeedInput => {
-                // keep going
-            }
-            StepResult::Output(value) => {
-                chain.add_input(value);
-                signal = value;
-            }
-            StepResult::Halt => {
-                break;
-            }
-        };
-    }
-
-    signal
+    let mut outputs = runtime.run().expect("amplifiers failed");
+    // The last output of the last amplifier is the signal sent to the thrusters.
+    outputs
+        .pop()
+        .and_then(|output| output.last().copied())
+        .expect("expected an output")
 }
 
 fn part2(program: &Vec<i64>) -> i64 {
@@ -1,5 +1,7 @@
+use advent_of_code_2019::input::input_from_args;
+
 fn main() {
-    let input: Vec<Color> = parse_input(include_str!("input"));
+    let input: Vec<Color> = parse_input(&input_from_args(include_str!("input")));
     let width = 25;
     let height = 6;
     println!("Answer to part 1: {}", part1(&input, width, height));
@@ -1,8 +1,8 @@
-use advent_of_code_2019::input::parse_list;
+use advent_of_code_2019::input::intcode_from_args;
 use advent_of_code_2019::intcode::*;
 
 fn main() {
-    let input: Vec<i64> = parse_list(include_str!("input"), ',');
+    let input: Vec<i64> = intcode_from_args(include_str!("input"));
     println!("Answer to part 1: {}", part1(&input));
     println!("Answer to part 2: {}", part2(&input));
 }
@@ -49,4 +49,27 @@ mod tests {
         let output = ProgramMachine::new(program, vec![]).run();
         assert_eq!(output, vec![large_number]);
     }
+
+    #[test]
+    fn test_overflow() {
+        let program = vec![
+            1102, 34915192, 34915192, 11, 1002, 11, 34915192, 11, 4, 11, 99, 0,
+        ];
+        assert_eq!(
+            ProgramMachine::new(program.clone(), vec![]).try_run(),
+            Err(IntcodeError::Overflow {
+                pc: 4,
+                instruction: 1002
+            })
+        );
+        let output = BigMachine::from(program).try_run_big().unwrap();
+        assert_eq!(output[0].to_string(), "42564085191697926757888");
+    }
+
+    #[test]
+    fn test_large_number_big() {
+        let program = vec![104, 1125899906842624, 99];
+        let output = BigMachine::from(program).run();
+        assert_eq!(output, vec![1125899906842624]);
+    }
 }
@@ -0,0 +1,94 @@
+// Generated by intcode-aot. Do not edit.
+
+use advent_of_code_2019::intcode::aot::Runtime;
+use advent_of_code_2019::intcode::{IntcodeError, Machine, StepResult};
+
+const PROGRAM: &[i64] = &[
+    3, 18, 4, 18, 1001, 18, -1, 18, 1005, 18, 2, 1101,
+    104, 0, 15, 4, 7, 99, 0,
+];
+
+const CODE: &[(usize, usize)] = &[
+    (0, 18),
+];
+
+#[derive(Debug, Clone)]
+pub struct Countdown(Runtime);
+
+impl Countdown {
+    pub fn new(input: Vec<i64>) -> Countdown {
+        Countdown(Runtime::new(PROGRAM, CODE, input))
+    }
+
+    #[allow(dead_code)]
+    pub fn runtime(&self) -> &Runtime {
+        &self.0
+    }
+}
+
+impl Machine for Countdown {
+    fn add_input(&mut self, value: i64) {
+        self.0.add_input(value);
+    }
+
+    fn try_step(&mut self) -> Result<StepResult, IntcodeError> {
+        if self.0.is_interpreted() {
+            return self.0.try_step();
+        }
+        match self.0.pc() {
+            0 => self.block_0(),
+            2 => self.block_2(),
+            4 => self.block_4(),
+            11 => self.block_11(),
+            17 => self.block_17(),
+            _ => self.0.try_step(),
+        }
+    }
+}
+
+impl Countdown {
+    fn block_0(&mut self) -> Result<StepResult, IntcodeError> {
+        // 0: in [18]
+        let value = match self.0.input(0) {
+            Some(value) => value,
+            None => return Ok(StepResult::NeedInput),
+        };
+        if self.0.write(0, 18, value)? {
+            return Ok(self.0.fall_back(2));
+        }
+        Ok(self.0.goto(2))
+    }
+
+    fn block_2(&mut self) -> Result<StepResult, IntcodeError> {
+        // 2: out [18]
+        Ok(self.0.output(4, self.0.read(2, 18)?))
+    }
+
+    fn block_4(&mut self) -> Result<StepResult, IntcodeError> {
+        // 4: add [18], #-1, [18]
+        let value = self.0.add(4, self.0.read(4, 18)?, -1)?;
+        if self.0.write(4, 18, value)? {
+            return Ok(self.0.fall_back(8));
+        }
+        // 8: jt [18], #2
+        if self.0.read(8, 18)? != 0 {
+            return self.0.jump(8, 2);
+        }
+        Ok(self.0.goto(11))
+    }
+
+    fn block_11(&mut self) -> Result<StepResult, IntcodeError> {
+        // 11: add #104, #0, [15]
+        let value = self.0.add(11, 104, 0)?;
+        if self.0.write(11, 15, value)? {
+            return Ok(self.0.fall_back(15));
+        }
+        // 15: out [7]
+        Ok(self.0.output(17, self.0.read(15, 7)?))
+    }
+
+    fn block_17(&mut self) -> Result<StepResult, IntcodeError> {
+        // 17: hlt
+        Ok(self.0.halt(17))
+    }
+}
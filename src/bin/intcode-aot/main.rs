@@ -0,0 +1,69 @@
+use std::env;
+use std::fs;
+
+use advent_of_code_2019::input::{load_intcode, Source};
+use advent_of_code_2019::intcode::aot::translate;
+
+#[cfg(test)]
+#[rustfmt::skip]
+mod countdown;
+
+const USAGE: &str = "usage: intcode-aot <program file> <type name> [output file]";
+
+// Translates an Intcode program into a Rust module, which a binary can include with `mod`.
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if args.len() < 2 || args.len() > 3 {
+        panic!("{}", USAGE);
+    }
+    let program = load_intcode(&Source::from_arg(&args[0])).unwrap_or_else(|err| panic!("{}", err));
+    let code = translate(&program, &args[1]);
+    match args.get(2) {
+        Some(path) => fs::write(path, code).expect("failed to write module"),
+        None => print!("{}", code),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use advent_of_code_2019::intcode::asm::assemble;
+    use advent_of_code_2019::intcode::{Machine, ProgramMachine, StepResult};
+    use countdown::Countdown;
+
+    // counts down from the input, then patches the mode of its last output to print 7
+    const SOURCE: &str = "
+            in [counter]
+        loop:
+            out [counter]
+            add [counter], #-1, [counter]
+            jt [counter], #loop
+            add #104, #0, [patch]
+        patch:
+            out [7]
+            hlt
+        counter: db 0
+    ";
+
+    #[test]
+    fn test_countdown_is_up_to_date() {
+        let program = assemble(SOURCE).unwrap();
+        assert_eq!(
+            translate(&program, "Countdown"),
+            include_str!("countdown.rs")
+        );
+    }
+
+    #[test]
+    fn test_same_as_interpreter() {
+        let program = assemble(SOURCE).unwrap();
+        let mut interpreter = ProgramMachine::new(program, vec![]);
+        let mut translated = Countdown::new(vec![]);
+        assert!(matches!(translated.step(), StepResult::NeedInput));
+        interpreter.add_input(3);
+        translated.add_input(3);
+        assert_eq!(translated.run(), vec![3, 2, 1, 7]);
+        assert_eq!(interpreter.run(), vec![3, 2, 1, 7]);
+        assert!(translated.runtime().is_interpreted());
+    }
+}
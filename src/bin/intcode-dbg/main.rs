@@ -0,0 +1,613 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::env;
+use std::fs::{self, File};
+use std::io::{stdin, stdout, BufRead, BufWriter, Write};
+
+use advent_of_code_2019::input::{exit_with, load_intcode, Source};
+use advent_of_code_2019::intcode::cfg;
+use advent_of_code_2019::intcode::trace::TracingMachine;
+use advent_of_code_2019::intcode::{
+    Instruction, IntcodeError, Machine, Patches, ProgramMachine, StepResult,
+};
+
+const OUTPUT_HISTORY: usize = 4096;
+const LIST_LENGTH: usize = 10;
+const EXAMINE_PER_LINE: usize = 8;
+// so a far-away write is an error, rather than an allocation that ends the session
+const MEMORY_LIMIT: usize = 1 << 24;
+
+const HELP: &str = "\
+commands:
+  s, step [n]          execute n instructions (default 1)
+  c, continue          run until a breakpoint, watchpoint, input request or halt
+  b, break <addr>      set a breakpoint on pc
+  d, delete <addr>     delete a breakpoint or watchpoint
+  w, watch <addr> [r|w|rw]
+                       stop before an instruction reads or writes memory
+  i, info              show breakpoints and watchpoints
+  r, regs              show pc, relative base and pending input
+  l, list [addr] [n]   disassemble n instructions (default: from pc)
+  x <addr> [n]         examine n memory cells
+  set <addr> <value>   edit a memory cell
+  pc <value>           set the program counter
+  rb [value]           show or set the relative base
+  in <value>...        queue input values
+  line <text>          queue a line of ASCII input
+  out                  show recent output
+  profile              show where the program spent its time
+  trace <file>|off     write every executed instruction to a file
+  cfg <file>           write the control-flow graph of memory to a Graphviz file
+  patches [on|off]     report writes to instructions that already ran, or summarise them
+  q, quit              exit
+addresses can be absolute or relative to the base, e.g. rb+3";
+
+fn main() {
+    let path = env::args()
+        .nth(1)
+        .unwrap_or_else(|| exit_with("usage: intcode-dbg <program file>"));
+    let source = match Source::from_arg(&path) {
+        // commands are read from stdin
+        Source::Stdin => exit_with("intcode-dbg can't read the program from stdin"),
+        source => source,
+    };
+    let program = load_intcode(&source).unwrap_or_else(|err| exit_with(err));
+    let mut debugger = Debugger::new(ProgramMachine::new(program, vec![]));
+
+    println!("{}", debugger.location());
+    let stdin = stdin();
+    let mut last_command = String::new();
+    loop {
+        print!("(dbg) ");
+        stdout().flush().unwrap();
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap() == 0 {
+            break;
+        }
+        let mut command = line.trim().to_string();
+        if command.is_empty() {
+            // repeat the previous command, like gdb
+            command = last_command.clone();
+        }
+        if command == "q" || command == "quit" {
+            break;
+        }
+        match debugger.execute(&command) {
+            Ok(output) => print!("{}", output),
+            Err(err) => println!("error: {}", err),
+        }
+        last_command = command;
+    }
+
+    let profile = debugger.tracer.profile();
+    if profile.steps > 0 {
+        print!("{}", profile);
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Access {
+    Read,
+    Write,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Watch {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl Watch {
+    fn parse(s: &str) -> Result<Watch, String> {
+        match s {
+            "r" => Ok(Watch::Read),
+            "w" => Ok(Watch::Write),
+            "rw" => Ok(Watch::ReadWrite),
+            _ => Err(format!("invalid watch kind {}", s)),
+        }
+    }
+
+    fn matches(self, access: Access) -> bool {
+        matches!(
+            (self, access),
+            (Watch::ReadWrite, _) | (Watch::Read, Access::Read) | (Watch::Write, Access::Write)
+        )
+    }
+
+    fn to_str(self) -> &'static str {
+        match self {
+            Watch::Read => "r",
+            Watch::Write => "w",
+            Watch::ReadWrite => "rw",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Stop {
+    Steps,
+    Breakpoint,
+    Watchpoint(Access, usize),
+    NeedInput,
+    Halt,
+    Error(IntcodeError),
+}
+
+struct Debugger {
+    tracer: TracingMachine<BufWriter<File>>,
+    breakpoints: BTreeSet<usize>,
+    watchpoints: BTreeMap<usize, Watch>,
+    output: VecDeque<i64>,
+    new_output: Vec<i64>,
+    // patches that were already shown
+    reported_patches: usize,
+}
+
+impl Debugger {
+    fn new(mut machine: ProgramMachine) -> Debugger {
+        machine.set_memory_limit(Some(MEMORY_LIMIT));
+        Debugger {
+            tracer: TracingMachine::new(machine),
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeMap::new(),
+            output: VecDeque::new(),
+            new_output: Vec::new(),
+            reported_patches: 0,
+        }
+    }
+
+    fn machine(&self) -> &ProgramMachine {
+        self.tracer.machine()
+    }
+
+    fn machine_mut(&mut self) -> &mut ProgramMachine {
+        self.tracer.machine_mut()
+    }
+
+    fn accesses(&self) -> Vec<(Access, usize)> {
+        let instruction = match self.machine().instruction() {
+            Ok(instruction) => instruction,
+            Err(_) => return vec![],
+        };
+        let base = self.machine().base();
+        let reads = instruction
+            .inputs()
+            .into_iter()
+            .filter_map(|value| value.address(base))
+            .map(|address| (Access::Read, address));
+        let writes = instruction
+            .output()
+            .and_then(|result| result.address(base))
+            .map(|address| (Access::Write, address));
+        reads
+            .chain(writes)
+            .filter(|&(_, address)| address >= 0)
+            .map(|(access, address)| (access, address as usize))
+            .collect()
+    }
+
+    fn check_watchpoints(&self) -> Option<Stop> {
+        self.accesses().into_iter().find_map(|(access, address)| {
+            match self.watchpoints.get(&address) {
+                Some(watch) if watch.matches(access) => Some(Stop::Watchpoint(access, address)),
+                _ => None,
+            }
+        })
+    }
+
+    fn step_once(&mut self) -> Option<Stop> {
+        match self.tracer.try_step() {
+            Ok(StepResult::Ok) => None,
+            Ok(StepResult::Output(value)) => {
+                if self.output.len() == OUTPUT_HISTORY {
+                    self.output.pop_front();
+                }
+                self.output.push_back(value);
+                self.new_output.push(value);
+                None
+            }
+            Ok(StepResult::NeedInput) => Some(Stop::NeedInput),
+            Ok(StepResult::Halt) => Some(Stop::Halt),
+            Err(err) => Some(Stop::Error(err)),
+        }
+    }
+
+    fn run(&mut self, max_steps: Option<usize>) -> Stop {
+        for steps in 0.. {
+            if steps > 0 {
+                if self.breakpoints.contains(&self.machine().pc()) {
+                    return Stop::Breakpoint;
+                }
+                if let Some(stop) = self.check_watchpoints() {
+                    return stop;
+                }
+            }
+            if max_steps == Some(steps) {
+                return Stop::Steps;
+            }
+            if let Some(stop) = self.step_once() {
+                return stop;
+            }
+        }
+        unreachable!()
+    }
+
+    fn location(&self) -> String {
+        match self.machine().instruction() {
+            Ok(instruction) => format!("=> {:>6}: {}", self.machine().pc(), instruction),
+            Err(err) => format!("=> {:>6}: {}", self.machine().pc(), err),
+        }
+    }
+
+    fn describe(&self, stop: Stop) -> String {
+        let reason = match stop {
+            Stop::Steps => None,
+            Stop::Breakpoint => Some(format!("breakpoint at {}", self.machine().pc())),
+            Stop::Watchpoint(Access::Read, address) => Some(format!("read of [{}]", address)),
+            Stop::Watchpoint(Access::Write, address) => Some(format!("write to [{}]", address)),
+            Stop::NeedInput => Some("waiting for input".into()),
+            Stop::Halt => Some("halted".into()),
+            Stop::Error(err) => Some(format!("error: {}", err)),
+        };
+        let mut result = match reason {
+            Some(reason) => format!("{}\n{}\n", reason, self.location()),
+            None => format!("{}\n", self.location()),
+        };
+        if let Some(summary) = self.machine().patches().and_then(Patches::halt_summary) {
+            if let Stop::Halt = stop {
+                result += &summary.to_string();
+            }
+        }
+        result
+    }
+
+    fn parse_address(&self, s: &str) -> Result<usize, String> {
+        let address = if let Some(offset) = s.strip_prefix("rb") {
+            let offset = offset.strip_prefix('+').unwrap_or(offset);
+            let offset = parse_number(offset)?;
+            self.machine()
+                .base()
+                .checked_add(offset)
+                .ok_or_else(|| format!("address rb+{} overflows", offset))?
+        } else {
+            parse_number(s)?
+        };
+        if address < 0 {
+            return Err(format!("negative address {}", address));
+        }
+        Ok(address as usize)
+    }
+
+    fn list(&self, start: usize, count: usize) -> String {
+        let program = self.machine().program();
+        let mut result = String::new();
+        let mut address = start;
+        for _ in 0..count {
+            let marker = match (
+                address == self.machine().pc(),
+                self.breakpoints.contains(&address),
+            ) {
+                (true, true) => "*>",
+                (true, false) => "=>",
+                (false, true) => "* ",
+                (false, false) => "  ",
+            };
+            match Instruction::try_parse(program, address) {
+                Ok(instruction) => {
+                    result += &format!("{} {:>6}: {}\n", marker, address, instruction);
+                    address += instruction.length();
+                }
+                Err(IntcodeError::PcOutOfRange { .. }) => break,
+                Err(_) => {
+                    result += &format!("{} {:>6}: db {}\n", marker, address, program[address]);
+                    address += 1;
+                }
+            }
+        }
+        result
+    }
+
+    fn execute(&mut self, command: &str) -> Result<String, String> {
+        let mut words = command.split_whitespace();
+        let name = match words.next() {
+            Some(name) => name,
+            None => return Ok(String::new()),
+        };
+        let args = words.collect::<Vec<_>>();
+        let mut result = match (name, &args[..]) {
+            ("s", []) | ("step", []) => self.describe_run(Some(1)),
+            ("s", [n]) | ("step", [n]) => self.describe_run(Some(parse_count(n)?)),
+            ("c", []) | ("continue", []) => self.describe_run(None),
+            ("b", [address]) | ("break", [address]) => {
+                let address = self.parse_address(address)?;
+                self.breakpoints.insert(address);
+                format!("breakpoint at {}\n", address)
+            }
+            ("w", [address]) | ("watch", [address]) => self.watch(address, Watch::ReadWrite)?,
+            ("w", [address, kind]) | ("watch", [address, kind]) => {
+                self.watch(address, Watch::parse(kind)?)?
+            }
+            ("d", [address]) | ("delete", [address]) => {
+                let address = self.parse_address(address)?;
+                let removed =
+                    self.breakpoints.remove(&address) | self.watchpoints.remove(&address).is_some();
+                if !removed {
+                    return Err(format!("nothing set at {}", address));
+                }
+                format!("deleted {}\n", address)
+            }
+            ("i", []) | ("info", []) => {
+                let mut result = String::new();
+                for address in &self.breakpoints {
+                    result += &format!("breakpoint {}\n", address);
+                }
+                for (address, watch) in &self.watchpoints {
+                    result += &format!("watchpoint {} {}\n", address, watch.to_str());
+                }
+                result
+            }
+            ("r", []) | ("regs", []) => format!(
+                "pc {}\nrb {}\ninput {:?}\n",
+                self.machine().pc(),
+                self.machine().base(),
+                self.machine().input()
+            ),
+            ("l", []) | ("list", []) => self.list(self.machine().pc(), LIST_LENGTH),
+            ("l", [address]) | ("list", [address]) => {
+                self.list(self.parse_address(address)?, LIST_LENGTH)
+            }
+            ("l", [address, n]) | ("list", [address, n]) => {
+                self.list(self.parse_address(address)?, parse_count(n)?)
+            }
+            ("x", [address]) => self.examine(self.parse_address(address)?, 1)?,
+            ("x", [address, n]) => self.examine(self.parse_address(address)?, parse_count(n)?)?,
+            ("set", [address, value]) => {
+                let address = self.parse_address(address)?;
+                let value = parse_number(value)?;
+                if let Some(limit) = self.machine().memory_limit() {
+                    if address >= limit {
+                        return Err(format!(
+                            "address {} is beyond the memory limit of {}",
+                            address, limit
+                        ));
+                    }
+                }
+                self.machine_mut().poke(address, value);
+                self.examine(address, 1)?
+            }
+            ("pc", [value]) => {
+                let pc = parse_count(value)?;
+                self.machine_mut().set_pc(pc);
+                format!("{}\n", self.location())
+            }
+            ("rb", []) => format!("rb {}\n", self.machine().base()),
+            ("rb", [value]) => {
+                self.machine_mut().set_base(parse_number(value)?);
+                format!("rb {}\n", self.machine().base())
+            }
+            ("in", values) if !values.is_empty() => {
+                for value in values {
+                    self.machine_mut().add_input(parse_number(value)?);
+                }
+                format!("{} values queued\n", values.len())
+            }
+            ("line", _) => {
+                let text = command[name.len()..].trim_start();
+                self.machine_mut().add_line(text);
+                format!("{} values queued\n", text.len() + 1)
+            }
+            ("out", []) => format_output(self.output.iter()),
+            ("profile", []) => self.tracer.profile().to_string(),
+            ("trace", ["off"]) => {
+                self.tracer.set_trace(None);
+                "tracing stopped\n".into()
+            }
+            ("trace", [path]) => {
+                let file = File::create(path).map_err(|err| err.to_string())?;
+                self.tracer.set_trace(Some(BufWriter::new(file)));
+                format!("tracing to {}\n", path)
+            }
+            ("cfg", [path]) => {
+                let graph = cfg::analyze(self.machine().program());
+                fs::write(path, graph.to_dot()).map_err(|err| err.to_string())?;
+                format!(
+                    "{} blocks and {} routines written to {}\n",
+                    graph.blocks.len(),
+                    graph.routines.len(),
+                    path
+                )
+            }
+            ("patches", ["on"]) => {
+                self.machine_mut().set_patch_detection(true);
+                self.reported_patches = 0;
+                "reporting writes to instructions that already ran\n".into()
+            }
+            ("patches", ["off"]) => {
+                self.machine_mut().set_patch_detection(false);
+                "stopped reporting patches\n".into()
+            }
+            ("patches", []) => match self.machine().patches() {
+                Some(patches) => patches.summary().to_string(),
+                None => return Err("not reporting patches, try patches on".into()),
+            },
+            ("h", []) | ("help", []) => format!("{}\n", HELP),
+            _ => return Err(format!("unknown command {}, try help", command)),
+        };
+        if let Some(patches) = self.machine().patches() {
+            let new_patches = &patches.events[self.reported_patches..];
+            let lines = new_patches.iter().map(|patch| format!("{}\n", patch));
+            result = lines.collect::<String>() + &result;
+            self.reported_patches = patches.events.len();
+        }
+        if !self.new_output.is_empty() {
+            result = format!("{}{}", format_output(self.new_output.iter()), result);
+            self.new_output.clear();
+        }
+        Ok(result)
+    }
+
+    fn describe_run(&mut self, max_steps: Option<usize>) -> String {
+        let stop = self.run(max_steps);
+        self.describe(stop)
+    }
+
+    fn watch(&mut self, address: &str, watch: Watch) -> Result<String, String> {
+        let address = self.parse_address(address)?;
+        self.watchpoints.insert(address, watch);
+        Ok(format!("watchpoint {} {}\n", address, watch.to_str()))
+    }
+
+    // Shows memory from `start`, up to the end of what's allocated.
+    fn examine(&self, start: usize, count: usize) -> Result<String, String> {
+        let len = self.machine().program().len();
+        if start >= len {
+            return Err(format!(
+                "address {} is beyond the end of memory at {}",
+                start, len
+            ));
+        }
+        let end = start.saturating_add(count).min(len);
+        let mut result = String::new();
+        for line_start in (start..end).step_by(EXAMINE_PER_LINE) {
+            let line_end = (line_start + EXAMINE_PER_LINE).min(end);
+            let values = (line_start..line_end)
+                .map(|address| self.machine().peek(address).to_string())
+                .collect::<Vec<_>>();
+            result += &format!("{:>6}: {}\n", line_start, values.join(" "));
+        }
+        Ok(result)
+    }
+}
+
+fn parse_number(s: &str) -> Result<i64, String> {
+    s.parse().map_err(|_| format!("invalid number {}", s))
+}
+
+fn parse_count(s: &str) -> Result<usize, String> {
+    s.parse().map_err(|_| format!("invalid count {}", s))
+}
+
+fn is_printable(value: i64) -> bool {
+    value == '\n' as i64 || (32..127).contains(&value)
+}
+
+fn format_output<'a>(values: impl Iterator<Item = &'a i64>) -> String {
+    let mut result = String::new();
+    for &value in values {
+        if is_printable(value) {
+            result.push(value as u8 as char);
+        } else {
+            if !result.is_empty() && !result.ends_with('\n') {
+                result.push('\n');
+            }
+            result += &format!("{}\n", value);
+        }
+    }
+    if !result.is_empty() && !result.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn debugger(program: Vec<i64>) -> Debugger {
+        Debugger::new(ProgramMachine::new(program, vec![]))
+    }
+
+    #[test]
+    fn test_breakpoint() {
+        // counts down from 3, printing each value
+        let mut debugger = debugger(vec![4, 10, 1001, 10, -1, 10, 1005, 10, 0, 99, 3]);
+        assert_eq!(debugger.execute("b 6").unwrap(), "breakpoint at 6\n");
+        assert_eq!(
+            debugger.execute("c").unwrap(),
+            "3\nbreakpoint at 6\n=>      6: jt [10], #0\n"
+        );
+        assert_eq!(debugger.execute("x 10").unwrap(), "    10: 2\n");
+        assert_eq!(debugger.execute("d 6").unwrap(), "deleted 6\n");
+        assert_eq!(
+            debugger.execute("c").unwrap(),
+            "2\n1\nhalted\n=>      9: hlt\n"
+        );
+        assert_eq!(debugger.execute("out").unwrap(), "3\n2\n1\n");
+        assert!(debugger
+            .execute("profile")
+            .unwrap()
+            .starts_with("10 instructions executed\n"));
+    }
+
+    #[test]
+    fn test_watchpoint() {
+        let mut debugger = debugger(vec![1101, 1, 2, 9, 1, 9, 9, 10, 99, 0, 0]);
+        debugger.execute("w 9 r").unwrap();
+        assert_eq!(
+            debugger.execute("c").unwrap(),
+            "read of [9]\n=>      4: add [9], [9], [10]\n"
+        );
+        debugger.execute("d 9").unwrap();
+        debugger.execute("w 10 w").unwrap();
+        debugger.execute("set 9 20").unwrap();
+        assert_eq!(debugger.execute("s").unwrap(), "=>      8: hlt\n");
+        assert_eq!(debugger.execute("x 9 2").unwrap(), "     9: 20 40\n");
+    }
+
+    #[test]
+    fn test_memory_limit() {
+        // writes far beyond its program
+        let mut debugger = debugger(vec![1101, 1, 2, 99999999999, 99]);
+        assert_eq!(
+            debugger.execute("set 99999999999 1").unwrap_err(),
+            "address 99999999999 is beyond the memory limit of 16777216"
+        );
+        debugger.execute("rb 1").unwrap();
+        assert_eq!(
+            debugger.execute("x rb+9223372036854775807").unwrap_err(),
+            "address rb+9223372036854775807 overflows"
+        );
+        assert!(debugger.execute("c").unwrap().starts_with("error: "));
+        assert_eq!(debugger.execute("x 4").unwrap(), "     4: 99\n");
+        assert_eq!(
+            debugger.execute("x 3 18446744073709551615").unwrap(),
+            "     3: 99999999999 99\n"
+        );
+        assert_eq!(
+            debugger.execute("x 5").unwrap_err(),
+            "address 5 is beyond the end of memory at 5"
+        );
+    }
+
+    #[test]
+    fn test_input() {
+        let mut debugger = debugger(vec![109, 5, 3, 0, 4, 0, 1105, 1, 2]);
+        assert_eq!(
+            debugger.execute("c").unwrap(),
+            "waiting for input\n=>      2: in [0]\n"
+        );
+        debugger.execute("line hi").unwrap();
+        assert_eq!(
+            debugger.execute("c").unwrap(),
+            "hi\nwaiting for input\n=>      2: in [0]\n"
+        );
+        assert_eq!(debugger.execute("x rb-5 2").unwrap(), "     0: 10 5\n");
+    }
+
+    #[test]
+    fn test_patches() {
+        // outputs 5, then turns the output into an immediate one
+        let mut debugger = debugger(vec![4, 7, 1101, 104, 0, 0, 99, 5]);
+        assert!(debugger.execute("patches").is_err());
+        debugger.execute("patches on").unwrap();
+        assert_eq!(
+            debugger.execute("c").unwrap(),
+            "5\n\
+             2 patched [0]: 4 -> 104\n\
+             halted\n\
+             =>      6: hlt\n\
+             1 writes to code that already ran, 1 to instruction words, 0 unchanged\n     \
+             2: [0]\n"
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::VecDeque;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, stdout, ErrorKind, LineWriter, Write};
+
+use rustyline::error::ReadlineError;
+use rustyline::Editor;
+
+use advent_of_code_2019::input::{load_intcode, Source};
+use advent_of_code_2019::intcode::ascii::{AsciiMachine, Event};
+use advent_of_code_2019::intcode::ProgramMachine;
+
+const USAGE: &str = "usage: intcode-term <program file> [--record <file>] [--replay <file>]";
+
+fn main() {
+    let mut path = None;
+    let mut record = None;
+    let mut replay = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--record" => record = Some(args.next().expect(USAGE)),
+            "--replay" => replay = Some(args.next().expect(USAGE)),
+            _ if path.is_none() => path = Some(arg),
+            _ => panic!("{}", USAGE),
+        }
+    }
+    let source = match Source::from_arg(&path.expect(USAGE)) {
+        // input lines are read from stdin
+        Source::Stdin => panic!("intcode-term can't read the program from stdin"),
+        source => source,
+    };
+    let program = load_intcode(&source).unwrap_or_else(|err| panic!("{}", err));
+
+    let mut terminal = Terminal::new(ProgramMachine::new(program, vec![]));
+    if let Some(path) = replay {
+        let transcript = fs::read_to_string(path).expect("failed to read transcript");
+        terminal.replay(&transcript);
+    }
+    if let Some(path) = record {
+        let file = File::create(path).expect("failed to create transcript");
+        // flush every line, so the transcript survives ctrl-c
+        terminal.record(LineWriter::new(file));
+    }
+
+    let mut editor = Editor::<()>::new();
+    let result = terminal.run(&mut stdout(), |prompt| match editor.readline(prompt) {
+        Ok(line) => {
+            editor.add_history_entry(line.as_str());
+            Some(line)
+        }
+        Err(ReadlineError::Eof) | Err(ReadlineError::Interrupted) => None,
+        Err(err) => panic!("failed to read input: {}", err),
+    });
+    match result {
+        // stdout was closed, for example by piping into `head`
+        Err(err) if err.kind() == ErrorKind::BrokenPipe => {}
+        Err(err) => eprintln!("error: {}", err),
+        Ok(()) => {}
+    }
+}
+
+// Connects an ASCII machine to a line-based terminal.
+struct Terminal<W: Write> {
+    machine: AsciiMachine<ProgramMachine>,
+    // lines to send before asking for any
+    replay: VecDeque<String>,
+    transcript: Option<W>,
+}
+
+impl<W: Write> Terminal<W> {
+    fn new(machine: ProgramMachine) -> Terminal<W> {
+        Terminal {
+            machine: AsciiMachine::new(machine),
+            replay: VecDeque::new(),
+            transcript: None,
+        }
+    }
+
+    fn replay(&mut self, transcript: &str) {
+        self.replay.extend(transcript.lines().map(String::from));
+    }
+
+    // Writes every line sent to the machine to `transcript`, including replayed ones.
+    fn record(&mut self, transcript: W) {
+        self.transcript = Some(transcript);
+    }
+
+    // Runs until the machine halts, or until `read_line` runs out of input. Errors in the
+    // machine are reported as invalid data.
+    fn run(
+        &mut self,
+        out: &mut impl Write,
+        mut read_line: impl FnMut(&str) -> Option<String>,
+    ) -> io::Result<()> {
+        loop {
+            let event = self
+                .machine
+                .try_next_event()
+                .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+            match event {
+                Event::Line(line) => writeln!(out, "{}", line)?,
+                // values that aren't characters, like a final score
+                Event::Value(value) => writeln!(out, "{}", value)?,
+                Event::Prompt(prompt) => {
+                    let line = match self.replay.pop_front() {
+                        Some(line) => {
+                            writeln!(out, "{}{}", prompt, line)?;
+                            line
+                        }
+                        None => {
+                            out.flush()?;
+                            match read_line(&prompt) {
+                                Some(line) => line,
+                                None => return Ok(()),
+                            }
+                        }
+                    };
+                    if let Some(transcript) = &mut self.transcript {
+                        writeln!(transcript, "{}", line)?;
+                    }
+                    self.machine.send_line(&line);
+                }
+                Event::Halt => return Ok(()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use advent_of_code_2019::input::parse_list;
+
+    const SCRIPT: &str = "OR A J\nAND B J\nAND C J\nNOT J J\nAND D J\nWALK\n";
+
+    fn springdroid() -> Terminal<Vec<u8>> {
+        let program: Vec<i64> = parse_list(include_str!("../day21/input"), ',');
+        Terminal::new(ProgramMachine::new(program, vec![]))
+    }
+
+    #[test]
+    fn test_record_and_replay() {
+        let mut terminal = springdroid();
+        terminal.record(Vec::new());
+        let mut lines = SCRIPT.lines();
+        let mut out = Vec::new();
+        terminal
+            .run(&mut out, |_| lines.next().map(String::from))
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("Input instructions:\n"));
+        assert!(out.ends_with("\nWalking...\n\n19357534\n"));
+        let transcript = String::from_utf8(terminal.transcript.unwrap()).unwrap();
+        assert_eq!(transcript, SCRIPT);
+
+        let mut terminal = springdroid();
+        terminal.replay(&transcript);
+        let mut replayed = Vec::new();
+        terminal.run(&mut replayed, |_| None).unwrap();
+        let replayed = String::from_utf8(replayed).unwrap();
+        // replayed lines are echoed, as if they had been typed
+        assert!(replayed.starts_with("Input instructions:\nOR A J\nAND B J\n"));
+        assert!(replayed.ends_with("\nWalking...\n\n19357534\n"));
+    }
+
+    #[test]
+    fn test_end_of_input() {
+        let mut terminal = springdroid();
+        terminal.replay("NOT A J\n");
+        let mut out = Vec::new();
+        terminal.run(&mut out, |_| None).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Input instructions:\nNOT A J\n"
+        );
+    }
+
+    // fails every write, like stdout after the reader went away
+    struct ClosedPipe;
+
+    impl Write for ClosedPipe {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::from(ErrorKind::BrokenPipe))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_closed_output() {
+        let mut terminal = springdroid();
+        let result = terminal.run(&mut ClosedPipe, |_| panic!("asked for input"));
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::BrokenPipe);
+    }
+}
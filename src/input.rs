@@ -1,6 +1,14 @@
-use std::fmt::Debug;
+use std::env;
+use std::error::Error;
+use std::fmt::{self, Debug};
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::process;
 use std::str::FromStr;
 
+const USAGE: &str = "usage: [--input <path>], where a path of - reads from stdin";
+
 pub fn parse_list<T, E>(input: &str, separator: char) -> Vec<T>
 where
     T: FromStr<Err = E>,
@@ -12,3 +20,235 @@ where
         .map(|x| x.parse().expect("invalid input"))
         .collect();
 }
+
+// Where to read a puzzle input from.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Source {
+    Path(PathBuf),
+    Stdin,
+    Text(String),
+}
+
+impl Source {
+    // A path from the command line, where `-` means stdin.
+    pub fn from_arg(arg: &str) -> Source {
+        match arg {
+            "-" => Source::Stdin,
+            path => Source::Path(PathBuf::from(path)),
+        }
+    }
+
+    pub fn read(&self) -> io::Result<String> {
+        match self {
+            Source::Path(path) => fs::read_to_string(path),
+            Source::Stdin => {
+                let mut text = String::new();
+                io::stdin().read_to_string(&mut text)?;
+                Ok(text)
+            }
+            Source::Text(text) => Ok(text.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    fn at(source: &str, offset: usize, message: String) -> ParseError {
+        let before = &source[..offset];
+        let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+        ParseError {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl Error for ParseError {}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    Parse(ParseError),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io(err) => write!(f, "failed to read input: {}", err),
+            LoadError::Parse(err) => write!(f, "invalid program at {}", err),
+        }
+    }
+}
+
+impl Error for LoadError {}
+
+impl From<io::Error> for LoadError {
+    fn from(err: io::Error) -> Self {
+        LoadError::Io(err)
+    }
+}
+
+impl From<ParseError> for LoadError {
+    fn from(err: ParseError) -> Self {
+        LoadError::Parse(err)
+    }
+}
+
+// Parses a comma-separated Intcode program, allowing whitespace and newlines around the
+// commas.
+pub fn parse_intcode(source: &str) -> Result<Vec<i64>, ParseError> {
+    let mut program = Vec::new();
+    let mut offset = 0;
+    for item in source.split(',') {
+        let start = offset + (item.len() - item.trim_start().len());
+        let word = item.trim();
+        if word.is_empty() {
+            let message = if source.trim().is_empty() {
+                "empty program".to_string()
+            } else {
+                "expected a number".to_string()
+            };
+            return Err(ParseError::at(source, start, message));
+        }
+        match word.parse() {
+            Ok(value) => program.push(value),
+            Err(_) => {
+                let message = format!("invalid number `{}`", word);
+                return Err(ParseError::at(source, start, message));
+            }
+        }
+        offset += item.len() + 1;
+    }
+    Ok(program)
+}
+
+pub fn load_intcode(source: &Source) -> Result<Vec<i64>, LoadError> {
+    Ok(parse_intcode(&source.read()?)?)
+}
+
+// Finds `--input <path>` or `--input=<path>` in the arguments.
+fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Option<Source>, String> {
+    let mut source = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let path = match arg.strip_prefix("--input") {
+            Some("") => args.next(),
+            Some(rest) if rest.starts_with('=') => Some(rest[1..].to_string()),
+            _ => return Err(format!("unexpected argument `{}`", arg)),
+        };
+        match path {
+            Some(path) if source.is_none() => source = Some(Source::from_arg(&path)),
+            Some(_) => return Err("--input given more than once".to_string()),
+            None => return Err("--input needs a path".to_string()),
+        }
+    }
+    Ok(source)
+}
+
+// Prints the message and exits, for errors in a binary's arguments or input.
+pub fn exit_with(message: impl fmt::Display) -> ! {
+    eprintln!("{}", message);
+    process::exit(1)
+}
+
+// The source given with `--input` on the command line, if any. Exits on any other
+// argument.
+pub fn source_from_args() -> Option<Source> {
+    parse_args(env::args().skip(1)).unwrap_or_else(|err| exit_with(format!("{}\n{}", err, USAGE)))
+}
+
+// The puzzle input from `--input`, or else the one the binary was built with.
+pub fn input_from_args(default: &str) -> String {
+    match source_from_args() {
+        Some(source) => source
+            .read()
+            .unwrap_or_else(|err| exit_with(LoadError::Io(err))),
+        None => default.to_string(),
+    }
+}
+
+// The Intcode program from `--input`, or else the one the binary was built with.
+pub fn intcode_from_args(default: &str) -> Vec<i64> {
+    let source = source_from_args().unwrap_or_else(|| Source::Text(default.to_string()));
+    load_intcode(&source).unwrap_or_else(|err| exit_with(err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_intcode() {
+        assert_eq!(parse_intcode("1,0,0,3,99\n"), Ok(vec![1, 0, 0, 3, 99]));
+        assert_eq!(
+            parse_intcode("1, -2 ,\n3\r\n,\t4\n\n"),
+            Ok(vec![1, -2, 3, 4])
+        );
+        assert_eq!(
+            parse_intcode("1,2,\n3,x4,5").unwrap_err().to_string(),
+            "2:3: invalid number `x4`"
+        );
+        assert_eq!(
+            parse_intcode("1,2,\n  ,3").unwrap_err().to_string(),
+            "2:3: expected a number"
+        );
+        assert_eq!(
+            parse_intcode("1,2,3,\n").unwrap_err().to_string(),
+            "2:1: expected a number"
+        );
+        assert_eq!(
+            parse_intcode(" \n").unwrap_err().to_string(),
+            "2:1: empty program"
+        );
+    }
+
+    #[test]
+    fn test_load_intcode() {
+        let program = load_intcode(&Source::Path("src/bin/day2/input".into())).unwrap();
+        assert_eq!(&program[..4], &[1, 0, 0, 3]);
+        let text = Source::Text("104,7,99".to_string());
+        assert_eq!(load_intcode(&text).unwrap(), vec![104, 7, 99]);
+        let missing = Source::Path("src/bin/day2/missing".into());
+        assert!(matches!(load_intcode(&missing), Err(LoadError::Io(_))));
+        let invalid = Source::Text("1,,2".to_string());
+        assert_eq!(
+            load_intcode(&invalid).unwrap_err().to_string(),
+            "invalid program at 1:3: expected a number"
+        );
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse_args(args(&[])), Ok(None));
+        assert_eq!(
+            parse_args(args(&["--input", "my/input"])),
+            Ok(Some(Source::Path("my/input".into())))
+        );
+        assert_eq!(
+            parse_args(args(&["--input=my/input"])),
+            Ok(Some(Source::Path("my/input".into())))
+        );
+        assert_eq!(parse_args(args(&["--input", "-"])), Ok(Some(Source::Stdin)));
+        assert!(parse_args(args(&["--input"])).is_err());
+        assert!(parse_args(args(&["--input", "a", "--input", "b"])).is_err());
+        assert!(parse_args(args(&["input"])).is_err());
+        assert!(parse_args(args(&["--inputs"])).is_err());
+    }
+}
@@ -1,8 +1,130 @@
 use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::io::ErrorKind;
 use std::ops::DerefMut;
+use std::sync::Arc;
 
-#[derive(Debug)]
-enum InputValue {
+pub use bignum::{BigMachine, BigStepResult};
+pub use budget::{Budget, Outcome};
+pub use coverage::Coverage;
+pub use io::{Format, InputSource, OutputSink, ReaderSource, WriterSink};
+pub use memory::{Memory, PagedMemory, SparseMemory};
+pub use network::{Network, NetworkBuilder, NetworkState};
+pub use patches::{Patch, Patches};
+pub use snapshot::Snapshot;
+
+pub mod aot;
+pub mod ascii;
+pub mod asm;
+mod bignum;
+mod budget;
+pub mod cfg;
+pub mod coverage;
+pub mod disasm;
+pub mod fuzz;
+pub mod io;
+mod memory;
+pub mod network;
+pub mod packet;
+pub mod patches;
+pub mod rewind;
+mod snapshot;
+pub mod symbolic;
+pub mod task;
+pub mod threads;
+pub mod trace;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum IntcodeError {
+    InvalidOpcode {
+        pc: usize,
+        instruction: i64,
+    },
+    InvalidMode {
+        pc: usize,
+        instruction: i64,
+        mode: i64,
+    },
+    NegativeAddress {
+        pc: usize,
+        instruction: i64,
+        address: i64,
+    },
+    ImmediateWrite {
+        pc: usize,
+        instruction: i64,
+    },
+    MemoryLimit {
+        pc: usize,
+        instruction: i64,
+        address: usize,
+    },
+    Overflow {
+        pc: usize,
+        instruction: i64,
+    },
+    PcOutOfRange {
+        pc: usize,
+    },
+    MissingInput,
+    Io(ErrorKind),
+}
+
+impl fmt::Display for IntcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IntcodeError::InvalidOpcode { pc, instruction } => {
+                write!(f, "unexpected opcode {} at index {}", instruction, pc)
+            }
+            IntcodeError::InvalidMode {
+                pc,
+                instruction,
+                mode,
+            } => write!(
+                f,
+                "unexpected parameter mode {} in instruction {} at index {}",
+                mode, instruction, pc
+            ),
+            IntcodeError::NegativeAddress {
+                pc,
+                instruction,
+                address,
+            } => write!(
+                f,
+                "negative address {} in instruction {} at index {}",
+                address, instruction, pc
+            ),
+            IntcodeError::ImmediateWrite { pc, instruction } => write!(
+                f,
+                "write in immediate mode in instruction {} at index {}",
+                instruction, pc
+            ),
+            IntcodeError::MemoryLimit {
+                pc,
+                instruction,
+                address,
+            } => write!(
+                f,
+                "write to address {} exceeds memory limit in instruction {} at index {}",
+                address, instruction, pc
+            ),
+            IntcodeError::Overflow { pc, instruction } => write!(
+                f,
+                "integer overflow in instruction {} at index {}",
+                instruction, pc
+            ),
+            IntcodeError::PcOutOfRange { pc } => write!(f, "program counter {} out of range", pc),
+            IntcodeError::MissingInput => write!(f, "missing input"),
+            IntcodeError::Io(kind) => write!(f, "i/o error: {}", kind),
+        }
+    }
+}
+
+impl Error for IntcodeError {}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum InputValue {
     Position(i64),
     Immediate(i64),
     Relative(i64),
@@ -10,56 +132,100 @@ enum InputValue {
 
 impl InputValue {
     #[inline]
-    fn parse(mode: i32, value: i64) -> InputValue {
+    pub fn try_parse(mode: i64, value: i64) -> Option<InputValue> {
         match mode {
-            0 => InputValue::Position(value),
-            1 => InputValue::Immediate(value),
-            2 => InputValue::Relative(value),
-            _ => panic!("unexpected parameter mode {}", mode),
+            0 => Some(InputValue::Position(value)),
+            1 => Some(InputValue::Immediate(value)),
+            2 => Some(InputValue::Relative(value)),
+            _ => None,
         }
     }
 
+    pub fn parse(mode: i64, value: i64) -> InputValue {
+        InputValue::try_parse(mode, value)
+            .unwrap_or_else(|| panic!("unexpected parameter mode {}", mode))
+    }
+
+    // The address read from, or None for an immediate or a relative address that overflows.
     #[inline]
-    fn read(&self, program: &Vec<i64>, base: i64) -> i64 {
+    pub fn address(&self, base: i64) -> Option<i64> {
+        match *self {
+            InputValue::Position(pos) => Some(pos),
+            InputValue::Immediate(_) => None,
+            InputValue::Relative(pos) => base.checked_add(pos),
+        }
+    }
+
+    pub fn encode(&self) -> (i64, i64) {
+        match *self {
+            InputValue::Position(pos) => (0, pos),
+            InputValue::Immediate(value) => (1, value),
+            InputValue::Relative(pos) => (2, pos),
+        }
+    }
+
+    pub fn read(&self, program: &[i64], base: i64) -> i64 {
         match *self {
-            InputValue::Position(pos) => *program.get(pos as usize).unwrap_or(&0),
             InputValue::Immediate(value) => value,
-            InputValue::Relative(pos) => *program.get((base + pos) as usize).unwrap_or(&0),
+            _ => {
+                let pos = self.address(base).expect("address overflows");
+                assert!(pos >= 0, "negative address {}", pos);
+                *program.get(pos as usize).unwrap_or(&0)
+            }
         }
     }
 }
 
-#[derive(Debug)]
-enum OutputValue {
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum OutputValue {
     Position(i64),
     Relative(i64),
 }
 
 impl OutputValue {
     #[inline]
-    fn parse(mode: i32, value: i64) -> OutputValue {
+    pub fn try_parse(mode: i64, value: i64) -> Option<OutputValue> {
         match mode {
-            0 => OutputValue::Position(value),
-            2 => OutputValue::Relative(value),
-            _ => panic!("unexpected parameter mode {}", mode),
+            0 => Some(OutputValue::Position(value)),
+            2 => Some(OutputValue::Relative(value)),
+            _ => None,
         }
     }
 
+    pub fn parse(mode: i64, value: i64) -> OutputValue {
+        OutputValue::try_parse(mode, value)
+            .unwrap_or_else(|| panic!("unexpected parameter mode {}", mode))
+    }
+
+    // The address written to, or None for a relative address that overflows.
     #[inline]
-    fn write(&self, program: &mut Vec<i64>, base: i64, value: i64) {
-        let pos = match *self {
-            OutputValue::Position(pos) => pos as usize,
-            OutputValue::Relative(pos) => (base + pos) as usize,
-        };
+    pub fn address(&self, base: i64) -> Option<i64> {
+        match *self {
+            OutputValue::Position(pos) => Some(pos),
+            OutputValue::Relative(pos) => base.checked_add(pos),
+        }
+    }
+
+    pub fn encode(&self) -> (i64, i64) {
+        match *self {
+            OutputValue::Position(pos) => (0, pos),
+            OutputValue::Relative(pos) => (2, pos),
+        }
+    }
+
+    pub fn write(&self, program: &mut Vec<i64>, base: i64, value: i64) {
+        let pos = self.address(base).expect("address overflows");
+        assert!(pos >= 0, "negative address {}", pos);
+        let pos = pos as usize;
         if pos >= program.len() {
             program.resize(pos + 1, 0);
         }
-        program[pos as usize] = value;
+        program[pos] = value;
     }
 }
 
-#[derive(Debug)]
-enum Instruction {
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Instruction {
     Add(InputValue, InputValue, OutputValue),
     Multiply(InputValue, InputValue, OutputValue),
     Read(OutputValue),
@@ -72,7 +238,7 @@ enum Instruction {
     Halt,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum StepResult {
     Ok,
     NeedInput,
@@ -81,50 +247,63 @@ pub enum StepResult {
 }
 
 impl Instruction {
-    fn parse(program: &Vec<i64>, pc: usize) -> Instruction {
-        let opcode = program[pc] as i32;
-        let mode1 = (opcode / 100) % 10;
-        let mode2 = (opcode / 1000) % 10;
-        let mode3 = (opcode / 10000) % 10;
-        match opcode % 100 {
-            1 => Instruction::Add(
-                InputValue::parse(mode1, program[pc + 1]),
-                InputValue::parse(mode2, program[pc + 2]),
-                OutputValue::parse(mode3, program[pc + 3]),
-            ),
-            2 => Instruction::Multiply(
-                InputValue::parse(mode1, program[pc + 1]),
-                InputValue::parse(mode2, program[pc + 2]),
-                OutputValue::parse(mode3, program[pc + 3]),
-            ),
-            3 => Instruction::Read(OutputValue::parse(mode1, program[pc + 1])),
-            4 => Instruction::Write(InputValue::parse(mode1, program[pc + 1])),
-            5 => Instruction::JumpIfTrue(
-                InputValue::parse(mode1, program[pc + 1]),
-                InputValue::parse(mode2, program[pc + 2]),
-            ),
-            6 => Instruction::JumpIfFalse(
-                InputValue::parse(mode1, program[pc + 1]),
-                InputValue::parse(mode2, program[pc + 2]),
-            ),
-            7 => Instruction::LessThan(
-                InputValue::parse(mode1, program[pc + 1]),
-                InputValue::parse(mode2, program[pc + 2]),
-                OutputValue::parse(mode3, program[pc + 3]),
-            ),
-            8 => Instruction::Equals(
-                InputValue::parse(mode1, program[pc + 1]),
-                InputValue::parse(mode2, program[pc + 2]),
-                OutputValue::parse(mode3, program[pc + 3]),
-            ),
-            9 => Instruction::RelativeBaseOffset(InputValue::parse(mode1, program[pc + 1])),
-            99 => Instruction::Halt,
-            _ => panic!("unexpected opcode {} at index {}", opcode, pc),
+    pub fn try_parse(program: &[i64], pc: usize) -> Result<Instruction, IntcodeError> {
+        if pc >= program.len() {
+            return Err(IntcodeError::PcOutOfRange { pc });
         }
+        // parameters past the end of the program read as zero, just like memory
+        Instruction::try_decode(pc, |address| *program.get(address).unwrap_or(&0))
+    }
+
+    // Decodes the instruction at pc, fetching words from memory through `word`.
+    pub fn try_decode(pc: usize, word: impl Fn(usize) -> i64) -> Result<Instruction, IntcodeError> {
+        let opcode = word(pc);
+        let param = |index: usize| word(pc + index);
+        let mode = |index: usize| (opcode / 10i64.pow(index as u32 + 1)) % 10;
+        let input = |index: usize| {
+            InputValue::try_parse(mode(index), param(index)).ok_or(IntcodeError::InvalidMode {
+                pc,
+                instruction: opcode,
+                mode: mode(index),
+            })
+        };
+        let output = |index: usize| match mode(index) {
+            1 => Err(IntcodeError::ImmediateWrite {
+                pc,
+                instruction: opcode,
+            }),
+            mode => OutputValue::try_parse(mode, param(index)).ok_or(IntcodeError::InvalidMode {
+                pc,
+                instruction: opcode,
+                mode,
+            }),
+        };
+        Ok(match opcode % 100 {
+            1 => Instruction::Add(input(1)?, input(2)?, output(3)?),
+            2 => Instruction::Multiply(input(1)?, input(2)?, output(3)?),
+            3 => Instruction::Read(output(1)?),
+            4 => Instruction::Write(input(1)?),
+            5 => Instruction::JumpIfTrue(input(1)?, input(2)?),
+            6 => Instruction::JumpIfFalse(input(1)?, input(2)?),
+            7 => Instruction::LessThan(input(1)?, input(2)?, output(3)?),
+            8 => Instruction::Equals(input(1)?, input(2)?, output(3)?),
+            9 => Instruction::RelativeBaseOffset(input(1)?),
+            99 => Instruction::Halt,
+            _ => {
+                return Err(IntcodeError::InvalidOpcode {
+                    pc,
+                    instruction: opcode,
+                })
+            }
+        })
+    }
+
+    pub fn parse(program: &[i64], pc: usize) -> Instruction {
+        Instruction::try_parse(program, pc).unwrap_or_else(|err| panic!("{}", err))
     }
 
     #[inline]
-    fn length(&self) -> usize {
+    pub fn length(&self) -> usize {
         match self {
             Instruction::Add(_, _, _)
             | Instruction::Multiply(_, _, _)
@@ -135,34 +314,108 @@ impl Instruction {
             Instruction::Halt => 1,
         }
     }
+
+    pub fn opcode(&self) -> i64 {
+        match self {
+            Instruction::Add(_, _, _) => 1,
+            Instruction::Multiply(_, _, _) => 2,
+            Instruction::Read(_) => 3,
+            Instruction::Write(_) => 4,
+            Instruction::JumpIfTrue(_, _) => 5,
+            Instruction::JumpIfFalse(_, _) => 6,
+            Instruction::LessThan(_, _, _) => 7,
+            Instruction::Equals(_, _, _) => 8,
+            Instruction::RelativeBaseOffset(_) => 9,
+            Instruction::Halt => 99,
+        }
+    }
+
+    pub fn inputs(&self) -> Vec<InputValue> {
+        match *self {
+            Instruction::Add(left, right, _)
+            | Instruction::Multiply(left, right, _)
+            | Instruction::LessThan(left, right, _)
+            | Instruction::Equals(left, right, _)
+            | Instruction::JumpIfTrue(left, right)
+            | Instruction::JumpIfFalse(left, right) => vec![left, right],
+            Instruction::Write(value) | Instruction::RelativeBaseOffset(value) => vec![value],
+            Instruction::Read(_) | Instruction::Halt => vec![],
+        }
+    }
+
+    pub fn output(&self) -> Option<OutputValue> {
+        match *self {
+            Instruction::Add(_, _, result)
+            | Instruction::Multiply(_, _, result)
+            | Instruction::LessThan(_, _, result)
+            | Instruction::Equals(_, _, result)
+            | Instruction::Read(result) => Some(result),
+            _ => None,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<i64> {
+        let params = match self {
+            Instruction::Add(left, right, result)
+            | Instruction::Multiply(left, right, result)
+            | Instruction::LessThan(left, right, result)
+            | Instruction::Equals(left, right, result) => {
+                vec![left.encode(), right.encode(), result.encode()]
+            }
+            Instruction::JumpIfTrue(test, jump) | Instruction::JumpIfFalse(test, jump) => {
+                vec![test.encode(), jump.encode()]
+            }
+            Instruction::Read(result) => vec![result.encode()],
+            Instruction::Write(value) | Instruction::RelativeBaseOffset(value) => {
+                vec![value.encode()]
+            }
+            Instruction::Halt => vec![],
+        };
+        let mut words = vec![self.opcode()];
+        for (index, (mode, value)) in params.into_iter().enumerate() {
+            words[0] += mode * 10i64.pow(index as u32 + 2);
+            words.push(value);
+        }
+        words
+    }
 }
 
 pub trait Machine {
     fn add_input(&mut self, value: i64);
-    fn step(&mut self) -> StepResult;
+    fn try_step(&mut self) -> Result<StepResult, IntcodeError>;
 
-    fn run(&mut self) -> Vec<i64> {
+    fn step(&mut self) -> StepResult {
+        self.try_step().unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    fn try_run(&mut self) -> Result<Vec<i64>, IntcodeError> {
         let mut output = Vec::new();
-        loop {
-            match self.run_to_output() {
-                Some(value) => output.push(value),
-                None => break,
-            };
+        while let Some(value) = self.try_run_to_output()? {
+            output.push(value);
         }
-        output
+        Ok(output)
     }
 
-    fn run_to_output(&mut self) -> Option<i64> {
+    fn run(&mut self) -> Vec<i64> {
+        self.try_run().unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    fn try_run_to_output(&mut self) -> Result<Option<i64>, IntcodeError> {
         loop {
-            match self.step() {
-                StepResult::NeedInput => panic!("missing input"),
-                StepResult::Output(value) => return Some(value),
-                StepResult::Halt => return None,
+            match self.try_step()? {
+                StepResult::NeedInput => return Err(IntcodeError::MissingInput),
+                StepResult::Output(value) => return Ok(Some(value)),
+                StepResult::Halt => return Ok(None),
                 StepResult::Ok => {}
             };
         }
     }
 
+    fn run_to_output(&mut self) -> Option<i64> {
+        self.try_run_to_output()
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
     fn add_line(&mut self, line: &str) {
         for byte in line.bytes() {
             self.add_input(byte as i64);
@@ -197,6 +450,82 @@ pub trait Machine {
         }
         output
     }
+
+    // Runs until the machine halts, reading from `input` whenever the queued input runs out.
+    fn try_run_with(
+        &mut self,
+        input: &mut dyn InputSource,
+        output: &mut dyn OutputSink,
+    ) -> Result<(), IntcodeError> {
+        loop {
+            match self.try_step()? {
+                StepResult::Ok => {}
+                StepResult::NeedInput => match input.read_input()? {
+                    Some(value) => self.add_input(value),
+                    None => return Err(IntcodeError::MissingInput),
+                },
+                StepResult::Output(value) => output.write_output(value)?,
+                StepResult::Halt => return Ok(()),
+            }
+        }
+    }
+
+    fn run_with(&mut self, input: &mut dyn InputSource, output: &mut dyn OutputSink) {
+        self.try_run_with(input, output)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    fn try_run_within(&mut self, budget: &mut Budget) -> Result<Outcome<Vec<i64>>, IntcodeError> {
+        let mut output = Vec::new();
+        loop {
+            match self.try_run_to_output_within(budget)? {
+                Outcome::Done(Some(value)) => output.push(value),
+                Outcome::Done(None) => return Ok(Outcome::Done(output)),
+                Outcome::BudgetExhausted(_) => return Ok(Outcome::BudgetExhausted(output)),
+            }
+        }
+    }
+
+    fn run_within(&mut self, budget: &mut Budget) -> Outcome<Vec<i64>> {
+        self.try_run_within(budget)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    fn try_run_to_output_within(
+        &mut self,
+        budget: &mut Budget,
+    ) -> Result<Outcome<Option<i64>>, IntcodeError> {
+        loop {
+            if !budget.consume() {
+                return Ok(Outcome::BudgetExhausted(None));
+            }
+            match self.try_step()? {
+                StepResult::NeedInput => return Err(IntcodeError::MissingInput),
+                StepResult::Output(value) => return Ok(Outcome::Done(Some(value))),
+                StepResult::Halt => return Ok(Outcome::Done(None)),
+                StepResult::Ok => {}
+            };
+        }
+    }
+
+    fn run_to_output_within(&mut self, budget: &mut Budget) -> Outcome<Option<i64>> {
+        self.try_run_to_output_within(budget)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    fn read_string_within(&mut self, budget: &mut Budget) -> Outcome<String> {
+        let mut output = String::new();
+        loop {
+            if !budget.consume() {
+                return Outcome::BudgetExhausted(output);
+            }
+            match self.step() {
+                StepResult::Ok => {}
+                StepResult::Output(value) => output.push(value as u8 as char),
+                StepResult::NeedInput | StepResult::Halt => return Outcome::Done(output),
+            }
+        }
+    }
 }
 
 impl<T: DerefMut<Target = dyn Machine>> Machine for T {
@@ -204,149 +533,644 @@ impl<T: DerefMut<Target = dyn Machine>> Machine for T {
         self.deref_mut().add_input(value)
     }
 
-    fn step(&mut self) -> StepResult {
-        self.deref_mut().step()
+    fn try_step(&mut self) -> Result<StepResult, IntcodeError> {
+        self.deref_mut().try_step()
     }
 }
 
-pub struct ProgramMachine {
-    program: Vec<i64>,
+// The opcode and parameter modes of a valid instruction word, so they don't need to be
+// worked out again every time the instruction runs. An opcode of 0 means not decoded.
+#[derive(Debug, Copy, Clone, Default)]
+struct Decoded {
+    opcode: u8,
+    modes: [u8; 3],
+}
+
+impl Decoded {
+    fn new(word: i64) -> Decoded {
+        Decoded {
+            opcode: (word % 100) as u8,
+            modes: [
+                ((word / 100) % 10) as u8,
+                ((word / 1000) % 10) as u8,
+                ((word / 10000) % 10) as u8,
+            ],
+        }
+    }
+
+    #[inline]
+    fn instruction<M: Memory>(&self, memory: &M, pc: usize) -> Instruction {
+        let param = |index: usize| memory.get(pc + index);
+        let input = |index: usize| match self.modes[index - 1] {
+            0 => InputValue::Position(param(index)),
+            1 => InputValue::Immediate(param(index)),
+            _ => InputValue::Relative(param(index)),
+        };
+        let output = |index: usize| match self.modes[index - 1] {
+            0 => OutputValue::Position(param(index)),
+            _ => OutputValue::Relative(param(index)),
+        };
+        match self.opcode {
+            1 => Instruction::Add(input(1), input(2), output(3)),
+            2 => Instruction::Multiply(input(1), input(2), output(3)),
+            3 => Instruction::Read(output(1)),
+            4 => Instruction::Write(input(1)),
+            5 => Instruction::JumpIfTrue(input(1), input(2)),
+            6 => Instruction::JumpIfFalse(input(1), input(2)),
+            7 => Instruction::LessThan(input(1), input(2), output(3)),
+            8 => Instruction::Equals(input(1), input(2), output(3)),
+            9 => Instruction::RelativeBaseOffset(input(1)),
+            _ => Instruction::Halt,
+        }
+    }
+}
+
+// the decode cache only covers the low addresses, so a jump far into sparse memory
+// doesn't allocate a huge cache
+const MAX_CACHED_PC: usize = 1 << 20;
+
+#[derive(Debug, Clone)]
+pub struct ProgramMachine<M: Memory = Vec<i64>> {
+    program: M,
     pc: usize,
     base: i64,
     input: VecDeque<i64>,
+    // decoded opcode and modes by pc, cleared whenever the instruction word is written.
+    // Clones share it until one of them changes an entry, so cloning a machine stays cheap
+    // and writes to data never touch it.
+    cache: Arc<Vec<Decoded>>,
+    use_cache: bool,
+    // maximum number of words the memory may hold
+    memory_limit: Option<usize>,
+    coverage: Option<Coverage>,
+    patches: Option<Patches>,
 }
 
 impl ProgramMachine {
     pub fn new(program: Vec<i64>, input: Vec<i64>) -> ProgramMachine {
-        ProgramMachine {
-            program,
+        ProgramMachine::with_memory(program, input)
+    }
+}
+
+impl<M: Memory> ProgramMachine<M> {
+    pub fn with_memory(memory: M, input: Vec<i64>) -> ProgramMachine<M> {
+        let mut machine = ProgramMachine {
+            program: memory,
             pc: 0,
             base: 0,
             input: VecDeque::from(input),
+            cache: Arc::new(Vec::new()),
+            use_cache: true,
+            memory_limit: None,
+            coverage: None,
+            patches: None,
+        };
+        machine.fill_cache();
+        machine
+    }
+
+    pub fn set_decode_cache(&mut self, enabled: bool) {
+        self.use_cache = enabled;
+        self.fill_cache();
+    }
+
+    pub fn memory_limit(&self) -> Option<usize> {
+        self.memory_limit
+    }
+
+    pub fn set_memory_limit(&mut self, limit: Option<usize>) {
+        self.memory_limit = limit;
+    }
+
+    // Starts recording which instructions run, or stops and discards what was recorded.
+    pub fn set_coverage(&mut self, enabled: bool) {
+        self.coverage = if enabled { Some(Coverage::new()) } else { None };
+    }
+
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+
+    pub fn take_coverage(&mut self) -> Option<Coverage> {
+        self.coverage.take()
+    }
+
+    // Starts reporting writes to instructions that already ran, or stops and discards them.
+    pub fn set_patch_detection(&mut self, enabled: bool) {
+        self.patches = if enabled { Some(Patches::new()) } else { None };
+    }
+
+    pub fn patches(&self) -> Option<&Patches> {
+        self.patches.as_ref()
+    }
+
+    pub fn take_patches(&mut self) -> Option<Patches> {
+        self.patches.take()
+    }
+
+    fn fill_cache(&mut self) {
+        // decode every word up front, so clones of a fresh machine start with a warm cache
+        let mut cache = Vec::new();
+        if self.use_cache {
+            let program = &self.program;
+            let len = program.len().min(MAX_CACHED_PC);
+            cache.extend((0..len).map(|pc| {
+                match Instruction::try_decode(pc, |address| program.get(address)) {
+                    Ok(_) => Decoded::new(program.get(pc)),
+                    Err(_) => Decoded::default(),
+                }
+            }));
         }
+        self.cache = Arc::new(cache);
     }
 
-    pub fn program(&self) -> &Vec<i64> {
+    pub fn program(&self) -> &M {
         &self.program
     }
+
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    pub fn set_pc(&mut self, pc: usize) {
+        self.pc = pc;
+    }
+
+    pub fn base(&self) -> i64 {
+        self.base
+    }
+
+    pub fn set_base(&mut self, base: i64) {
+        self.base = base;
+    }
+
+    pub fn input(&self) -> &VecDeque<i64> {
+        &self.input
+    }
+
+    pub fn peek(&self, address: usize) -> i64 {
+        self.program.get(address)
+    }
+
+    pub fn poke(&mut self, address: usize, value: i64) {
+        self.program.set(address, value);
+        self.invalidate(address);
+    }
+
+    pub fn instruction(&self) -> Result<Instruction, IntcodeError> {
+        if self.pc >= self.program.len() {
+            return Err(IntcodeError::PcOutOfRange { pc: self.pc });
+        }
+        Instruction::try_decode(self.pc, |address| self.program.get(address))
+    }
+
+    fn overflow(&self) -> IntcodeError {
+        IntcodeError::Overflow {
+            pc: self.pc,
+            instruction: self.program.get(self.pc),
+        }
+    }
+
+    fn negative_address(&self, address: i64) -> IntcodeError {
+        IntcodeError::NegativeAddress {
+            pc: self.pc,
+            instruction: self.program.get(self.pc),
+            address,
+        }
+    }
+
+    #[inline]
+    fn read(&self, value: &InputValue) -> Result<i64, IntcodeError> {
+        match *value {
+            InputValue::Immediate(value) => Ok(value),
+            _ => match value.address(self.base) {
+                Some(pos) if pos < 0 => Err(self.negative_address(pos)),
+                Some(pos) => Ok(self.program.get(pos as usize)),
+                None => Err(self.overflow()),
+            },
+        }
+    }
+
+    #[inline]
+    fn write(&mut self, result: &OutputValue, value: i64) -> Result<(), IntcodeError> {
+        let pos = result.address(self.base).ok_or_else(|| self.overflow())?;
+        if pos < 0 {
+            return Err(self.negative_address(pos));
+        }
+        let pos = pos as usize;
+        if let Some(limit) = self.memory_limit {
+            if self.program.size_with(pos) > limit {
+                return Err(IntcodeError::MemoryLimit {
+                    pc: self.pc,
+                    instruction: self.program.get(self.pc),
+                    address: pos,
+                });
+            }
+        }
+        if let Some(patches) = &mut self.patches {
+            patches.write(self.pc, pos, self.program.get(pos), value);
+        }
+        self.program.set(pos, value);
+        self.invalidate(pos);
+        Ok(())
+    }
+
+    #[inline]
+    fn invalidate(&mut self, address: usize) {
+        // operands are always read from memory, so only the instruction word matters, and
+        // words that never decoded have nothing to clear
+        if matches!(self.cache.get(address), Some(entry) if entry.opcode != 0) {
+            Arc::make_mut(&mut self.cache)[address] = Decoded::default();
+        }
+    }
+
+    #[inline]
+    fn decode(&mut self) -> Result<Instruction, IntcodeError> {
+        match self.cache.get(self.pc) {
+            Some(decoded) if decoded.opcode != 0 => Ok(decoded.instruction(&self.program, self.pc)),
+            _ => {
+                let instr = self.instruction()?;
+                if self.use_cache && self.pc < MAX_CACHED_PC {
+                    let cache = Arc::make_mut(&mut self.cache);
+                    if self.pc >= cache.len() {
+                        cache.resize(self.pc + 1, Decoded::default());
+                    }
+                    cache[self.pc] = Decoded::new(self.program.get(self.pc));
+                }
+                Ok(instr)
+            }
+        }
+    }
+
+    #[inline]
+    fn jump(&mut self, target: &InputValue) -> Result<(), IntcodeError> {
+        let target = self.read(target)?;
+        if target < 0 {
+            return Err(self.negative_address(target));
+        }
+        self.pc = target as usize;
+        Ok(())
+    }
 }
 
-impl Machine for ProgramMachine {
+impl<M: Memory> Machine for ProgramMachine<M> {
     fn add_input(&mut self, input: i64) {
         self.input.push_back(input);
     }
 
-    fn step(&mut self) -> StepResult {
-        let program = &mut self.program;
-        let base = &mut self.base;
-        let instr = Instruction::parse(&program, self.pc);
+    fn try_step(&mut self) -> Result<StepResult, IntcodeError> {
+        if self.coverage.is_none() && self.patches.is_none() {
+            return self.execute();
+        }
+        let pc = self.pc;
+        // decoded before it runs, so an instruction that patches itself counts as what ran
+        let instruction = self.instruction().ok();
+        if let (Some(patches), Some(instruction)) = (&mut self.patches, &instruction) {
+            patches.begin(pc, instruction.length());
+        }
+        // which way a jump goes, from its test rather than where the pc ends up, since the
+        // target may be the next instruction
+        let taken = match &instruction {
+            // a jump with an immediate test always goes the same way
+            Some(Instruction::JumpIfTrue(InputValue::Immediate(_), _))
+            | Some(Instruction::JumpIfFalse(InputValue::Immediate(_), _)) => None,
+            Some(Instruction::JumpIfTrue(test, _)) => self.read(test).ok().map(|test| test != 0),
+            Some(Instruction::JumpIfFalse(test, _)) => self.read(test).ok().map(|test| test == 0),
+            _ => None,
+        };
+        let result = self.execute();
+        if let Some(patches) = &mut self.patches {
+            patches.finish(matches!(result, Ok(result) if result != StepResult::NeedInput));
+            if let Ok(StepResult::Halt) = result {
+                patches.halt();
+            }
+        }
+        let result = result?;
+        let coverage = match self.coverage.as_mut() {
+            Some(coverage) => coverage,
+            None => return Ok(result),
+        };
+        match (result, taken) {
+            // the instruction runs again once there is input
+            (StepResult::NeedInput, _) => {}
+            (_, Some(taken)) => coverage.record_branch(pc, taken),
+            (_, None) => coverage.record(pc),
+        }
+        Ok(result)
+    }
+}
+
+impl<M: Memory> ProgramMachine<M> {
+    #[inline]
+    fn execute(&mut self) -> Result<StepResult, IntcodeError> {
+        let instr = self.decode()?;
         match &instr {
             Instruction::Add(left, right, result) => {
-                result.write(
-                    program,
-                    *base,
-                    left.read(program, *base) + right.read(program, *base),
-                );
+                let value = self.read(left)?.checked_add(self.read(right)?);
+                let value = value.ok_or_else(|| self.overflow())?;
+                self.write(result, value)?;
             }
             Instruction::Multiply(left, right, result) => {
-                result.write(
-                    program,
-                    *base,
-                    left.read(program, *base) * right.read(program, *base),
-                );
+                let value = self.read(left)?.checked_mul(self.read(right)?);
+                let value = value.ok_or_else(|| self.overflow())?;
+                self.write(result, value)?;
             }
             Instruction::Read(result) => match self.input.pop_front() {
                 Some(value) => {
-                    result.write(program, *base, value);
+                    self.write(result, value)?;
                 }
-                None => return StepResult::NeedInput,
+                None => return Ok(StepResult::NeedInput),
             },
             Instruction::Write(value) => {
+                let value = self.read(value)?;
                 self.pc += instr.length();
-                return StepResult::Output(value.read(program, *base));
+                return Ok(StepResult::Output(value));
             }
             Instruction::JumpIfTrue(test, jump) => {
-                if test.read(program, *base) != 0 {
-                    self.pc = jump.read(program, *base) as usize;
-                    return StepResult::Ok;
+                if self.read(test)? != 0 {
+                    self.jump(jump)?;
+                    return Ok(StepResult::Ok);
                 }
             }
             Instruction::JumpIfFalse(test, jump) => {
-                if test.read(program, *base) == 0 {
-                    self.pc = jump.read(program, *base) as usize;
-                    return StepResult::Ok;
+                if self.read(test)? == 0 {
+                    self.jump(jump)?;
+                    return Ok(StepResult::Ok);
                 }
             }
             Instruction::LessThan(left, right, result) => {
-                let test = left.read(program, *base) < right.read(program, *base);
-                result.write(program, *base, if test { 1 } else { 0 });
+                let test = self.read(left)? < self.read(right)?;
+                self.write(result, if test { 1 } else { 0 })?;
             }
             Instruction::Equals(left, right, result) => {
-                let test = left.read(program, *base) == right.read(program, *base);
-                result.write(program, *base, if test { 1 } else { 0 });
+                let test = self.read(left)? == self.read(right)?;
+                self.write(result, if test { 1 } else { 0 })?;
             }
             Instruction::RelativeBaseOffset(offset) => {
-                *base += offset.read(program, *base);
+                let base = self.base.checked_add(self.read(offset)?);
+                self.base = base.ok_or_else(|| self.overflow())?;
             }
-            Instruction::Halt => return StepResult::Halt,
+            Instruction::Halt => return Ok(StepResult::Halt),
         };
         self.pc += instr.length();
-        StepResult::Ok
+        Ok(StepResult::Ok)
     }
 }
 
-struct Chain<M1: Machine, M2: Machine> {
-    head: M1,
-    tail: M2,
+// A pipeline where every machine's output goes to the next machine's input.
+pub fn make_chain(machines: VecDeque<Box<dyn Machine>>) -> Box<dyn Machine> {
+    Box::new(network::chain(machines))
 }
 
-impl<M1: Machine, M2: Machine> Chain<M1, M2> {
-    fn new(head: M1, tail: M2) -> Chain<M1, M2> {
-        Chain { head, tail }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::parse_list;
+    use std::time::Duration;
+
+    fn try_run(program: Vec<i64>) -> Result<Vec<i64>, IntcodeError> {
+        ProgramMachine::new(program, vec![]).try_run()
     }
-}
 
-impl<M1: Machine, M2: Machine> Machine for Chain<M1, M2> {
-    fn add_input(&mut self, value: i64) {
-        self.head.add_input(value);
+    #[test]
+    fn test_invalid_opcode() {
+        assert_eq!(
+            try_run(vec![1101, 1, 2, 5, 42, 0]),
+            Err(IntcodeError::InvalidOpcode {
+                pc: 4,
+                instruction: 42
+            })
+        );
     }
 
-    fn step(&mut self) -> StepResult {
-        let head_result = self.head.step();
-        if let StepResult::Output(value) = head_result {
-            // forward outputs from head to tail
-            self.tail.add_input(value);
-        }
-        let tail_result = self.tail.step();
-        match (head_result, tail_result) {
-            (_, StepResult::Output(value)) => {
-                // output from tail
-                StepResult::Output(value)
-            }
-            (_, StepResult::Halt) => {
-                // if tail has halted, then head must have halted as well
-                // since it can no longer output values
-                StepResult::Halt
-            }
-            (StepResult::Halt, _) => {
-                // if head has halted but tail hasn't yet,
-                // continue running tail
-                StepResult::Ok
-            }
-            (StepResult::Output(_), _) => {
-                // output from head was already forwarded internally
-                StepResult::Ok
+    #[test]
+    fn test_invalid_mode() {
+        assert_eq!(
+            try_run(vec![301, 0, 0, 0, 99]),
+            Err(IntcodeError::InvalidMode {
+                pc: 0,
+                instruction: 301,
+                mode: 3
+            })
+        );
+        assert_eq!(
+            try_run(vec![11101, 1, 2, 3, 99]),
+            Err(IntcodeError::ImmediateWrite {
+                pc: 0,
+                instruction: 11101
+            })
+        );
+    }
+
+    #[test]
+    fn test_negative_address() {
+        assert_eq!(
+            try_run(vec![109, -5, 22101, 1, 0, 0, 99]),
+            Err(IntcodeError::NegativeAddress {
+                pc: 2,
+                instruction: 22101,
+                address: -5
+            })
+        );
+        assert_eq!(
+            try_run(vec![1105, 1, -1]),
+            Err(IntcodeError::NegativeAddress {
+                pc: 0,
+                instruction: 1105,
+                address: -1
+            })
+        );
+    }
+
+    #[test]
+    fn test_relative_overflow() {
+        // reads and writes one past a relative base of i64::MAX
+        for program in &[
+            vec![109, i64::MAX, 204, 1, 99],
+            vec![109, i64::MAX, 203, 1, 99],
+        ] {
+            let mut machine = ProgramMachine::new(program.clone(), vec![1]);
+            assert!(matches!(
+                machine.try_run(),
+                Err(IntcodeError::Overflow { pc: 2, .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_pc_out_of_range() {
+        assert_eq!(
+            try_run(vec![1101, 1, 2, 0]),
+            Err(IntcodeError::PcOutOfRange { pc: 4 })
+        );
+    }
+
+    #[test]
+    fn test_missing_input() {
+        assert_eq!(try_run(vec![3, 0, 99]), Err(IntcodeError::MissingInput));
+        assert_eq!(
+            ProgramMachine::new(vec![3, 0, 4, 0, 99], vec![7]).try_run(),
+            Ok(vec![7])
+        );
+    }
+
+    #[test]
+    fn test_self_modifying() {
+        // the first instruction turns the halt at 4 into an output of [0]
+        let program = vec![1101, 4, 0, 4, 99, 0, 99];
+        for &use_cache in &[true, false] {
+            let mut machine = ProgramMachine::new(program.clone(), vec![]);
+            machine.set_decode_cache(use_cache);
+            assert_eq!(machine.run(), vec![1101]);
+        }
+
+        // the loop patches its own output operand on every iteration
+        let program = vec![104, 0, 1001, 1, 1, 1, 1007, 1, 3, 14, 1005, 14, 0, 99, 0];
+        for &use_cache in &[true, false] {
+            let mut machine = ProgramMachine::new(program.clone(), vec![]);
+            machine.set_decode_cache(use_cache);
+            assert_eq!(machine.run(), vec![0, 1, 2]);
+        }
+    }
+
+    #[test]
+    fn test_decode_cache() {
+        let program = parse_list(include_str!("bin/day9/input"), ',');
+        let mut cached = ProgramMachine::new(program, vec![2]);
+        let mut uncached = cached.clone();
+        uncached.set_decode_cache(false);
+        loop {
+            let result = cached.step();
+            assert_eq!(format!("{:?}", result), format!("{:?}", uncached.step()));
+            assert_eq!(cached.pc(), uncached.pc());
+            if let StepResult::Halt = result {
+                break;
             }
-            (head_result, _) => head_result,
         }
+        assert_eq!(cached.program(), uncached.program());
     }
-}
 
-pub fn make_chain(mut machines: VecDeque<Box<dyn Machine>>) -> Box<dyn Machine> {
-    let head = machines.pop_front().expect("expected at least one machine");
-    if machines.is_empty() {
-        head
-    } else {
-        Box::new(Chain::new(head, make_chain(machines)))
+    #[test]
+    fn test_shared_decode_cache() {
+        // outputs [5], after the clone below patches it into outputting #7
+        let program = vec![4, 5, 99, 0, 0, 5];
+        let original = ProgramMachine::new(program, vec![]);
+        let mut patched = original.clone();
+        patched.poke(0, 104);
+        patched.poke(1, 7);
+        assert_eq!(patched.run(), vec![7]);
+        assert_eq!(original.clone().run(), vec![5]);
+    }
+
+    // stores 3 at 10^12, reads it back along with an unwritten cell, and jumps there
+    const HUGE_ADDRESS: &[i64] = &[
+        1101,
+        3,
+        0,
+        1000000000000,
+        4,
+        1000000000000,
+        4,
+        2000000000000,
+        1105,
+        1,
+        1000000000000,
+    ];
+
+    #[test]
+    fn test_sparse_memory() {
+        let mut machine =
+            ProgramMachine::with_memory(SparseMemory::from(HUGE_ADDRESS.to_vec()), vec![]);
+        assert_eq!(machine.run_to_output(), Some(3));
+        assert_eq!(machine.run_to_output(), Some(0));
+        machine.step();
+        // 3 at 10^12 decodes as an input instruction
+        assert!(matches!(machine.try_step(), Ok(StepResult::NeedInput)));
+        assert_eq!(machine.pc(), 1_000_000_000_000);
+    }
+
+    #[test]
+    fn test_paged_memory() {
+        let mut machine =
+            ProgramMachine::with_memory(PagedMemory::from(HUGE_ADDRESS.to_vec()), vec![]);
+        assert_eq!(machine.run_to_output(), Some(3));
+        assert_eq!(machine.run_to_output(), Some(0));
+        assert_eq!(machine.program().len(), 1_000_000_000_001);
+    }
+
+    #[test]
+    fn test_memory_limit() {
+        let mut machine = ProgramMachine::new(HUGE_ADDRESS.to_vec(), vec![]);
+        machine.set_memory_limit(Some(1 << 20));
+        assert_eq!(
+            machine.try_step().unwrap_err(),
+            IntcodeError::MemoryLimit {
+                pc: 0,
+                instruction: 1101,
+                address: 1_000_000_000_000,
+            }
+        );
+        assert_eq!(machine.program().len(), HUGE_ADDRESS.len());
+
+        let mut machine =
+            ProgramMachine::with_memory(SparseMemory::from(HUGE_ADDRESS.to_vec()), vec![]);
+        machine.set_memory_limit(Some(HUGE_ADDRESS.len()));
+        assert!(machine.try_step().is_err());
+        machine.set_memory_limit(Some(HUGE_ADDRESS.len() + 1));
+        assert_eq!(machine.run_to_output(), Some(3));
+    }
+
+    #[test]
+    fn test_budget() {
+        // outputs 1, then loops forever
+        let program = vec![104, 1, 1105, 1, 2];
+        let mut machine = ProgramMachine::new(program, vec![]);
+        let mut budget = Budget::steps(100);
+        assert_eq!(
+            machine.run_within(&mut budget),
+            Outcome::BudgetExhausted(vec![1])
+        );
+        assert_eq!(machine.pc(), 2);
+        assert_eq!(
+            machine.read_string_within(&mut Budget::timeout(Duration::from_millis(10))),
+            Outcome::BudgetExhausted(String::new())
+        );
+
+        // resuming with a fresh budget continues where the machine stopped
+        let program = vec![1001, 10, -1, 10, 1005, 10, 0, 104, 7, 99, 50];
+        let mut machine = ProgramMachine::new(program, vec![]);
+        let mut budget = Budget::steps(60);
+        assert_eq!(
+            machine.run_to_output_within(&mut budget),
+            Outcome::BudgetExhausted(None)
+        );
+        assert_eq!(
+            machine.run_to_output_within(&mut Budget::steps(60)),
+            Outcome::Done(Some(7))
+        );
+        assert_eq!(
+            machine.run_within(&mut Budget::unlimited()),
+            Outcome::Done(vec![])
+        );
+    }
+
+    #[test]
+    fn test_chain() {
+        // three machines that each add one to their input
+        let program = vec![3, 9, 1001, 9, 1, 9, 4, 9, 99, 0];
+        let machines: Vec<Box<dyn Machine>> = (0..3)
+            .map(|_| Box::new(ProgramMachine::new(program.clone(), vec![])) as Box<dyn Machine>)
+            .collect();
+        let mut chain = make_chain(VecDeque::from(machines));
+        chain.add_input(5);
+        assert_eq!(chain.run(), vec![8]);
+
+        // halts with its last machine, even though the first one never does
+        let echo = ProgramMachine::new(vec![3, 0, 4, 0, 1105, 1, 0], vec![]);
+        let once = ProgramMachine::new(vec![3, 0, 4, 0, 99], vec![]);
+        let machines: Vec<Box<dyn Machine>> = vec![Box::new(echo), Box::new(once)];
+        let mut chain = make_chain(VecDeque::from(machines));
+        chain.add_input(5);
+        assert_eq!(chain.try_run(), Ok(vec![5]));
     }
 }
@@ -0,0 +1,585 @@
+use std::collections::{BTreeSet, VecDeque};
+use std::fmt::Write;
+use std::mem;
+
+use crate::intcode::cfg::analyze;
+use crate::intcode::{
+    InputValue, Instruction, IntcodeError, Machine, OutputValue, ProgramMachine, StepResult,
+};
+
+const VALUES_PER_LINE: usize = 12;
+
+// The state of a translated program, and the helpers its generated code calls.
+//
+// The generated code only stays valid while the program's code is unchanged. As soon as a
+// write lands in a translated instruction, the runtime hands everything over to a
+// `ProgramMachine`, which runs the program from there. The same happens when the program
+// jumps to an address where no translated block starts.
+#[derive(Debug, Clone)]
+pub struct Runtime {
+    memory: Vec<i64>,
+    pc: usize,
+    base: i64,
+    input: VecDeque<i64>,
+    // sorted, non-overlapping address ranges of the translated instructions
+    code: &'static [(usize, usize)],
+    interpreter: Option<ProgramMachine>,
+}
+
+impl Runtime {
+    pub fn new(program: &[i64], code: &'static [(usize, usize)], input: Vec<i64>) -> Runtime {
+        Runtime {
+            memory: program.to_vec(),
+            pc: 0,
+            base: 0,
+            input: input.into(),
+            code,
+            interpreter: None,
+        }
+    }
+
+    pub fn pc(&self) -> usize {
+        match &self.interpreter {
+            Some(machine) => machine.pc(),
+            None => self.pc,
+        }
+    }
+
+    pub fn peek(&self, address: usize) -> i64 {
+        match &self.interpreter {
+            Some(machine) => machine.peek(address),
+            None => self.memory.get(address).copied().unwrap_or(0),
+        }
+    }
+
+    pub fn is_interpreted(&self) -> bool {
+        self.interpreter.is_some()
+    }
+
+    fn is_code(&self, address: usize) -> bool {
+        let index = self.code.partition_point(|&(_, end)| end <= address);
+        matches!(self.code.get(index), Some(&(start, _)) if start <= address)
+    }
+
+    fn negative_address(&self, pc: usize, address: i64) -> IntcodeError {
+        IntcodeError::NegativeAddress {
+            pc,
+            instruction: self.memory[pc],
+            address,
+        }
+    }
+
+    fn overflow(&self, pc: usize) -> IntcodeError {
+        IntcodeError::Overflow {
+            pc,
+            instruction: self.memory[pc],
+        }
+    }
+
+    #[inline]
+    pub fn relative(&self, pc: usize, offset: i64) -> Result<i64, IntcodeError> {
+        self.base
+            .checked_add(offset)
+            .ok_or_else(|| self.overflow(pc))
+    }
+
+    #[inline]
+    pub fn read(&self, pc: usize, address: i64) -> Result<i64, IntcodeError> {
+        if address < 0 {
+            return Err(self.negative_address(pc, address));
+        }
+        Ok(self.memory.get(address as usize).copied().unwrap_or(0))
+    }
+
+    // Returns true if the write changed translated code, after which the generated code
+    // must fall back to the interpreter.
+    #[inline]
+    pub fn write(&mut self, pc: usize, address: i64, value: i64) -> Result<bool, IntcodeError> {
+        if address < 0 {
+            return Err(self.negative_address(pc, address));
+        }
+        let address = address as usize;
+        if address >= self.memory.len() {
+            self.memory.resize(address + 1, 0);
+        }
+        let changed = self.memory[address] != value;
+        self.memory[address] = value;
+        Ok(changed && self.is_code(address))
+    }
+
+    #[inline]
+    pub fn add(&self, pc: usize, left: i64, right: i64) -> Result<i64, IntcodeError> {
+        left.checked_add(right).ok_or_else(|| self.overflow(pc))
+    }
+
+    #[inline]
+    pub fn mul(&self, pc: usize, left: i64, right: i64) -> Result<i64, IntcodeError> {
+        left.checked_mul(right).ok_or_else(|| self.overflow(pc))
+    }
+
+    #[inline]
+    pub fn offset_base(&mut self, pc: usize, offset: i64) -> Result<(), IntcodeError> {
+        self.base = self.add(pc, self.base, offset)?;
+        Ok(())
+    }
+
+    // Takes the next input value, or stops at `pc` until there is one.
+    #[inline]
+    pub fn input(&mut self, pc: usize) -> Option<i64> {
+        let value = self.input.pop_front();
+        if value.is_none() {
+            self.pc = pc;
+        }
+        value
+    }
+
+    #[inline]
+    pub fn output(&mut self, next: usize, value: i64) -> StepResult {
+        self.pc = next;
+        StepResult::Output(value)
+    }
+
+    #[inline]
+    pub fn jump(&mut self, pc: usize, target: i64) -> Result<StepResult, IntcodeError> {
+        if target < 0 {
+            return Err(self.negative_address(pc, target));
+        }
+        self.pc = target as usize;
+        Ok(StepResult::Ok)
+    }
+
+    #[inline]
+    pub fn goto(&mut self, next: usize) -> StepResult {
+        self.pc = next;
+        StepResult::Ok
+    }
+
+    #[inline]
+    pub fn halt(&mut self, pc: usize) -> StepResult {
+        self.pc = pc;
+        StepResult::Halt
+    }
+
+    // Hands the program over to the interpreter, which continues at `next`.
+    pub fn fall_back(&mut self, next: usize) -> StepResult {
+        let mut machine = ProgramMachine::new(mem::take(&mut self.memory), vec![]);
+        machine.pc = next;
+        machine.base = self.base;
+        machine.input = mem::take(&mut self.input);
+        self.pc = next;
+        self.interpreter = Some(machine);
+        StepResult::Ok
+    }
+}
+
+// Generated code dispatches to its own blocks, and only calls this for a pc where no
+// translated block starts, or once the interpreter has taken over.
+impl Machine for Runtime {
+    fn add_input(&mut self, value: i64) {
+        match &mut self.interpreter {
+            Some(machine) => machine.add_input(value),
+            None => self.input.push_back(value),
+        }
+    }
+
+    fn try_step(&mut self) -> Result<StepResult, IntcodeError> {
+        if self.interpreter.is_none() {
+            self.fall_back(self.pc);
+        }
+        self.interpreter.as_mut().unwrap().try_step()
+    }
+}
+
+// The blocks of the control-flow graph, split further so that every input instruction
+// starts a block and every output instruction ends one. A generated machine can then stop
+// for input or output and pick up again at the start of a block.
+fn blocks(program: &[i64]) -> Vec<Vec<(usize, Instruction)>> {
+    let mut blocks = Vec::new();
+    for block in analyze(program).blocks.values() {
+        let mut current = Vec::new();
+        for &(pc, instruction) in &block.instructions {
+            if let Instruction::Read(_) = instruction {
+                if !current.is_empty() {
+                    blocks.push(mem::take(&mut current));
+                }
+            }
+            current.push((pc, instruction));
+            if let Instruction::Write(_) = instruction {
+                blocks.push(mem::take(&mut current));
+            }
+        }
+        if !current.is_empty() {
+            blocks.push(current);
+        }
+    }
+    blocks
+}
+
+// Positions the program writes to directly. An operand stored at one of them is read from
+// memory when the instruction runs, so patching it doesn't make the code invalid.
+fn patched(blocks: &[Vec<(usize, Instruction)>]) -> BTreeSet<usize> {
+    blocks
+        .iter()
+        .flatten()
+        .filter_map(|(_, instruction)| match instruction.output() {
+            Some(OutputValue::Position(pos)) if pos >= 0 => Some(pos as usize),
+            _ => None,
+        })
+        .collect()
+}
+
+// The words the generated code depends on: every instruction word, and every operand that is
+// not read from memory.
+fn code_ranges(
+    blocks: &[Vec<(usize, Instruction)>],
+    patched: &BTreeSet<usize>,
+) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &(pc, instruction) in blocks.iter().flatten() {
+        let words = (pc..pc + instruction.length())
+            .filter(|&address| address == pc || !patched.contains(&address));
+        for address in words {
+            match ranges.last_mut() {
+                Some((_, end)) if *end == address => *end += 1,
+                _ => ranges.push((address, address + 1)),
+            }
+        }
+    }
+    ranges
+}
+
+// Generates the expressions for the operands of the instruction at `pc`.
+struct Operands<'a> {
+    pc: usize,
+    patched: &'a BTreeSet<usize>,
+}
+
+impl<'a> Operands<'a> {
+    // The value of an immediate operand, if it can't change.
+    fn constant(&self, index: usize, value: &InputValue) -> Option<i64> {
+        match *value {
+            InputValue::Immediate(value) if !self.patched.contains(&(self.pc + index)) => {
+                Some(value)
+            }
+            _ => None,
+        }
+    }
+
+    fn word(&self, index: usize, value: i64) -> String {
+        if self.patched.contains(&(self.pc + index)) {
+            format!("self.0.read({}, {})?", self.pc, self.pc + index)
+        } else {
+            value.to_string()
+        }
+    }
+
+    fn read(&self, index: usize, value: &InputValue) -> String {
+        match *value {
+            InputValue::Immediate(value) => self.word(index, value),
+            InputValue::Position(pos) => {
+                format!("self.0.read({}, {})?", self.pc, self.word(index, pos))
+            }
+            InputValue::Relative(pos) => format!(
+                "self.0.read({}, self.0.relative({}, {})?)?",
+                self.pc,
+                self.pc,
+                self.word(index, pos)
+            ),
+        }
+    }
+
+    fn address(&self, index: usize, result: &OutputValue) -> String {
+        match *result {
+            OutputValue::Position(pos) => self.word(index, pos),
+            OutputValue::Relative(pos) => {
+                format!("self.0.relative({}, {})?", self.pc, self.word(index, pos))
+            }
+        }
+    }
+
+    fn write(&self, code: &mut String, index: usize, result: &OutputValue, next: usize) {
+        writeln!(
+            code,
+            "        if self.0.write({}, {}, value)? {{\n            \
+             return Ok(self.0.fall_back({}));\n        }}",
+            self.pc,
+            self.address(index, result),
+            next
+        )
+        .unwrap();
+    }
+}
+
+// Generates the code for one instruction of a block. Returns true if it always returns from
+// the block's function.
+fn translate_instruction(
+    code: &mut String,
+    pc: usize,
+    instruction: &Instruction,
+    patched: &BTreeSet<usize>,
+) -> bool {
+    let operands = Operands { pc, patched };
+    let next = pc + instruction.length();
+    writeln!(code, "        // {}: {}", pc, instruction).unwrap();
+    match instruction {
+        Instruction::Add(left, right, result) | Instruction::Multiply(left, right, result) => {
+            let op = if let Instruction::Add(_, _, _) = instruction {
+                "add"
+            } else {
+                "mul"
+            };
+            writeln!(
+                code,
+                "        let value = self.0.{}({}, {}, {})?;",
+                op,
+                pc,
+                operands.read(1, left),
+                operands.read(2, right)
+            )
+            .unwrap();
+            operands.write(code, 3, result, next);
+        }
+        Instruction::LessThan(left, right, result) | Instruction::Equals(left, right, result) => {
+            let less = matches!(instruction, Instruction::LessThan(_, _, _));
+            let value = match (operands.constant(1, left), operands.constant(2, right)) {
+                (Some(left), Some(right)) => {
+                    let test = if less { left < right } else { left == right };
+                    (test as i64).to_string()
+                }
+                _ => format!(
+                    "({} {} {}) as i64",
+                    operands.read(1, left),
+                    if less { "<" } else { "==" },
+                    operands.read(2, right)
+                ),
+            };
+            writeln!(code, "        let value = {};", value).unwrap();
+            operands.write(code, 3, result, next);
+        }
+        Instruction::Read(result) => {
+            writeln!(
+                code,
+                "        let value = match self.0.input({}) {{\n            \
+                 Some(value) => value,\n            \
+                 None => return Ok(StepResult::NeedInput),\n        }};",
+                pc
+            )
+            .unwrap();
+            operands.write(code, 1, result, next);
+        }
+        Instruction::Write(value) => {
+            let value = operands.read(1, value);
+            writeln!(code, "        Ok(self.0.output({}, {}))", next, value).unwrap();
+            return true;
+        }
+        Instruction::JumpIfTrue(test, target) | Instruction::JumpIfFalse(test, target) => {
+            let op = if let Instruction::JumpIfTrue(_, _) = instruction {
+                "!="
+            } else {
+                "=="
+            };
+            let target = operands.read(2, target);
+            match operands.constant(1, test) {
+                // the test has the same outcome every time
+                Some(value) => {
+                    if (value != 0) == (op == "!=") {
+                        writeln!(code, "        self.0.jump({}, {})", pc, target).unwrap();
+                        return true;
+                    }
+                }
+                None => writeln!(
+                    code,
+                    "        if {} {} 0 {{\n            return self.0.jump({}, {});\n        }}",
+                    operands.read(1, test),
+                    op,
+                    pc,
+                    target
+                )
+                .unwrap(),
+            }
+        }
+        Instruction::RelativeBaseOffset(offset) => {
+            let offset = operands.read(1, offset);
+            writeln!(code, "        self.0.offset_base({}, {})?;", pc, offset).unwrap();
+        }
+        Instruction::Halt => {
+            writeln!(code, "        Ok(self.0.halt({}))", pc).unwrap();
+            return true;
+        }
+    }
+    false
+}
+
+fn write_values<T: ToString>(code: &mut String, values: &[T]) {
+    for line in values.chunks(VALUES_PER_LINE) {
+        let line = line.iter().map(|value| value.to_string());
+        writeln!(code, "    {},", line.collect::<Vec<_>>().join(", ")).unwrap();
+    }
+}
+
+// Translates `program` into the source of a Rust module, with a machine called `name` that
+// implements `Machine`. Every reachable block becomes a method, and each step runs one of
+// them. The generated code refers to this crate as `advent_of_code_2019`, so it can be
+// included in any of the binaries.
+pub fn translate(program: &[i64], name: &str) -> String {
+    let blocks = blocks(program);
+    let patched = patched(&blocks);
+    let mut code = String::new();
+    writeln!(
+        code,
+        "// Generated by intcode-aot. Do not edit.\n\n\
+         use advent_of_code_2019::intcode::aot::Runtime;\n\
+         use advent_of_code_2019::intcode::{{IntcodeError, Machine, StepResult}};\n"
+    )
+    .unwrap();
+
+    writeln!(code, "const PROGRAM: &[i64] = &[").unwrap();
+    write_values(&mut code, program);
+    writeln!(code, "];\n").unwrap();
+    writeln!(code, "const CODE: &[(usize, usize)] = &[").unwrap();
+    let ranges = code_ranges(&blocks, &patched)
+        .iter()
+        .map(|(start, end)| format!("({}, {})", start, end))
+        .collect::<Vec<_>>();
+    write_values(&mut code, &ranges);
+    writeln!(code, "];\n").unwrap();
+
+    writeln!(
+        code,
+        "#[derive(Debug, Clone)]\n\
+         pub struct {name}(Runtime);\n\n\
+         impl {name} {{\n    \
+             pub fn new(input: Vec<i64>) -> {name} {{\n        \
+                 {name}(Runtime::new(PROGRAM, CODE, input))\n    \
+             }}\n\n    \
+             #[allow(dead_code)]\n    \
+             pub fn runtime(&self) -> &Runtime {{\n        \
+                 &self.0\n    \
+             }}\n\
+         }}\n",
+        name = name
+    )
+    .unwrap();
+
+    writeln!(
+        code,
+        "impl Machine for {} {{\n    \
+             fn add_input(&mut self, value: i64) {{\n        \
+                 self.0.add_input(value);\n    \
+             }}\n\n    \
+             fn try_step(&mut self) -> Result<StepResult, IntcodeError> {{\n        \
+                 if self.0.is_interpreted() {{\n            \
+                     return self.0.try_step();\n        \
+                 }}\n        \
+                 match self.0.pc() {{",
+        name
+    )
+    .unwrap();
+    for block in &blocks {
+        let start = block[0].0;
+        writeln!(code, "            {} => self.block_{}(),", start, start).unwrap();
+    }
+    writeln!(
+        code,
+        "            _ => self.0.try_step(),\n        \
+                 }}\n    \
+             }}\n\
+         }}\n"
+    )
+    .unwrap();
+
+    writeln!(code, "impl {} {{", name).unwrap();
+    for (index, block) in blocks.iter().enumerate() {
+        if index > 0 {
+            writeln!(code).unwrap();
+        }
+        writeln!(
+            code,
+            "    fn block_{}(&mut self) -> Result<StepResult, IntcodeError> {{",
+            block[0].0
+        )
+        .unwrap();
+        let mut returns = false;
+        for (pc, instruction) in block {
+            returns = translate_instruction(&mut code, *pc, instruction, &patched);
+        }
+        if !returns {
+            let (pc, instruction) = block.last().unwrap();
+            writeln!(
+                code,
+                "        Ok(self.0.goto({}))",
+                pc + instruction.length()
+            )
+            .unwrap();
+        }
+        writeln!(code, "    }}").unwrap();
+    }
+    writeln!(code, "}}").unwrap();
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::parse_list;
+
+    #[test]
+    fn test_blocks() {
+        // reads a value, then echoes it until it counts down to zero
+        let program = vec![3, 12, 4, 12, 1001, 12, -1, 12, 1005, 12, 2, 99, 0];
+        let starts = blocks(&program)
+            .iter()
+            .map(|block| block[0].0)
+            .collect::<Vec<_>>();
+        assert_eq!(starts, vec![0, 2, 4, 11]);
+        assert_eq!(
+            code_ranges(&blocks(&program), &patched(&blocks(&program))),
+            vec![(0, 12)]
+        );
+    }
+
+    #[test]
+    fn test_translate() {
+        let program = vec![3, 12, 4, 12, 1001, 12, -1, 12, 1005, 12, 2, 99, 0];
+        let code = translate(&program, "Echo");
+        assert!(code.contains("pub struct Echo(Runtime);"));
+        assert!(code.contains(
+            "    fn block_4(&mut self) -> Result<StepResult, IntcodeError> {\n        \
+             // 4: add [12], #-1, [12]\n        \
+             let value = self.0.add(4, self.0.read(4, 12)?, -1)?;\n        \
+             if self.0.write(4, 12, value)? {\n            \
+             return Ok(self.0.fall_back(8));\n        \
+             }\n        \
+             // 8: jt [12], #2\n        \
+             if self.0.read(8, 12)? != 0 {\n            \
+             return self.0.jump(8, 2);\n        \
+             }\n        \
+             Ok(self.0.goto(11))\n    \
+             }\n"
+        ));
+    }
+
+    #[test]
+    fn test_runtime_falls_back() {
+        static CODE: &[(usize, usize)] = &[(0, 4)];
+        let mut runtime = Runtime::new(&[1101, 2, 3, 5, 99, 0], CODE, vec![7]);
+        // writes outside the code, or that don't change it, are fine
+        assert_eq!(runtime.write(0, 5, 1), Ok(false));
+        assert_eq!(runtime.write(0, 1, 2), Ok(false));
+        assert_eq!(runtime.write(0, 3, 4), Ok(true));
+        assert!(matches!(runtime.fall_back(4), StepResult::Ok));
+        assert!(runtime.is_interpreted());
+        assert_eq!(runtime.pc(), 4);
+        assert_eq!(runtime.peek(3), 4);
+        assert!(matches!(runtime.step(), StepResult::Halt));
+    }
+
+    #[test]
+    fn test_day19_is_up_to_date() {
+        let program: Vec<i64> = parse_list(include_str!("../bin/day19/input"), ',');
+        assert_eq!(
+            translate(&program, "Probe"),
+            include_str!("../bin/day19/probe.rs")
+        );
+    }
+}
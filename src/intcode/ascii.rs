@@ -0,0 +1,243 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::intcode::{IntcodeError, Machine, StepResult};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Event {
+    // a line of text, without the newline
+    Line(String),
+    // the machine needs input; holds whatever it printed on the current line so far
+    Prompt(String),
+    // an output that isn't an ASCII character
+    Value(i64),
+    Halt,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum AsciiError {
+    Machine(IntcodeError),
+    Mismatch {
+        expected: String,
+        found: String,
+        output: Vec<String>,
+    },
+    Unexpected {
+        expected: String,
+        event: Event,
+        output: Vec<String>,
+    },
+}
+
+impl fmt::Display for AsciiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let output = match self {
+            AsciiError::Machine(error) => return write!(f, "{}", error),
+            AsciiError::Mismatch {
+                expected,
+                found,
+                output,
+            } => {
+                write!(f, "expected prompt {:?}, but got {:?}", expected, found)?;
+                output
+            }
+            AsciiError::Unexpected {
+                expected,
+                event,
+                output,
+            } => {
+                write!(f, "expected {}, but got {:?}", expected, event)?;
+                output
+            }
+        };
+        if !output.is_empty() {
+            write!(f, ", after:")?;
+            for line in output {
+                write!(f, "\n{}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Error for AsciiError {}
+
+impl From<IntcodeError> for AsciiError {
+    fn from(error: IntcodeError) -> AsciiError {
+        AsciiError::Machine(error)
+    }
+}
+
+// Talks to a machine that speaks ASCII, one line at a time.
+pub struct AsciiMachine<M: Machine> {
+    machine: M,
+    line: String,
+}
+
+impl<M: Machine> AsciiMachine<M> {
+    pub fn new(machine: M) -> AsciiMachine<M> {
+        AsciiMachine {
+            machine,
+            line: String::new(),
+        }
+    }
+
+    pub fn machine(&self) -> &M {
+        &self.machine
+    }
+
+    pub fn machine_mut(&mut self) -> &mut M {
+        &mut self.machine
+    }
+
+    pub fn into_inner(self) -> M {
+        self.machine
+    }
+
+    pub fn send_line(&mut self, line: &str) {
+        self.machine.add_line(line);
+    }
+
+    pub fn try_next_event(&mut self) -> Result<Event, IntcodeError> {
+        loop {
+            match self.machine.try_step()? {
+                StepResult::Ok => {}
+                StepResult::Output(10) => return Ok(Event::Line(self.line.split_off(0))),
+                StepResult::Output(value) if (0..128).contains(&value) => {
+                    self.line.push(value as u8 as char)
+                }
+                StepResult::Output(value) => return Ok(Event::Value(value)),
+                StepResult::NeedInput => return Ok(Event::Prompt(self.line.split_off(0))),
+                // the last line may not end with a newline
+                StepResult::Halt if !self.line.is_empty() => {
+                    return Ok(Event::Line(self.line.split_off(0)))
+                }
+                StepResult::Halt => return Ok(Event::Halt),
+            }
+        }
+    }
+
+    pub fn next_event(&mut self) -> Event {
+        self.try_next_event()
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    // Reads up to the next prompt, and checks that it is `prompt`. A prompt is either the text
+    // before the input on the same line, or the line before it. Returns the lines before it.
+    pub fn expect_prompt(&mut self, prompt: &str) -> Result<Vec<String>, AsciiError> {
+        let mut output = Vec::new();
+        loop {
+            match self.try_next_event()? {
+                Event::Line(line) => output.push(line),
+                Event::Prompt(text) => {
+                    let found = if text.is_empty() {
+                        output.pop().unwrap_or_default()
+                    } else {
+                        text
+                    };
+                    if found == prompt {
+                        return Ok(output);
+                    }
+                    return Err(AsciiError::Mismatch {
+                        expected: prompt.into(),
+                        found,
+                        output,
+                    });
+                }
+                event => {
+                    return Err(AsciiError::Unexpected {
+                        expected: format!("prompt {:?}", prompt),
+                        event,
+                        output,
+                    })
+                }
+            }
+        }
+    }
+
+    // Reads up to the next non-ASCII value, and returns it with the lines before it.
+    pub fn expect_value(&mut self) -> Result<(Vec<String>, i64), AsciiError> {
+        let mut output = Vec::new();
+        loop {
+            match self.try_next_event()? {
+                Event::Line(line) => output.push(line),
+                Event::Value(value) => return Ok((output, value)),
+                event => {
+                    return Err(AsciiError::Unexpected {
+                        expected: "a value".into(),
+                        event,
+                        output,
+                    })
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::asm::assemble;
+    use crate::intcode::ProgramMachine;
+
+    fn ascii(source: &str) -> AsciiMachine<ProgramMachine> {
+        AsciiMachine::new(ProgramMachine::new(assemble(source).unwrap(), vec![]))
+    }
+
+    // prints "Hi" and "Go:" on separate lines, reads a character, then prints 1000 and "!"
+    const GREETER: &str = "
+        out #72
+        out #105
+        out #10
+        out #71
+        out #111
+        out #58
+        out #10
+        in [answer]
+        out #1000
+        out #33
+        hlt
+        answer: db 0
+    ";
+
+    #[test]
+    fn test_events() {
+        let mut machine = ascii(GREETER);
+        assert_eq!(machine.next_event(), Event::Line("Hi".into()));
+        assert_eq!(machine.next_event(), Event::Line("Go:".into()));
+        assert_eq!(machine.next_event(), Event::Prompt("".into()));
+        machine.send_line("y");
+        assert_eq!(machine.next_event(), Event::Value(1000));
+        assert_eq!(machine.next_event(), Event::Line("!".into()));
+        assert_eq!(machine.next_event(), Event::Halt);
+    }
+
+    #[test]
+    fn test_expect() {
+        let mut machine = ascii(GREETER);
+        assert_eq!(machine.expect_prompt("Go:"), Ok(vec!["Hi".to_string()]));
+        machine.send_line("y");
+        assert_eq!(machine.expect_value(), Ok((vec![], 1000)));
+        let error = machine.expect_value().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "expected a value, but got Halt, after:\n!"
+        );
+
+        let mut machine = ascii(GREETER);
+        assert_eq!(
+            machine.expect_prompt("Stop:"),
+            Err(AsciiError::Mismatch {
+                expected: "Stop:".into(),
+                found: "Go:".into(),
+                output: vec!["Hi".into()],
+            })
+        );
+    }
+
+    #[test]
+    fn test_prompt_on_same_line() {
+        let mut machine = ascii("out #62\nout #32\nin [0]\nhlt");
+        assert_eq!(machine.expect_prompt("> "), Ok(vec![]));
+    }
+}
@@ -0,0 +1,448 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use crate::intcode::{InputValue, Instruction, OutputValue};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for AsmError {}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Term {
+    Number(i64),
+    Label(String),
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Expr(Vec<(i64, Term)>);
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Operand {
+    Position(Expr),
+    Immediate(Expr),
+    Relative(Expr),
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Statement {
+    Instruction(String, Vec<Operand>),
+    Data(Vec<Expr>),
+}
+
+pub fn assemble(source: &str) -> Result<Vec<i64>, AsmError> {
+    // first pass: parse every line and assign addresses to labels
+    let mut labels = HashMap::new();
+    let mut statements = Vec::new();
+    let mut address = 0;
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let error = |message: String| AsmError {
+            line: line_number,
+            message,
+        };
+        let mut rest = strip_comment(line).trim();
+        while let Some(colon) = find_label(rest) {
+            let name = rest[..colon].trim();
+            if is_address(name) {
+                let expected = name
+                    .parse::<usize>()
+                    .map_err(|_| error(format!("invalid address {}", name)))?;
+                if expected != address {
+                    return Err(error(format!(
+                        "expected address {}, but got {}",
+                        expected, address
+                    )));
+                }
+            } else if labels.insert(name.to_string(), address as i64).is_some() {
+                return Err(error(format!("duplicate label {}", name)));
+            }
+            rest = rest[colon + 1..].trim();
+        }
+        if rest.is_empty() {
+            continue;
+        }
+        let statement = parse_statement(rest).map_err(error)?;
+        address += statement_length(&statement).map_err(error)?;
+        statements.push((line_number, statement));
+    }
+
+    // second pass: evaluate expressions and encode
+    let mut program = Vec::with_capacity(address);
+    for (line_number, statement) in statements {
+        let error = |message: String| AsmError {
+            line: line_number,
+            message,
+        };
+        match statement {
+            Statement::Data(values) => {
+                for value in values {
+                    program.push(evaluate(&value, &labels).map_err(error)?);
+                }
+            }
+            Statement::Instruction(mnemonic, operands) => {
+                let instruction = encode(&mnemonic, &operands, &labels).map_err(error)?;
+                program.extend(instruction.encode());
+            }
+        }
+    }
+    Ok(program)
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            ';' if !in_string => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+// Names can't start with a digit, so they are never mistaken for numbers.
+fn is_name(s: &str) -> bool {
+    match s.chars().next() {
+        Some(first) if !first.is_ascii_digit() => s
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.'),
+        _ => false,
+    }
+}
+
+fn is_address(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_digit())
+}
+
+fn find_label(s: &str) -> Option<usize> {
+    let colon = s.find(':')?;
+    let name = s[..colon].trim();
+    if is_name(name) || is_address(name) {
+        Some(colon)
+    } else {
+        None
+    }
+}
+
+fn split_operands(s: &str) -> Vec<&str> {
+    let mut operands = Vec::new();
+    let mut in_string = false;
+    let mut start = 0;
+    for (index, c) in s.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            ',' if !in_string => {
+                operands.push(s[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    operands.push(s[start..].trim());
+    operands
+}
+
+fn parse_statement(s: &str) -> Result<Statement, String> {
+    let (mnemonic, rest) = match s.find(char::is_whitespace) {
+        Some(index) => (&s[..index], s[index..].trim()),
+        None => (s, ""),
+    };
+    let mnemonic = mnemonic.to_lowercase();
+    let operands = if rest.is_empty() {
+        Vec::new()
+    } else {
+        split_operands(rest)
+    };
+    match &mnemonic[..] {
+        "db" | "data" => {
+            let mut values = Vec::new();
+            for operand in operands {
+                if operand.starts_with('"') {
+                    for c in parse_string(operand)?.chars() {
+                        values.push(Expr(vec![(1, Term::Number(c as i64))]));
+                    }
+                } else {
+                    values.push(parse_expr(operand)?);
+                }
+            }
+            Ok(Statement::Data(values))
+        }
+        _ => {
+            let operands = operands
+                .into_iter()
+                .map(parse_operand)
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(Statement::Instruction(mnemonic, operands))
+        }
+    }
+}
+
+fn parse_string(s: &str) -> Result<String, String> {
+    if s.len() < 2 || !s.ends_with('"') {
+        return Err(format!("unterminated string {}", s));
+    }
+    let mut result = String::new();
+    let mut chars = s[1..s.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') => result.push('\n'),
+                Some('"') => result.push('"'),
+                Some('\\') => result.push('\\'),
+                other => return Err(format!("invalid escape sequence \\{:?}", other)),
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    Ok(result)
+}
+
+fn parse_operand(s: &str) -> Result<Operand, String> {
+    if let Some(rest) = s.strip_prefix('#') {
+        Ok(Operand::Immediate(parse_expr(rest)?))
+    } else if s.starts_with('[') && s.ends_with(']') {
+        Ok(Operand::Position(parse_expr(&s[1..s.len() - 1])?))
+    } else if let Some(rest) = s.strip_prefix("rb") {
+        let rest = rest.trim();
+        if rest.is_empty() {
+            Ok(Operand::Relative(Expr(vec![(1, Term::Number(0))])))
+        } else if rest.starts_with('+') || rest.starts_with('-') {
+            Ok(Operand::Relative(parse_expr(rest)?))
+        } else {
+            Err(format!("invalid relative operand {}", s))
+        }
+    } else {
+        Err(format!("invalid operand {}", s))
+    }
+}
+
+fn parse_expr(s: &str) -> Result<Expr, String> {
+    let mut terms = Vec::new();
+    let mut sign = 1;
+    let mut term = String::new();
+    let push = |terms: &mut Vec<(i64, Term)>, sign: i64, term: &str| {
+        let term = term.trim();
+        if term.is_empty() {
+            return Err(format!("invalid expression {}", s));
+        }
+        if term.starts_with(|c: char| c.is_ascii_digit()) {
+            // parse negative numbers whole, so the smallest one fits
+            let value = match sign {
+                1 => term.parse::<i64>(),
+                _ => format!("-{}", term).parse::<i64>(),
+            };
+            let value = value.map_err(|_| format!("invalid number {}", term))?;
+            terms.push((1, Term::Number(value)));
+        } else if is_name(term) {
+            terms.push((sign, Term::Label(term.into())));
+        } else {
+            return Err(format!("invalid expression {}", s));
+        }
+        Ok(())
+    };
+    for c in s.chars() {
+        match c {
+            '+' | '-' if term.trim().is_empty() => {
+                if c == '-' {
+                    sign = -sign;
+                }
+            }
+            '+' | '-' => {
+                push(&mut terms, sign, &term)?;
+                term.clear();
+                sign = if c == '-' { -1 } else { 1 };
+            }
+            _ => term.push(c),
+        }
+    }
+    push(&mut terms, sign, &term)?;
+    Ok(Expr(terms))
+}
+
+fn evaluate(expr: &Expr, labels: &HashMap<String, i64>) -> Result<i64, String> {
+    let mut value: i64 = 0;
+    for (sign, term) in &expr.0 {
+        let term = match term {
+            Term::Number(value) => *value,
+            Term::Label(label) => *labels
+                .get(label)
+                .ok_or_else(|| format!("undefined label {}", label))?,
+        };
+        value = sign
+            .checked_mul(term)
+            .and_then(|term| value.checked_add(term))
+            .ok_or_else(|| "expression overflows".to_string())?;
+    }
+    Ok(value)
+}
+
+fn statement_length(statement: &Statement) -> Result<usize, String> {
+    match statement {
+        Statement::Data(values) => Ok(values.len()),
+        Statement::Instruction(mnemonic, _) => match &mnemonic[..] {
+            "add" | "mul" | "lt" | "eq" => Ok(4),
+            "jt" | "jf" => Ok(3),
+            "in" | "out" | "arb" => Ok(2),
+            "hlt" => Ok(1),
+            _ => Err(format!("unknown mnemonic {}", mnemonic)),
+        },
+    }
+}
+
+fn encode(
+    mnemonic: &str,
+    operands: &[Operand],
+    labels: &HashMap<String, i64>,
+) -> Result<Instruction, String> {
+    let expected = statement_length(&Statement::Instruction(mnemonic.into(), vec![]))? - 1;
+    if operands.len() != expected {
+        return Err(format!(
+            "{} expects {} operands, but got {}",
+            mnemonic,
+            expected,
+            operands.len()
+        ));
+    }
+    let input = |index: usize| -> Result<InputValue, String> {
+        Ok(match &operands[index] {
+            Operand::Position(expr) => InputValue::Position(evaluate(expr, labels)?),
+            Operand::Immediate(expr) => InputValue::Immediate(evaluate(expr, labels)?),
+            Operand::Relative(expr) => InputValue::Relative(evaluate(expr, labels)?),
+        })
+    };
+    let output = |index: usize| -> Result<OutputValue, String> {
+        Ok(match &operands[index] {
+            Operand::Position(expr) => OutputValue::Position(evaluate(expr, labels)?),
+            Operand::Immediate(_) => {
+                return Err(format!("{} cannot write in immediate mode", mnemonic))
+            }
+            Operand::Relative(expr) => OutputValue::Relative(evaluate(expr, labels)?),
+        })
+    };
+    Ok(match mnemonic {
+        "add" => Instruction::Add(input(0)?, input(1)?, output(2)?),
+        "mul" => Instruction::Multiply(input(0)?, input(1)?, output(2)?),
+        "in" => Instruction::Read(output(0)?),
+        "out" => Instruction::Write(input(0)?),
+        "jt" => Instruction::JumpIfTrue(input(0)?, input(1)?),
+        "jf" => Instruction::JumpIfFalse(input(0)?, input(1)?),
+        "lt" => Instruction::LessThan(input(0)?, input(1)?, output(2)?),
+        "eq" => Instruction::Equals(input(0)?, input(1)?, output(2)?),
+        "arb" => Instruction::RelativeBaseOffset(input(0)?),
+        "hlt" => Instruction::Halt,
+        _ => return Err(format!("unknown mnemonic {}", mnemonic)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::parse_list;
+    use crate::intcode::disasm::disassemble;
+    use crate::intcode::{Machine, ProgramMachine};
+
+    #[test]
+    fn test_assemble() {
+        let source = "
+            ; outputs the numbers from 10 down to 1
+            loop:
+                out [counter]
+                add [counter], #-1, [counter]
+                jt [counter], #loop
+                hlt
+            counter: db 10
+        ";
+        let program = assemble(source).unwrap();
+        assert_eq!(program, vec![4, 10, 1001, 10, -1, 10, 1005, 10, 0, 99, 10]);
+        let output = ProgramMachine::new(program, vec![]).run();
+        assert_eq!(output, (1..=10).rev().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_relative_and_expressions() {
+        let source = "
+            arb #table+1
+            out rb+0
+            out rb-1
+            out #end-table
+            in [table + 2]
+            out [table+2]
+            hlt
+            table: db 5, \"hi\", 0
+            end:
+        ";
+        let program = assemble(source).unwrap();
+        let output = ProgramMachine::new(program, vec![42]).run();
+        assert_eq!(output, vec!['h' as i64, 5, 4, 42]);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            assemble("add #1, #2, #3").unwrap_err().to_string(),
+            "line 1: add cannot write in immediate mode"
+        );
+        assert_eq!(
+            assemble("\njt #1, #nowhere").unwrap_err().to_string(),
+            "line 2: undefined label nowhere"
+        );
+        assert_eq!(
+            assemble("hlt\n0: hlt").unwrap_err().to_string(),
+            "line 2: expected address 0, but got 1"
+        );
+        assert_eq!(
+            assemble("mov [1], [2]").unwrap_err().to_string(),
+            "line 1: unknown mnemonic mov"
+        );
+        assert_eq!(
+            assemble("db 12abc").unwrap_err().to_string(),
+            "line 1: invalid number 12abc"
+        );
+        assert_eq!(
+            assemble("db 9223372036854775808").unwrap_err().to_string(),
+            "line 1: invalid number 9223372036854775808"
+        );
+        assert_eq!(
+            assemble("hlt\nend: db end+9223372036854775807")
+                .unwrap_err()
+                .to_string(),
+            "line 2: expression overflows"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let inputs = [
+            include_str!("../bin/day2/input"),
+            include_str!("../bin/day9/input"),
+            include_str!("../bin/day17/input"),
+            include_str!("../bin/day25/input"),
+        ];
+        for input in inputs.iter() {
+            let program: Vec<i64> = parse_list(input, ',');
+            let listing = disassemble(&program).to_string();
+            assert_eq!(assemble(&listing), Ok(program));
+        }
+
+        // the extremes, as operands and as data
+        let program = vec![104, i64::MIN, 1101, i64::MAX, i64::MIN, 0, 99, i64::MIN];
+        let listing = disassemble(&program).to_string();
+        assert_eq!(assemble(&listing), Ok(program));
+        assert_eq!(
+            assemble("db -9223372036854775808, --5, 1-2"),
+            Ok(vec![i64::MIN, 5, -1])
+        );
+    }
+}
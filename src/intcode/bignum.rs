@@ -0,0 +1,293 @@
+use std::collections::VecDeque;
+
+use num_bigint::{BigInt, Sign};
+use num_traits::{ToPrimitive, Zero};
+
+use crate::intcode::{InputValue, Instruction, IntcodeError, Machine, OutputValue, StepResult};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum BigStepResult {
+    Ok,
+    NeedInput,
+    Output(BigInt),
+    Halt,
+}
+
+// An Intcode machine with arbitrary precision words, for programs whose values don't fit in i64.
+#[derive(Debug, Clone)]
+pub struct BigMachine {
+    program: Vec<BigInt>,
+    pc: usize,
+    base: BigInt,
+    input: VecDeque<BigInt>,
+    memory_limit: Option<usize>,
+}
+
+// Saturates a word to i64, for reporting it in an error.
+fn clamp(value: &BigInt) -> i64 {
+    value.to_i64().unwrap_or(match value.sign() {
+        Sign::Minus => i64::MIN,
+        _ => i64::MAX,
+    })
+}
+
+impl BigMachine {
+    pub fn new(program: Vec<BigInt>, input: Vec<BigInt>) -> BigMachine {
+        BigMachine {
+            program,
+            pc: 0,
+            base: BigInt::zero(),
+            input: VecDeque::from(input),
+            memory_limit: None,
+        }
+    }
+
+    pub fn program(&self) -> &Vec<BigInt> {
+        &self.program
+    }
+
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    pub fn base(&self) -> &BigInt {
+        &self.base
+    }
+
+    pub fn set_memory_limit(&mut self, limit: Option<usize>) {
+        self.memory_limit = limit;
+    }
+
+    pub fn add_big_input(&mut self, value: BigInt) {
+        self.input.push_back(value);
+    }
+
+    fn instruction_word(&self) -> i64 {
+        clamp(&self.program[self.pc])
+    }
+
+    fn overflow(&self) -> IntcodeError {
+        IntcodeError::Overflow {
+            pc: self.pc,
+            instruction: self.instruction_word(),
+        }
+    }
+
+    fn param(&self, index: usize) -> BigInt {
+        self.program
+            .get(self.pc + index)
+            .cloned()
+            .unwrap_or_else(BigInt::zero)
+    }
+
+    fn address(&self, address: &BigInt) -> Result<usize, IntcodeError> {
+        if address.sign() == Sign::Minus {
+            return Err(IntcodeError::NegativeAddress {
+                pc: self.pc,
+                instruction: self.instruction_word(),
+                address: clamp(address),
+            });
+        }
+        address.to_usize().ok_or_else(|| self.overflow())
+    }
+
+    fn read(&self, value: &InputValue, index: usize) -> Result<BigInt, IntcodeError> {
+        let param = self.param(index);
+        let address = match value {
+            InputValue::Immediate(_) => return Ok(param),
+            InputValue::Position(_) => param,
+            InputValue::Relative(_) => &self.base + param,
+        };
+        let address = self.address(&address)?;
+        Ok(self
+            .program
+            .get(address)
+            .cloned()
+            .unwrap_or_else(BigInt::zero))
+    }
+
+    fn write(
+        &mut self,
+        result: &OutputValue,
+        index: usize,
+        value: BigInt,
+    ) -> Result<(), IntcodeError> {
+        let param = self.param(index);
+        let address = match result {
+            OutputValue::Position(_) => param,
+            OutputValue::Relative(_) => &self.base + param,
+        };
+        let address = self.address(&address)?;
+        if address >= self.program.len() {
+            if let Some(limit) = self.memory_limit {
+                if address >= limit {
+                    return Err(IntcodeError::MemoryLimit {
+                        pc: self.pc,
+                        instruction: self.instruction_word(),
+                        address,
+                    });
+                }
+            }
+            self.program.resize(address + 1, BigInt::zero());
+        }
+        self.program[address] = value;
+        Ok(())
+    }
+
+    fn jump(&mut self, target: &InputValue) -> Result<(), IntcodeError> {
+        let target = self.read(target, 2)?;
+        self.pc = self.address(&target)?;
+        Ok(())
+    }
+
+    fn decode(&self) -> Result<Instruction, IntcodeError> {
+        let opcode = match self.program.get(self.pc) {
+            Some(opcode) => opcode.to_i64().ok_or(IntcodeError::InvalidOpcode {
+                pc: self.pc,
+                instruction: clamp(opcode),
+            })?,
+            None => return Err(IntcodeError::PcOutOfRange { pc: self.pc }),
+        };
+        // only the opcode and modes matter, the parameters are read as big integers
+        let pc = self.pc;
+        Instruction::try_decode(pc, |address| if address == pc { opcode } else { 0 })
+    }
+
+    pub fn try_step_big(&mut self) -> Result<BigStepResult, IntcodeError> {
+        let instr = self.decode()?;
+        match &instr {
+            Instruction::Add(left, right, result) => {
+                let value = self.read(left, 1)? + self.read(right, 2)?;
+                self.write(result, 3, value)?;
+            }
+            Instruction::Multiply(left, right, result) => {
+                let value = self.read(left, 1)? * self.read(right, 2)?;
+                self.write(result, 3, value)?;
+            }
+            Instruction::Read(result) => match self.input.pop_front() {
+                Some(value) => self.write(result, 1, value)?,
+                None => return Ok(BigStepResult::NeedInput),
+            },
+            Instruction::Write(value) => {
+                let value = self.read(value, 1)?;
+                self.pc += instr.length();
+                return Ok(BigStepResult::Output(value));
+            }
+            Instruction::JumpIfTrue(test, jump) => {
+                if !self.read(test, 1)?.is_zero() {
+                    self.jump(jump)?;
+                    return Ok(BigStepResult::Ok);
+                }
+            }
+            Instruction::JumpIfFalse(test, jump) => {
+                if self.read(test, 1)?.is_zero() {
+                    self.jump(jump)?;
+                    return Ok(BigStepResult::Ok);
+                }
+            }
+            Instruction::LessThan(left, right, result) => {
+                let test = self.read(left, 1)? < self.read(right, 2)?;
+                self.write(result, 3, BigInt::from(test as i64))?;
+            }
+            Instruction::Equals(left, right, result) => {
+                let test = self.read(left, 1)? == self.read(right, 2)?;
+                self.write(result, 3, BigInt::from(test as i64))?;
+            }
+            Instruction::RelativeBaseOffset(offset) => {
+                self.base += self.read(offset, 1)?;
+            }
+            Instruction::Halt => return Ok(BigStepResult::Halt),
+        };
+        self.pc += instr.length();
+        Ok(BigStepResult::Ok)
+    }
+
+    pub fn try_run_big(&mut self) -> Result<Vec<BigInt>, IntcodeError> {
+        let mut output = Vec::new();
+        loop {
+            match self.try_step_big()? {
+                BigStepResult::Ok => {}
+                BigStepResult::NeedInput => return Err(IntcodeError::MissingInput),
+                BigStepResult::Output(value) => output.push(value),
+                BigStepResult::Halt => return Ok(output),
+            }
+        }
+    }
+}
+
+impl From<Vec<i64>> for BigMachine {
+    fn from(program: Vec<i64>) -> BigMachine {
+        BigMachine::new(program.into_iter().map(BigInt::from).collect(), vec![])
+    }
+}
+
+// Runs as a regular machine, as long as every output fits in i64.
+impl Machine for BigMachine {
+    fn add_input(&mut self, value: i64) {
+        self.add_big_input(BigInt::from(value));
+    }
+
+    fn try_step(&mut self) -> Result<StepResult, IntcodeError> {
+        let pc = self.pc;
+        let instruction = self.program.get(pc).map(clamp).unwrap_or(0);
+        Ok(match self.try_step_big()? {
+            BigStepResult::Ok => StepResult::Ok,
+            BigStepResult::NeedInput => StepResult::NeedInput,
+            BigStepResult::Output(value) => StepResult::Output(
+                value
+                    .to_i64()
+                    .ok_or(IntcodeError::Overflow { pc, instruction })?,
+            ),
+            BigStepResult::Halt => StepResult::Halt,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::parse_list;
+    use crate::intcode::ProgramMachine;
+
+    #[test]
+    fn test_exact_product() {
+        // squares 2^62 twice, then compares the result against 2^248
+        let program = parse_list(
+            "1102,4611686018427387904,4611686018427387904,17,\
+             2,17,17,17,\
+             4,17,\
+             1008,17,452312848583266388373324160190187140051835877600158453279131187530910662656,18,\
+             4,18,\
+             99,0,0",
+            ',',
+        );
+        let mut machine = BigMachine::new(program, vec![]);
+        let output = machine.try_run_big().unwrap();
+        assert_eq!(
+            output[0].to_string(),
+            "452312848583266388373324160190187140051835877600158453279131187530910662656"
+        );
+        assert_eq!(output[1], BigInt::from(1));
+    }
+
+    #[test]
+    fn test_output_overflow() {
+        let program = vec![1102, i64::MAX, 2, 7, 4, 7, 99, 0];
+        let mut machine = BigMachine::from(program);
+        assert_eq!(
+            machine.try_run(),
+            Err(IntcodeError::Overflow {
+                pc: 4,
+                instruction: 4
+            })
+        );
+    }
+
+    #[test]
+    fn test_matches_program_machine() {
+        let program: Vec<i64> = parse_list(include_str!("../bin/day9/input"), ',');
+        let mut machine = BigMachine::from(program.clone());
+        machine.add_input(1);
+        assert_eq!(machine.run(), ProgramMachine::new(program, vec![1]).run());
+    }
+}
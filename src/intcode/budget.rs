@@ -0,0 +1,127 @@
+use std::time::{Duration, Instant};
+
+// checking the clock on every step would dominate the run time
+const CLOCK_INTERVAL: u64 = 1024;
+
+// A limit on how long a machine may run, in executed instructions and/or wall-clock time.
+// The same budget can be passed to several calls, which then share what is left.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Budget {
+    steps: Option<u64>,
+    deadline: Option<Instant>,
+    ticks: u64,
+    expired: bool,
+}
+
+impl Budget {
+    pub fn unlimited() -> Budget {
+        Budget::default()
+    }
+
+    pub fn steps(steps: u64) -> Budget {
+        Budget::unlimited().and_steps(steps)
+    }
+
+    pub fn deadline(deadline: Instant) -> Budget {
+        Budget::unlimited().and_deadline(deadline)
+    }
+
+    pub fn timeout(timeout: Duration) -> Budget {
+        Budget::deadline(Instant::now() + timeout)
+    }
+
+    pub fn and_steps(self, steps: u64) -> Budget {
+        Budget {
+            steps: Some(steps),
+            ..self
+        }
+    }
+
+    pub fn and_deadline(self, deadline: Instant) -> Budget {
+        Budget {
+            deadline: Some(deadline),
+            ..self
+        }
+    }
+
+    pub fn and_timeout(self, timeout: Duration) -> Budget {
+        self.and_deadline(Instant::now() + timeout)
+    }
+
+    pub fn remaining_steps(&self) -> Option<u64> {
+        self.steps
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.expired || self.steps == Some(0)
+    }
+
+    // Takes one step from the budget, or returns false if there is nothing left.
+    pub fn consume(&mut self) -> bool {
+        if self.is_exhausted() {
+            return false;
+        }
+        if let Some(deadline) = self.deadline {
+            if self.ticks.is_multiple_of(CLOCK_INTERVAL) && Instant::now() >= deadline {
+                self.expired = true;
+                return false;
+            }
+            self.ticks += 1;
+        }
+        if let Some(steps) = &mut self.steps {
+            *steps -= 1;
+        }
+        true
+    }
+}
+
+// The result of running a machine within a budget. When the budget runs out, the machine is
+// left just before the next instruction, so it can be resumed with a new budget.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Outcome<T> {
+    Done(T),
+    BudgetExhausted(T),
+}
+
+impl<T> Outcome<T> {
+    pub fn is_done(&self) -> bool {
+        matches!(self, Outcome::Done(_))
+    }
+
+    pub fn into_inner(self) -> T {
+        match self {
+            Outcome::Done(value) | Outcome::BudgetExhausted(value) => value,
+        }
+    }
+
+    pub fn done(self) -> Option<T> {
+        match self {
+            Outcome::Done(value) => Some(value),
+            Outcome::BudgetExhausted(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steps() {
+        let mut budget = Budget::steps(2);
+        assert!(budget.consume());
+        assert!(budget.consume());
+        assert!(!budget.consume());
+        assert!(budget.is_exhausted());
+        assert_eq!(budget.remaining_steps(), Some(0));
+    }
+
+    #[test]
+    fn test_deadline() {
+        let mut budget = Budget::deadline(Instant::now());
+        assert!(!budget.consume());
+        let mut budget = Budget::timeout(Duration::from_secs(3600)).and_steps(1);
+        assert!(budget.consume());
+        assert!(!budget.consume());
+    }
+}
@@ -0,0 +1,497 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use crate::intcode::disasm::{decode, reachable_from};
+use crate::intcode::{InputValue, Instruction, OutputValue};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum EdgeKind {
+    FallThrough,
+    Jump,
+    // a jump through a position whose value no reachable instruction overwrites
+    Indirect,
+    Call,
+    Return,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Block {
+    pub start: usize,
+    pub instructions: Vec<(usize, Instruction)>,
+}
+
+impl Block {
+    pub fn end(&self) -> usize {
+        let &(address, instruction) = self.instructions.last().unwrap();
+        address + instruction.length()
+    }
+
+    fn last(&self) -> (usize, Instruction) {
+        *self.instructions.last().unwrap()
+    }
+}
+
+// A call stores its return address on the stack, then jumps to the routine:
+//     add #return, #0, rb+0
+//     jt #1, #routine
+// return:
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Call {
+    pub site: usize,
+    pub target: usize,
+    pub return_address: usize,
+}
+
+// A routine starts at the target of a call, and returns by jumping through the stack.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Routine {
+    pub entry: usize,
+    pub blocks: BTreeSet<usize>,
+    pub returns: BTreeSet<usize>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Graph {
+    pub blocks: BTreeMap<usize, Block>,
+    pub edges: Vec<Edge>,
+    pub calls: Vec<Call>,
+    pub routines: BTreeMap<usize, Routine>,
+}
+
+// The target of a jump that is always taken.
+fn unconditional(instruction: &Instruction) -> Option<InputValue> {
+    match *instruction {
+        Instruction::JumpIfTrue(InputValue::Immediate(test), target) if test != 0 => Some(target),
+        Instruction::JumpIfFalse(InputValue::Immediate(0), target) => Some(target),
+        _ => None,
+    }
+}
+
+fn jump_target(instruction: &Instruction) -> Option<InputValue> {
+    match *instruction {
+        Instruction::JumpIfTrue(_, target) | Instruction::JumpIfFalse(_, target) => Some(target),
+        _ => None,
+    }
+}
+
+fn is_return(instruction: &Instruction) -> bool {
+    matches!(jump_target(instruction), Some(InputValue::Relative(_)))
+}
+
+fn written_position(instruction: &Instruction) -> Option<i64> {
+    match *instruction {
+        Instruction::Add(_, _, OutputValue::Position(pos))
+        | Instruction::Multiply(_, _, OutputValue::Position(pos))
+        | Instruction::LessThan(_, _, OutputValue::Position(pos))
+        | Instruction::Equals(_, _, OutputValue::Position(pos))
+        | Instruction::Read(OutputValue::Position(pos)) => Some(pos),
+        _ => None,
+    }
+}
+
+// Calls can also jump through a pointer, using the targets found by `find_indirect`.
+fn find_calls(code: &BTreeMap<usize, Instruction>, indirect: &BTreeMap<usize, usize>) -> Vec<Call> {
+    let mut calls = Vec::new();
+    let mut previous: Option<(usize, Instruction)> = None;
+    for (&pc, instruction) in code {
+        let target = match (unconditional(instruction), indirect.get(&pc)) {
+            (Some(InputValue::Immediate(target)), _) if target >= 0 => target as usize,
+            (Some(InputValue::Position(_)), Some(&target)) => target,
+            _ => {
+                previous = Some((pc, *instruction));
+                continue;
+            }
+        };
+        let return_address = (pc + instruction.length()) as i64;
+        let stored = match previous {
+            Some((address, store)) if address + store.length() == pc => match store {
+                Instruction::Add(
+                    InputValue::Immediate(a),
+                    InputValue::Immediate(b),
+                    OutputValue::Relative(_),
+                ) => a.checked_add(b),
+                Instruction::Multiply(
+                    InputValue::Immediate(a),
+                    InputValue::Immediate(b),
+                    OutputValue::Relative(_),
+                ) => a.checked_mul(b),
+                _ => None,
+            },
+            _ => None,
+        };
+        if stored == Some(return_address) {
+            calls.push(Call {
+                site: pc,
+                target,
+                return_address: return_address as usize,
+            });
+        }
+        previous = Some((pc, *instruction));
+    }
+    calls
+}
+
+// Resolves jumps through a position that is never written to. Writes through the relative
+// base are assumed to stay on the stack.
+fn find_indirect(program: &[i64], code: &BTreeMap<usize, Instruction>) -> BTreeMap<usize, usize> {
+    let written = code
+        .values()
+        .filter_map(written_position)
+        .collect::<BTreeSet<_>>();
+    code.iter()
+        .filter_map(|(&pc, instruction)| match jump_target(instruction) {
+            Some(InputValue::Position(pos)) if pos >= 0 && !written.contains(&pos) => {
+                let target = *program.get(pos as usize)?;
+                if target >= 0 && decode(program, target as usize).is_some() {
+                    Some((pc, target as usize))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+pub fn analyze(program: &[i64]) -> Graph {
+    // return addresses and indirect targets are only found once the code around them is
+    let mut starts = BTreeSet::new();
+    starts.insert(0);
+    let (code, calls, indirect) = loop {
+        let code = reachable_from(program, &starts.iter().cloned().collect::<Vec<_>>());
+        let indirect = find_indirect(program, &code);
+        let calls = find_calls(&code, &indirect);
+        let count = starts.len();
+        starts.extend(calls.iter().map(|call| call.return_address));
+        starts.extend(indirect.values());
+        if starts.len() == count {
+            break (code, calls, indirect);
+        }
+    };
+
+    // every block starts at an entry point, a jump target, or right after a jump
+    let mut leaders = starts.clone();
+    for (&pc, instruction) in &code {
+        if jump_target(instruction).is_some() || *instruction == Instruction::Halt {
+            leaders.insert(pc + instruction.length());
+            leaders.extend(instruction.successors(pc));
+        }
+    }
+    leaders.extend(calls.iter().map(|call| call.target));
+
+    let mut blocks: BTreeMap<usize, Block> = BTreeMap::new();
+    let mut current: Option<Block> = None;
+    for (&pc, &instruction) in &code {
+        let extends =
+            matches!(&current, Some(block) if block.end() == pc && !leaders.contains(&pc));
+        if !extends {
+            if let Some(block) = current.take() {
+                blocks.insert(block.start, block);
+            }
+            current = Some(Block {
+                start: pc,
+                instructions: Vec::new(),
+            });
+        }
+        current
+            .as_mut()
+            .unwrap()
+            .instructions
+            .push((pc, instruction));
+    }
+    if let Some(block) = current {
+        blocks.insert(block.start, block);
+    }
+
+    let call_sites = calls
+        .iter()
+        .map(|call| (call.site, *call))
+        .collect::<BTreeMap<_, _>>();
+    let mut edges = Vec::new();
+    for block in blocks.values() {
+        let (pc, instruction) = block.last();
+        let next = block.end();
+        let mut edge = |to: usize, kind: EdgeKind| {
+            edges.push(Edge {
+                from: block.start,
+                to,
+                kind,
+            })
+        };
+        if let Some(call) = call_sites.get(&pc) {
+            edge(call.target, EdgeKind::Call);
+        } else if jump_target(&instruction).is_some() {
+            for target in instruction.successors(pc) {
+                let kind = if target == next && unconditional(&instruction).is_none() {
+                    EdgeKind::FallThrough
+                } else {
+                    EdgeKind::Jump
+                };
+                edge(target, kind);
+            }
+            if let Some(&target) = indirect.get(&pc) {
+                edge(target, EdgeKind::Indirect);
+            }
+        } else if instruction != Instruction::Halt && blocks.contains_key(&next) {
+            edge(next, EdgeKind::FallThrough);
+        }
+    }
+
+    // a routine is everything reachable from its entry without following calls into other
+    // routines, stepping over them to their return address instead
+    let mut routines = BTreeMap::new();
+    for entry in calls.iter().map(|call| call.target) {
+        if routines.contains_key(&entry) || !blocks.contains_key(&entry) {
+            continue;
+        }
+        let mut routine = Routine {
+            entry,
+            blocks: BTreeSet::new(),
+            returns: BTreeSet::new(),
+        };
+        let mut pending = vec![entry];
+        while let Some(start) = pending.pop() {
+            if !routine.blocks.insert(start) {
+                continue;
+            }
+            let (pc, instruction) = blocks[&start].last();
+            if let Some(call) = call_sites.get(&pc) {
+                pending.push(call.return_address);
+                continue;
+            }
+            if is_return(&instruction) {
+                routine.returns.insert(start);
+            }
+            pending.extend(
+                edges
+                    .iter()
+                    .filter(|edge| edge.from == start)
+                    .map(|edge| edge.to)
+                    .filter(|to| blocks.contains_key(to)),
+            );
+        }
+        routines.insert(entry, routine);
+    }
+    for call in &calls {
+        if let Some(routine) = routines.get(&call.target) {
+            for &from in &routine.returns {
+                edges.push(Edge {
+                    from,
+                    to: call.return_address,
+                    kind: EdgeKind::Return,
+                });
+            }
+        }
+    }
+    edges.sort_by_key(|edge| (edge.from, edge.to));
+    edges.dedup();
+
+    Graph {
+        blocks,
+        edges,
+        calls,
+        routines,
+    }
+}
+
+impl Graph {
+    // Exports the graph in Graphviz DOT format, with one node per block.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        writeln!(dot, "digraph intcode {{").unwrap();
+        writeln!(dot, "    node [shape=box, fontname=\"monospace\"];").unwrap();
+        for block in self.blocks.values() {
+            let mut label = String::new();
+            if self.routines.contains_key(&block.start) {
+                write!(label, "routine {}:\\l", block.start).unwrap();
+            }
+            for (address, instruction) in &block.instructions {
+                write!(label, "{}: {}\\l", address, instruction).unwrap();
+            }
+            writeln!(dot, "    b{} [label=\"{}\"];", block.start, label).unwrap();
+        }
+        for edge in &self.edges {
+            let style = match edge.kind {
+                EdgeKind::FallThrough => "",
+                EdgeKind::Jump => " [color=blue]",
+                EdgeKind::Indirect => " [color=blue, style=dotted]",
+                EdgeKind::Call => " [label=\"call\", style=bold]",
+                EdgeKind::Return => " [label=\"return\", style=dashed]",
+            };
+            writeln!(dot, "    b{} -> b{}{};", edge.from, edge.to, style).unwrap();
+        }
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::parse_list;
+    use crate::intcode::asm::assemble;
+    use crate::intcode::{Machine, ProgramMachine};
+
+    // calls a routine that doubles its argument, twice
+    const PROGRAM: &str = "
+                arb #stack
+                add #5, #0, rb+1
+                add #first, #0, rb+0
+                jt #1, #double
+        first:  add #second, #0, rb+0
+                jt #1, #double
+        second: out rb+1
+                hlt
+        double: arb #2
+                mul rb-1, #2, rb-1
+                arb #-2
+                jf #0, rb+0
+        stack:  db 0, 0, 0, 0
+    ";
+
+    #[test]
+    fn test_calls() {
+        let program = assemble(PROGRAM).unwrap();
+        assert_eq!(ProgramMachine::new(program.clone(), vec![]).run(), vec![20]);
+        let graph = analyze(&program);
+        assert_eq!(
+            graph.calls,
+            vec![
+                Call {
+                    site: 10,
+                    target: 23,
+                    return_address: 13
+                },
+                Call {
+                    site: 17,
+                    target: 23,
+                    return_address: 20
+                }
+            ]
+        );
+        assert_eq!(
+            graph.blocks.keys().cloned().collect::<Vec<_>>(),
+            vec![0, 13, 20, 23]
+        );
+        assert_eq!(graph.routines[&23].returns, vec![23].into_iter().collect());
+        assert_eq!(
+            graph.to_dot(),
+            "digraph intcode {\n    \
+             node [shape=box, fontname=\"monospace\"];\n    \
+             b0 [label=\"0: arb #34\\l2: add #5, #0, rb+1\\l6: add #13, #0, rb+0\\l10: jt #1, #23\\l\"];\n    \
+             b13 [label=\"13: add #20, #0, rb+0\\l17: jt #1, #23\\l\"];\n    \
+             b20 [label=\"20: out rb+1\\l22: hlt\\l\"];\n    \
+             b23 [label=\"routine 23:\\l23: arb #2\\l25: mul rb-1, #2, rb-1\\l29: arb #-2\\l31: jf #0, rb+0\\l\"];\n    \
+             b0 -> b23 [label=\"call\", style=bold];\n    \
+             b13 -> b23 [label=\"call\", style=bold];\n    \
+             b23 -> b13 [label=\"return\", style=dashed];\n    \
+             b23 -> b20 [label=\"return\", style=dashed];\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn test_branches_and_indirect_jumps() {
+        let program = assemble(
+            "
+                in [value]
+                jt [value], #skip
+                out #1
+            skip:
+                jt #1, [target]
+                hlt
+            end:
+                hlt
+            value: db 0
+            target: db end
+            ",
+        )
+        .unwrap();
+        let graph = analyze(&program);
+        assert_eq!(
+            graph.edges,
+            vec![
+                Edge {
+                    from: 0,
+                    to: 5,
+                    kind: EdgeKind::FallThrough
+                },
+                Edge {
+                    from: 0,
+                    to: 7,
+                    kind: EdgeKind::Jump
+                },
+                Edge {
+                    from: 5,
+                    to: 7,
+                    kind: EdgeKind::FallThrough
+                },
+                Edge {
+                    from: 7,
+                    to: 11,
+                    kind: EdgeKind::Indirect
+                },
+            ]
+        );
+        // the hlt after the indirect jump is unreachable
+        assert!(!graph.blocks.contains_key(&10));
+    }
+
+    #[test]
+    fn test_indirect_call() {
+        let program = assemble(
+            "
+                arb #stack
+                add #back, #0, rb+0
+                jt #1, [pointer]
+            back:
+                hlt
+            routine:
+                jf #0, rb+0
+            pointer: db routine
+            stack: db 0
+            ",
+        )
+        .unwrap();
+        let graph = analyze(&program);
+        assert_eq!(
+            graph.calls,
+            vec![Call {
+                site: 6,
+                target: 10,
+                return_address: 9
+            }]
+        );
+        assert!(graph.blocks.contains_key(&9));
+        assert!(graph.edges.contains(&Edge {
+            from: 10,
+            to: 9,
+            kind: EdgeKind::Return
+        }));
+    }
+
+    #[test]
+    fn test_day25() {
+        let program: Vec<i64> = parse_list(include_str!("../bin/day25/input"), ',');
+        let graph = analyze(&program);
+        assert!(!graph.routines.is_empty());
+        // every call returns to where it came from
+        for call in &graph.calls {
+            for &from in &graph.routines[&call.target].returns {
+                assert!(graph.edges.contains(&Edge {
+                    from,
+                    to: call.return_address,
+                    kind: EdgeKind::Return,
+                }));
+            }
+        }
+        assert!(graph.edges.iter().any(|edge| edge.kind == EdgeKind::Return));
+    }
+}
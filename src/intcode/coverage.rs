@@ -0,0 +1,212 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+use crate::intcode::disasm::{disassemble_from, Entry};
+
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct Branch {
+    pub taken: u64,
+    pub not_taken: u64,
+}
+
+impl Branch {
+    pub fn is_covered(&self) -> bool {
+        self.taken > 0 && self.not_taken > 0
+    }
+}
+
+// How often each instruction ran, and which way each conditional jump went.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Coverage {
+    pub pcs: HashMap<usize, u64>,
+    pub branches: HashMap<usize, Branch>,
+}
+
+impl Coverage {
+    pub fn new() -> Coverage {
+        Coverage::default()
+    }
+
+    pub fn record(&mut self, pc: usize) {
+        *self.pcs.entry(pc).or_insert(0) += 1;
+    }
+
+    pub fn record_branch(&mut self, pc: usize, taken: bool) {
+        self.record(pc);
+        let branch = self.branches.entry(pc).or_default();
+        if taken {
+            branch.taken += 1;
+        } else {
+            branch.not_taken += 1;
+        }
+    }
+
+    // Adds the coverage of another run of the same program.
+    pub fn merge(&mut self, other: &Coverage) {
+        for (&pc, &count) in &other.pcs {
+            *self.pcs.entry(pc).or_insert(0) += count;
+        }
+        for (&pc, other) in &other.branches {
+            let branch = self.branches.entry(pc).or_default();
+            branch.taken += other.taken;
+            branch.not_taken += other.not_taken;
+        }
+    }
+
+    pub fn is_covered(&self, pc: usize) -> bool {
+        self.pcs.contains_key(&pc)
+    }
+
+    // Disassembles `program`, with each instruction prefixed by how often it ran, or by
+    // ##### if it never did. Conditional jumps also show how often they were taken.
+    pub fn annotate(&self, program: &[i64]) -> String {
+        // executed code can be reached through jumps the disassembler can't follow
+        let mut starts = self.pcs.keys().cloned().collect::<Vec<_>>();
+        starts.push(0);
+        let disassembly = disassemble_from(program, &starts);
+        let mut listed = BTreeSet::new();
+        let mut instructions = 0;
+        let mut body = String::new();
+        for entry in &disassembly.entries {
+            let line = disassembly.format_entry(entry);
+            let address = match entry {
+                Entry::Code { address, .. } => *address,
+                Entry::Data { .. } => {
+                    writeln!(body, "{:>8}   {}", "", line).unwrap();
+                    continue;
+                }
+            };
+            instructions += 1;
+            listed.insert(address);
+            if let Some(label) = disassembly.labels.get(&address) {
+                writeln!(body, "{:>8}   {}:", "", label).unwrap();
+            }
+            match (self.pcs.get(&address), self.branches.get(&address)) {
+                (None, _) => writeln!(body, "{:>8} | {}", "#####", line).unwrap(),
+                (Some(count), None) => writeln!(body, "{:>8} | {}", count, line).unwrap(),
+                (Some(count), Some(branch)) => writeln!(
+                    body,
+                    "{:>8} {} {} ; taken {}, not taken {}",
+                    count,
+                    if branch.is_covered() { '|' } else { '!' },
+                    line,
+                    branch.taken,
+                    branch.not_taken
+                )
+                .unwrap(),
+            }
+        }
+
+        let covered = listed.iter().filter(|pc| self.is_covered(**pc)).count();
+        let branches = self.branches.values().filter(|b| b.is_covered()).count();
+        let mut report = format!(
+            "{} of {} instructions executed, {} of {} branches taken both ways\n",
+            covered,
+            instructions,
+            branches,
+            self.branches.len()
+        );
+        // code that only exists at runtime, or that the disassembler can't reach
+        let mut unlisted = self
+            .pcs
+            .keys()
+            .filter(|pc| !listed.contains(pc))
+            .collect::<Vec<_>>();
+        if !unlisted.is_empty() {
+            unlisted.sort();
+            let pcs = unlisted.iter().map(|pc| pc.to_string());
+            writeln!(
+                report,
+                "also executed: {}",
+                pcs.collect::<Vec<_>>().join(", ")
+            )
+            .unwrap();
+        }
+        report + &body
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::parse_list;
+    use crate::intcode::{Machine, ProgramMachine};
+
+    fn run(program: &[i64], input: Vec<i64>) -> (Vec<i64>, Coverage) {
+        let mut machine = ProgramMachine::new(program.to_vec(), input);
+        machine.set_coverage(true);
+        let output = machine.run();
+        (output, machine.take_coverage().unwrap())
+    }
+
+    #[test]
+    fn test_annotate() {
+        // outputs 1 if the input is 8, and 0 otherwise
+        let program = vec![
+            3, 15, 1008, 15, 8, 16, 1005, 16, 12, 104, 0, 99, 104, 1, 99, 0, 0,
+        ];
+        let (output, coverage) = run(&program, vec![7]);
+        assert_eq!(output, vec![0]);
+        assert_eq!(
+            coverage.annotate(&program),
+            "5 of 7 instructions executed, 0 of 1 branches taken both ways\n       \
+             1 |      0: in [15]\n       \
+             1 |      2: eq [15], #8, [16]\n       \
+             1 !      6: jt [16], #L12 ; taken 0, not taken 1\n       \
+             1 |      9: out #0\n       \
+             1 |     11: hlt\n           \
+             L12:\n   \
+             ##### |     12: out #1\n   \
+             ##### |     14: hlt\n               \
+             15: db 0, 0\n"
+        );
+
+        let (output, other) = run(&program, vec![8]);
+        assert_eq!(output, vec![1]);
+        let mut merged = coverage.clone();
+        merged.merge(&other);
+        assert_eq!(
+            merged.branches[&6],
+            Branch {
+                taken: 1,
+                not_taken: 1
+            }
+        );
+        assert_eq!(merged.pcs[&0], 2);
+        assert!(merged
+            .annotate(&program)
+            .starts_with("7 of 7 instructions executed, 1 of 1 branches taken both ways\n"));
+    }
+
+    #[test]
+    fn test_jump_to_next_instruction() {
+        // jumps to where it would have gone anyway
+        let program = vec![1005, 4, 3, 99, 1];
+        let (_, coverage) = run(&program, vec![]);
+        assert_eq!(
+            coverage.branches[&0],
+            Branch {
+                taken: 1,
+                not_taken: 0
+            }
+        );
+
+        // an add that turns itself into a jump is still an add
+        let program = vec![1101, 1000, 5, 0, 99];
+        let (_, coverage) = run(&program, vec![]);
+        assert!(coverage.branches.is_empty());
+        assert_eq!(coverage.pcs[&0], 1);
+    }
+
+    #[test]
+    fn test_day9_modes() {
+        let program: Vec<i64> = parse_list(include_str!("../bin/day9/input"), ',');
+        let (_, test_mode) = run(&program, vec![1]);
+        let (_, boost_mode) = run(&program, vec![2]);
+        let mut merged = test_mode.clone();
+        merged.merge(&boost_mode);
+        assert!(merged.pcs.len() > test_mode.pcs.len());
+        assert!(merged.pcs.len() > boost_mode.pcs.len());
+        assert!(!merged.annotate(&program).contains("also executed"));
+    }
+}
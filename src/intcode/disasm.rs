@@ -0,0 +1,322 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::intcode::{InputValue, Instruction, OutputValue};
+
+const DATA_PER_LINE: usize = 8;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Entry {
+    Code {
+        address: usize,
+        instruction: Instruction,
+    },
+    Data {
+        address: usize,
+        values: Vec<i64>,
+    },
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Disassembly {
+    pub entries: Vec<Entry>,
+    pub labels: BTreeMap<usize, String>,
+}
+
+impl fmt::Display for InputValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            InputValue::Position(pos) => write!(f, "[{}]", pos),
+            InputValue::Immediate(value) => write!(f, "#{}", value),
+            InputValue::Relative(pos) => write_relative(f, pos),
+        }
+    }
+}
+
+impl fmt::Display for OutputValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            OutputValue::Position(pos) => write!(f, "[{}]", pos),
+            OutputValue::Relative(pos) => write_relative(f, pos),
+        }
+    }
+}
+
+fn write_relative(f: &mut fmt::Formatter, offset: i64) -> fmt::Result {
+    if offset < 0 {
+        write!(f, "rb-{}", offset.unsigned_abs())
+    } else {
+        write!(f, "rb+{}", offset)
+    }
+}
+
+impl Instruction {
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Instruction::Add(_, _, _) => "add",
+            Instruction::Multiply(_, _, _) => "mul",
+            Instruction::Read(_) => "in",
+            Instruction::Write(_) => "out",
+            Instruction::JumpIfTrue(_, _) => "jt",
+            Instruction::JumpIfFalse(_, _) => "jf",
+            Instruction::LessThan(_, _, _) => "lt",
+            Instruction::Equals(_, _, _) => "eq",
+            Instruction::RelativeBaseOffset(_) => "arb",
+            Instruction::Halt => "hlt",
+        }
+    }
+
+    pub fn operands(&self) -> Vec<String> {
+        match self {
+            Instruction::Add(left, right, result)
+            | Instruction::Multiply(left, right, result)
+            | Instruction::LessThan(left, right, result)
+            | Instruction::Equals(left, right, result) => {
+                vec![left.to_string(), right.to_string(), result.to_string()]
+            }
+            Instruction::JumpIfTrue(test, jump) | Instruction::JumpIfFalse(test, jump) => {
+                vec![test.to_string(), jump.to_string()]
+            }
+            Instruction::Read(result) => vec![result.to_string()],
+            Instruction::Write(value) | Instruction::RelativeBaseOffset(value) => {
+                vec![value.to_string()]
+            }
+            Instruction::Halt => vec![],
+        }
+    }
+
+    pub fn successors(&self, pc: usize) -> Vec<usize> {
+        let next = pc + self.length();
+        let target = |jump: &InputValue| match *jump {
+            InputValue::Immediate(target) if target >= 0 => Some(target as usize),
+            _ => None,
+        };
+        match self {
+            Instruction::JumpIfTrue(test, jump) | Instruction::JumpIfFalse(test, jump) => {
+                let always = match (self, test) {
+                    (Instruction::JumpIfTrue(_, _), InputValue::Immediate(value)) => {
+                        Some(*value != 0)
+                    }
+                    (Instruction::JumpIfFalse(_, _), InputValue::Immediate(value)) => {
+                        Some(*value == 0)
+                    }
+                    _ => None,
+                };
+                match always {
+                    Some(true) => target(jump).into_iter().collect(),
+                    Some(false) => vec![next],
+                    None => vec![next].into_iter().chain(target(jump)).collect(),
+                }
+            }
+            Instruction::Halt => vec![],
+            _ => vec![next],
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.mnemonic())?;
+        let operands = self.operands();
+        if !operands.is_empty() {
+            write!(f, " {}", operands.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+// Decodes the instruction at `pc`, but only if it fits inside the program
+// and encodes back to exactly the same words.
+pub fn decode(program: &[i64], pc: usize) -> Option<Instruction> {
+    let instruction = Instruction::try_parse(program, pc).ok()?;
+    let words = instruction.encode();
+    if program.get(pc..pc + words.len()) == Some(&words[..]) {
+        Some(instruction)
+    } else {
+        None
+    }
+}
+
+// Finds the start of every instruction that can be reached from address 0
+// by following fall-through and immediate jump targets.
+pub fn reachable(program: &[i64]) -> BTreeMap<usize, Instruction> {
+    reachable_from(program, &[0])
+}
+
+// Like `reachable`, but starting from every address in `starts`.
+pub fn reachable_from(program: &[i64], starts: &[usize]) -> BTreeMap<usize, Instruction> {
+    let mut code = BTreeMap::new();
+    let mut pending = starts.to_vec();
+    while let Some(pc) = pending.pop() {
+        if code.contains_key(&pc) {
+            continue;
+        }
+        if let Some(instruction) = decode(program, pc) {
+            pending.extend(instruction.successors(pc));
+            code.insert(pc, instruction);
+        }
+    }
+    code
+}
+
+pub fn disassemble(program: &[i64]) -> Disassembly {
+    disassemble_from(program, &[0])
+}
+
+// Disassembles everything reachable from `starts`, such as return addresses
+// that are only known at runtime.
+pub fn disassemble_from(program: &[i64], starts: &[usize]) -> Disassembly {
+    let code = reachable_from(program, starts);
+    let mut entries = Vec::new();
+    let mut address = 0;
+    while address < program.len() {
+        match code.get(&address) {
+            Some(&instruction) => {
+                entries.push(Entry::Code {
+                    address,
+                    instruction,
+                });
+                address += instruction.length();
+            }
+            None => {
+                match entries.last_mut() {
+                    Some(Entry::Data { values, .. }) if values.len() < DATA_PER_LINE => {
+                        values.push(program[address])
+                    }
+                    _ => entries.push(Entry::Data {
+                        address,
+                        values: vec![program[address]],
+                    }),
+                }
+                address += 1;
+            }
+        }
+    }
+
+    // only label jump targets that start an instruction in the listing
+    let starts = entries
+        .iter()
+        .filter_map(|entry| match entry {
+            Entry::Code { address, .. } => Some(*address),
+            Entry::Data { .. } => None,
+        })
+        .collect::<BTreeSet<_>>();
+    let labels = code
+        .values()
+        .filter_map(|instruction| match *instruction {
+            Instruction::JumpIfTrue(_, InputValue::Immediate(target))
+            | Instruction::JumpIfFalse(_, InputValue::Immediate(target))
+                if target >= 0 && starts.contains(&(target as usize)) =>
+            {
+                Some(target as usize)
+            }
+            _ => None,
+        })
+        .map(|target| (target, format!("L{}", target)))
+        .collect();
+    Disassembly { entries, labels }
+}
+
+impl Disassembly {
+    // Formats a single entry as one line, without its label.
+    pub fn format_entry(&self, entry: &Entry) -> String {
+        match entry {
+            Entry::Code {
+                address,
+                instruction,
+            } => {
+                let mut operands = instruction.operands();
+                if let Instruction::JumpIfTrue(_, InputValue::Immediate(target))
+                | Instruction::JumpIfFalse(_, InputValue::Immediate(target)) = instruction
+                {
+                    if let Some(label) = self.labels.get(&(*target as usize)) {
+                        operands[1] = format!("#{}", label);
+                    }
+                }
+                let mut line = format!("{:>6}: {}", address, instruction.mnemonic());
+                if !operands.is_empty() {
+                    line += &format!(" {}", operands.join(", "));
+                }
+                line
+            }
+            Entry::Data { address, values } => {
+                let values = values.iter().map(|value| value.to_string());
+                format!(
+                    "{:>6}: db {}",
+                    address,
+                    values.collect::<Vec<_>>().join(", ")
+                )
+            }
+        }
+    }
+}
+
+impl fmt::Display for Disassembly {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for entry in &self.entries {
+            if let Entry::Code { address, .. } = entry {
+                if let Some(label) = self.labels.get(address) {
+                    writeln!(f, "{}:", label)?;
+                }
+            }
+            writeln!(f, "{}", self.format_entry(entry))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disassemble() {
+        let program = vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50];
+        assert_eq!(
+            disassemble(&program).to_string(),
+            "     0: add [9], [10], [3]\n     \
+             4: mul [3], [11], [0]\n     \
+             8: hlt\n     \
+             9: db 30, 40, 50\n"
+        );
+    }
+
+    #[test]
+    fn test_modes_and_labels() {
+        let program = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        assert_eq!(
+            disassemble(&program).to_string(),
+            "L0:\n     \
+             0: arb #1\n     \
+             2: out rb-1\n     \
+             4: add [100], #1, [100]\n     \
+             8: eq [100], #16, [101]\n    \
+             12: jf [101], #L0\n    \
+             15: hlt\n"
+        );
+        let program = vec![204, i64::MIN, 99];
+        assert_eq!(
+            disassemble(&program).to_string(),
+            "     0: out rb-9223372036854775808\n     \
+             2: hlt\n"
+        );
+    }
+
+    #[test]
+    fn test_data_fallback() {
+        // unconditional jump over a word that does not decode,
+        // followed by an instruction with an unused mode digit
+        let program = vec![1105, 1, 4, 42, 104, 7, 10099];
+        let disassembly = disassemble(&program);
+        assert_eq!(
+            disassembly.to_string(),
+            "     0: jt #1, #L4\n     \
+             3: db 42\n\
+             L4:\n     \
+             4: out #7\n     \
+             6: db 10099\n"
+        );
+    }
+}
@@ -0,0 +1,564 @@
+use std::fmt;
+
+use crate::intcode::network;
+use crate::intcode::packet::Rng;
+use crate::intcode::{IntcodeError, Machine, ProgramMachine, StepResult};
+
+const MAX_STEPS: usize = 1000;
+// random programs can write anywhere
+const MEMORY_LIMIT: usize = 1 << 16;
+
+// Input and output parameters of every opcode, in order.
+const PARAMETERS: [&[bool]; 9] = [
+    &[true, true, false],
+    &[true, true, false],
+    &[false],
+    &[true],
+    &[true, true],
+    &[true, true],
+    &[true, true, false],
+    &[true, true, false],
+    &[true],
+];
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Case {
+    pub program: Vec<i64>,
+    pub input: Vec<i64>,
+}
+
+// Generates random programs in which every instruction is valid, followed by some data.
+// Addresses point into the program, so programs read and overwrite their own code, and
+// jump targets are mostly the start of an instruction.
+pub struct Generator {
+    rng: Rng,
+    pub instructions: usize,
+    pub data: usize,
+    pub inputs: usize,
+}
+
+impl Generator {
+    pub fn new(seed: u64) -> Generator {
+        Generator {
+            rng: Rng(seed.max(1)),
+            instructions: 12,
+            data: 8,
+            inputs: 4,
+        }
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.rng.next() % n as u64) as usize
+    }
+
+    fn between(&mut self, low: i64, high: i64) -> i64 {
+        low + self.below((high - low + 1) as usize) as i64
+    }
+
+    pub fn case(&mut self) -> Case {
+        let opcodes = (0..self.instructions)
+            .map(|_| self.below(9) + 1)
+            .collect::<Vec<_>>();
+        let mut starts = Vec::new();
+        let mut length = 0;
+        for &opcode in &opcodes {
+            starts.push(length as i64);
+            length += 1 + PARAMETERS[opcode - 1].len();
+        }
+        // the last instruction halts, if the program ever gets there
+        length += 1;
+        let size = (length + self.data) as i64;
+
+        let mut program = Vec::new();
+        for &opcode in &opcodes {
+            let mut word = opcode as i64;
+            let mut operands = Vec::new();
+            for (index, &input) in PARAMETERS[opcode - 1].iter().enumerate() {
+                let jump = (opcode == 5 || opcode == 6) && index == 1;
+                let mode = if input {
+                    self.below(3)
+                } else {
+                    self.below(2) * 2
+                };
+                word += mode as i64 * 10i64.pow(index as u32 + 2);
+                operands.push(match mode {
+                    0 => self.between(0, size - 1),
+                    1 if jump => starts[self.below(starts.len())],
+                    1 if opcode == 9 => self.between(-1, 4),
+                    1 => self.between(-5, 20),
+                    _ => self.between(0, 6),
+                });
+            }
+            program.push(word);
+            program.extend(operands);
+        }
+        program.push(99);
+        for _ in 0..self.data {
+            let value = match self.below(4) {
+                0 => starts[self.below(starts.len())],
+                _ => self.between(-1, 20),
+            };
+            program.push(value);
+        }
+        let input = (0..self.inputs).map(|_| self.between(-5, 20)).collect();
+        Case { program, input }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct State {
+    pub memory: Vec<i64>,
+    pub pc: usize,
+    pub base: i64,
+}
+
+// What an implementation did with a case. Steps that only return `StepResult::Ok` are left
+// out, so implementations that take a different number of steps can still be compared.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Run {
+    pub results: Vec<Result<StepResult, IntcodeError>>,
+    pub steps: usize,
+    // false if the run was cut off after the maximum number of steps
+    pub finished: bool,
+    // memory, pc and relative base at the end, for implementations that can tell
+    pub state: Option<State>,
+}
+
+impl Run {
+    // Runs until the machine halts, fails, runs out of input or takes `max_steps` steps.
+    pub fn record(machine: &mut dyn Machine, input: &[i64], max_steps: usize) -> Run {
+        for &value in input {
+            machine.add_input(value);
+        }
+        let mut run = Run {
+            results: Vec::new(),
+            steps: 0,
+            finished: false,
+            state: None,
+        };
+        while run.steps < max_steps {
+            let result = machine.try_step();
+            run.steps += 1;
+            if result != Ok(StepResult::Ok) {
+                run.results.push(result);
+            }
+            if matches!(
+                result,
+                Err(_) | Ok(StepResult::Halt) | Ok(StepResult::NeedInput)
+            ) {
+                run.finished = true;
+                break;
+            }
+        }
+        run
+    }
+
+    pub fn output(&self) -> Vec<i64> {
+        self.results
+            .iter()
+            .filter_map(|result| match result {
+                Ok(StepResult::Output(value)) => Some(*value),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+// An implementation of Intcode to compare.
+pub trait Subject {
+    fn name(&self) -> String;
+    fn run(&self, case: &Case, max_steps: usize) -> Run;
+}
+
+pub struct Interpreter {
+    pub decode_cache: bool,
+}
+
+impl Subject for Interpreter {
+    fn name(&self) -> String {
+        if self.decode_cache {
+            "interpreter".to_string()
+        } else {
+            "interpreter without decode cache".to_string()
+        }
+    }
+
+    fn run(&self, case: &Case, max_steps: usize) -> Run {
+        let mut machine = ProgramMachine::new(case.program.clone(), vec![]);
+        machine.set_decode_cache(self.decode_cache);
+        machine.set_memory_limit(Some(MEMORY_LIMIT));
+        let mut run = Run::record(&mut machine, &case.input, max_steps);
+        run.state = Some(State {
+            memory: machine.program().clone(),
+            pc: machine.pc(),
+            base: machine.base(),
+        });
+        run
+    }
+}
+
+// A chain of just one interpreter, which should behave like the interpreter itself.
+pub struct Chain;
+
+impl Subject for Chain {
+    fn name(&self) -> String {
+        "chain".to_string()
+    }
+
+    fn run(&self, case: &Case, max_steps: usize) -> Run {
+        let mut machine = ProgramMachine::new(case.program.clone(), vec![]);
+        machine.set_memory_limit(Some(MEMORY_LIMIT));
+        let mut chain = network::chain(vec![machine]);
+        let mut run = Run::record(&mut chain, &case.input, max_steps);
+        let machine = chain.machine::<ProgramMachine>(0).unwrap();
+        run.state = Some(State {
+            memory: machine.program().clone(),
+            pc: machine.pc(),
+            base: machine.base(),
+        });
+        run
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Difference {
+    Result {
+        index: usize,
+        left: Option<Result<StepResult, IntcodeError>>,
+        right: Option<Result<StepResult, IntcodeError>>,
+    },
+    Finished {
+        left: bool,
+        right: bool,
+    },
+    Memory {
+        address: usize,
+        left: i64,
+        right: i64,
+    },
+    Pc {
+        left: usize,
+        right: usize,
+    },
+    Base {
+        left: i64,
+        right: i64,
+    },
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Difference::Result { index, left, right } => write!(
+                f,
+                "step result {} differs: {:?} vs {:?}",
+                index, left, right
+            ),
+            Difference::Finished { left, right } => {
+                write!(f, "finished differs: {} vs {}", left, right)
+            }
+            Difference::Memory {
+                address,
+                left,
+                right,
+            } => write!(f, "memory at {} differs: {} vs {}", address, left, right),
+            Difference::Pc { left, right } => write!(f, "pc differs: {} vs {}", left, right),
+            Difference::Base { left, right } => {
+                write!(f, "relative base differs: {} vs {}", left, right)
+            }
+        }
+    }
+}
+
+// Compares two runs of the same case. A run that was cut off only needs to agree with the
+// other one for as long as it went. The final state is compared if both runs finished, or
+// were cut off after the same number of steps.
+pub fn compare(left: &Run, right: &Run) -> Option<Difference> {
+    let count = match (left.finished, right.finished) {
+        (true, true) => left.results.len().max(right.results.len()),
+        (true, false) => right.results.len(),
+        (false, true) => left.results.len(),
+        (false, false) => left.results.len().min(right.results.len()),
+    };
+    for index in 0..count {
+        let (a, b) = (left.results.get(index), right.results.get(index));
+        if a != b {
+            return Some(Difference::Result {
+                index,
+                left: a.cloned(),
+                right: b.cloned(),
+            });
+        }
+    }
+    if left.finished != right.finished && left.steps == right.steps {
+        return Some(Difference::Finished {
+            left: left.finished,
+            right: right.finished,
+        });
+    }
+    let comparable =
+        left.finished == right.finished && (left.finished || left.steps == right.steps);
+    let (a, b) = match (&left.state, &right.state) {
+        (Some(a), Some(b)) if comparable => (a, b),
+        _ => return None,
+    };
+    // memory that was never written past the end of the program reads as 0 either way
+    let size = a.memory.len().max(b.memory.len());
+    for address in 0..size {
+        let left = a.memory.get(address).copied().unwrap_or(0);
+        let right = b.memory.get(address).copied().unwrap_or(0);
+        if left != right {
+            return Some(Difference::Memory {
+                address,
+                left,
+                right,
+            });
+        }
+    }
+    if a.pc != b.pc {
+        return Some(Difference::Pc {
+            left: a.pc,
+            right: b.pc,
+        });
+    }
+    if a.base != b.base {
+        return Some(Difference::Base {
+            left: a.base,
+            right: b.base,
+        });
+    }
+    None
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Counterexample {
+    pub seed: u64,
+    pub left: String,
+    pub right: String,
+    pub case: Case,
+    pub difference: Difference,
+}
+
+impl fmt::Display for Counterexample {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let join = |values: &[i64]| {
+            let values = values.iter().map(|value| value.to_string());
+            values.collect::<Vec<_>>().join(",")
+        };
+        writeln!(
+            f,
+            "{} vs {}, seed {}: {}",
+            self.left, self.right, self.seed, self.difference
+        )?;
+        writeln!(f, "program: {}", join(&self.case.program))?;
+        write!(f, "input: {}", join(&self.case.input))
+    }
+}
+
+// Runs cases through two implementations, and reports the first case they disagree on.
+pub struct Harness<A: Subject, B: Subject> {
+    pub left: A,
+    pub right: B,
+    pub max_steps: usize,
+}
+
+impl<A: Subject, B: Subject> Harness<A, B> {
+    pub fn new(left: A, right: B) -> Harness<A, B> {
+        Harness {
+            left,
+            right,
+            max_steps: MAX_STEPS,
+        }
+    }
+
+    pub fn check(&self, case: &Case) -> Option<Difference> {
+        compare(
+            &self.left.run(case, self.max_steps),
+            &self.right.run(case, self.max_steps),
+        )
+    }
+
+    // Checks `cases` generated cases, starting from `seed`. Any counterexample is shrunk.
+    pub fn fuzz(&self, seed: u64, cases: usize) -> Option<Counterexample> {
+        let mut generator = Generator::new(seed);
+        for _ in 0..cases {
+            let case = generator.case();
+            if self.check(&case).is_some() {
+                let case = self.shrink(case);
+                let difference = self.check(&case).unwrap();
+                return Some(Counterexample {
+                    seed,
+                    left: self.left.name(),
+                    right: self.right.name(),
+                    case,
+                    difference,
+                });
+            }
+        }
+        None
+    }
+
+    // Makes a failing case smaller for as long as it keeps failing: first by removing words
+    // from the program and values from the input, then by moving the remaining values
+    // closer to 0. The result can't be made smaller by any single step.
+    pub fn shrink(&self, mut case: Case) -> Case {
+        loop {
+            let program = self.shrink_values(&mut case, |case| &mut case.program);
+            let input = self.shrink_values(&mut case, |case| &mut case.input);
+            if !program && !input {
+                return case;
+            }
+        }
+    }
+
+    fn shrink_values(&self, case: &mut Case, values: fn(&mut Case) -> &mut Vec<i64>) -> bool {
+        let mut changed = false;
+        let mut size = values(case).len() / 2;
+        while size > 0 {
+            let mut start = 0;
+            while start + size <= values(case).len() {
+                let mut candidate = case.clone();
+                values(&mut candidate).drain(start..start + size);
+                if self.check(&candidate).is_some() {
+                    *case = candidate;
+                    changed = true;
+                } else {
+                    start += size;
+                }
+            }
+            size /= 2;
+        }
+        for index in 0..values(case).len() {
+            loop {
+                let value = values(case)[index];
+                let simpler = [0, value / 2, value - value.signum()];
+                let next = simpler.iter().cloned().find(|&simpler| {
+                    let mut candidate = case.clone();
+                    values(&mut candidate)[index] = simpler;
+                    simpler.unsigned_abs() < value.unsigned_abs()
+                        && self.check(&candidate).is_some()
+                });
+                match next {
+                    Some(simpler) => {
+                        values(case)[index] = simpler;
+                        changed = true;
+                    }
+                    None => break,
+                }
+            }
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_generator_covers_every_opcode_and_mode() {
+        let mut generator = Generator::new(1);
+        let mut seen = HashSet::new();
+        for _ in 0..100 {
+            let case = generator.case();
+            let mut pc = 0;
+            while case.program[pc] != 99 {
+                let word = case.program[pc];
+                let opcode = word % 100;
+                let parameters = PARAMETERS[opcode as usize - 1];
+                for index in 0..parameters.len() {
+                    seen.insert((opcode, word / 10i64.pow(index as u32 + 2) % 10));
+                }
+                pc += 1 + parameters.len();
+            }
+        }
+        for opcode in 1..=9 {
+            let modes: &[i64] = if opcode == 3 { &[0, 2] } else { &[0, 1, 2] };
+            for &mode in modes {
+                assert!(seen.contains(&(opcode, mode)), "{} {}", opcode, mode);
+            }
+        }
+        assert_eq!(Generator::new(7).case(), Generator::new(7).case());
+    }
+
+    #[test]
+    fn test_decode_cache() {
+        let harness = Harness::new(
+            Interpreter { decode_cache: true },
+            Interpreter {
+                decode_cache: false,
+            },
+        );
+        if let Some(counterexample) = harness.fuzz(1, 500) {
+            panic!("{}", counterexample);
+        }
+    }
+
+    #[test]
+    fn test_chain() {
+        let harness = Harness::new(Interpreter { decode_cache: true }, Chain);
+        if let Some(counterexample) = harness.fuzz(2, 500) {
+            panic!("{}", counterexample);
+        }
+    }
+
+    #[test]
+    fn test_chain_state() {
+        // writes to memory and moves the relative base, then halts
+        let case = Case {
+            program: vec![109, 7, 21101, 2, 3, 0, 99, 0],
+            input: vec![],
+        };
+        let run = Chain.run(&case, MAX_STEPS);
+        let state = run.state.as_ref().unwrap();
+        assert_eq!((state.memory[7], state.pc, state.base), (5, 6, 7));
+        let interpreter = Interpreter { decode_cache: true }.run(&case, MAX_STEPS);
+        assert_eq!(compare(&interpreter, &run), None);
+    }
+
+    // An interpreter that gets every output of 13 or more wrong.
+    struct Broken;
+
+    impl Subject for Broken {
+        fn name(&self) -> String {
+            "broken".to_string()
+        }
+
+        fn run(&self, case: &Case, max_steps: usize) -> Run {
+            let mut run = Interpreter { decode_cache: true }.run(case, max_steps);
+            for result in &mut run.results {
+                if let Ok(StepResult::Output(value)) = result {
+                    if *value >= 13 {
+                        *value += 1;
+                    }
+                }
+            }
+            run
+        }
+    }
+
+    #[test]
+    fn test_shrink() {
+        let harness = Harness::new(Interpreter { decode_cache: true }, Broken);
+        let counterexample = harness.fuzz(3, 100).unwrap();
+        let Counterexample {
+            case, difference, ..
+        } = counterexample;
+        // an output instruction and the value it outputs are enough
+        assert!(case.program.len() <= 8, "{:?}", case.program);
+        assert_eq!(case.program.iter().filter(|&&word| word != 0).count(), 3);
+        assert_eq!(case.input, vec![]);
+        assert!(matches!(difference, Difference::Result { .. }));
+
+        // the smallest value can be simplified too
+        let case = Case {
+            program: vec![104, 20, 99, i64::MIN],
+            input: vec![i64::MIN],
+        };
+        let case = harness.shrink(case);
+        assert!(harness.check(&case).is_some());
+        assert!(!case.program.contains(&i64::MIN), "{:?}", case.program);
+        assert!(!case.input.contains(&i64::MIN));
+    }
+}
@@ -0,0 +1,228 @@
+use std::collections::VecDeque;
+use std::io::{BufRead, ErrorKind, Write};
+use std::sync::mpsc::{Receiver, Sender, SyncSender};
+
+use crate::intcode::IntcodeError;
+
+// Where a machine gets its input from when it runs out of queued values.
+pub trait InputSource {
+    // Returns None when there is no more input.
+    fn read_input(&mut self) -> Result<Option<i64>, IntcodeError>;
+}
+
+// Where a machine sends its output.
+pub trait OutputSink {
+    fn write_output(&mut self, value: i64) -> Result<(), IntcodeError>;
+}
+
+impl<F: FnMut() -> Option<i64>> InputSource for F {
+    fn read_input(&mut self) -> Result<Option<i64>, IntcodeError> {
+        Ok(self())
+    }
+}
+
+impl<F: FnMut(i64)> OutputSink for F {
+    fn write_output(&mut self, value: i64) -> Result<(), IntcodeError> {
+        self(value);
+        Ok(())
+    }
+}
+
+impl InputSource for VecDeque<i64> {
+    fn read_input(&mut self) -> Result<Option<i64>, IntcodeError> {
+        Ok(self.pop_front())
+    }
+}
+
+impl OutputSink for VecDeque<i64> {
+    fn write_output(&mut self, value: i64) -> Result<(), IntcodeError> {
+        self.push_back(value);
+        Ok(())
+    }
+}
+
+impl OutputSink for Vec<i64> {
+    fn write_output(&mut self, value: i64) -> Result<(), IntcodeError> {
+        self.push(value);
+        Ok(())
+    }
+}
+
+// Blocks until a value arrives, and ends the input once every sender is gone.
+impl InputSource for Receiver<i64> {
+    fn read_input(&mut self) -> Result<Option<i64>, IntcodeError> {
+        Ok(self.recv().ok())
+    }
+}
+
+impl OutputSink for Sender<i64> {
+    fn write_output(&mut self, value: i64) -> Result<(), IntcodeError> {
+        self.send(value)
+            .map_err(|_| IntcodeError::Io(ErrorKind::BrokenPipe))
+    }
+}
+
+impl OutputSink for SyncSender<i64> {
+    fn write_output(&mut self, value: i64) -> Result<(), IntcodeError> {
+        self.send(value)
+            .map_err(|_| IntcodeError::Io(ErrorKind::BrokenPipe))
+    }
+}
+
+pub struct IterSource<I: Iterator<Item = i64>>(I);
+
+pub fn iter<T: IntoIterator<Item = i64>>(values: T) -> IterSource<T::IntoIter> {
+    IterSource(values.into_iter())
+}
+
+impl<I: Iterator<Item = i64>> InputSource for IterSource<I> {
+    fn read_input(&mut self) -> Result<Option<i64>, IntcodeError> {
+        Ok(self.0.next())
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Format {
+    // one number per line, or separated by commas
+    Numbers,
+    // every byte is a value, and values outside of ASCII are written as numbers
+    Ascii,
+}
+
+pub struct ReaderSource<R: BufRead> {
+    reader: R,
+    format: Format,
+    pending: VecDeque<i64>,
+}
+
+impl<R: BufRead> ReaderSource<R> {
+    pub fn new(reader: R, format: Format) -> ReaderSource<R> {
+        ReaderSource {
+            reader,
+            format,
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+impl<R: BufRead> InputSource for ReaderSource<R> {
+    fn read_input(&mut self) -> Result<Option<i64>, IntcodeError> {
+        while self.pending.is_empty() {
+            let mut line = String::new();
+            let read = self.reader.read_line(&mut line);
+            if read.map_err(|err| IntcodeError::Io(err.kind()))? == 0 {
+                return Ok(None);
+            }
+            match self.format {
+                Format::Numbers => {
+                    for value in line.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+                        let value = value
+                            .parse()
+                            .map_err(|_| IntcodeError::Io(ErrorKind::InvalidData))?;
+                        self.pending.push_back(value);
+                    }
+                }
+                Format::Ascii => self.pending.extend(line.bytes().map(i64::from)),
+            }
+        }
+        Ok(self.pending.pop_front())
+    }
+}
+
+pub struct WriterSink<W: Write> {
+    writer: W,
+    format: Format,
+}
+
+impl<W: Write> WriterSink<W> {
+    pub fn new(writer: W, format: Format) -> WriterSink<W> {
+        WriterSink { writer, format }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> OutputSink for WriterSink<W> {
+    fn write_output(&mut self, value: i64) -> Result<(), IntcodeError> {
+        let result = match self.format {
+            Format::Ascii if (0..128).contains(&value) => self.writer.write_all(&[value as u8]),
+            _ => writeln!(self.writer, "{}", value),
+        };
+        result.map_err(|err| IntcodeError::Io(err.kind()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::{Machine, ProgramMachine};
+    use std::sync::mpsc::channel;
+
+    // adds up its inputs until it reads a zero
+    fn sum() -> ProgramMachine {
+        let program = vec![
+            3, 15, 1006, 15, 12, 1, 15, 16, 16, 1105, 1, 0, 4, 16, 99, 0, 0,
+        ];
+        ProgramMachine::new(program, vec![])
+    }
+
+    #[test]
+    fn test_closures() {
+        let mut values = vec![1, 2, 3, 0].into_iter();
+        let mut output = Vec::new();
+        sum()
+            .try_run_with(&mut || values.next(), &mut |value| output.push(value))
+            .unwrap();
+        assert_eq!(output, vec![6]);
+    }
+
+    #[test]
+    fn test_missing_input() {
+        let mut output = Vec::new();
+        assert_eq!(
+            sum().try_run_with(&mut iter(vec![1, 2]), &mut output),
+            Err(IntcodeError::MissingInput)
+        );
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn test_channels() {
+        let (input, mut receiver) = channel();
+        let (mut sender, output) = channel();
+        for value in &[4, 5, 0] {
+            input.send(*value).unwrap();
+        }
+        sum().try_run_with(&mut receiver, &mut sender).unwrap();
+        assert_eq!(output.recv(), Ok(9));
+    }
+
+    #[test]
+    fn test_reader_and_writer() {
+        let mut input = ReaderSource::new("10,20\n\n30\n0\n".as_bytes(), Format::Numbers);
+        let mut output = WriterSink::new(Vec::new(), Format::Numbers);
+        sum().try_run_with(&mut input, &mut output).unwrap();
+        assert_eq!(output.into_inner(), b"60\n");
+
+        let mut input = ReaderSource::new("1,x\n".as_bytes(), Format::Numbers);
+        assert_eq!(
+            sum().try_run_with(&mut input, &mut Vec::new()),
+            Err(IntcodeError::Io(ErrorKind::InvalidData))
+        );
+
+        // echoes one line of text, then a number
+        let program = vec![
+            3, 14, 4, 14, 1008, 14, 10, 15, 1006, 15, 0, 104, 1000, 99, 0, 0,
+        ];
+        let mut output = WriterSink::new(Vec::new(), Format::Ascii);
+        ProgramMachine::new(program, vec![])
+            .try_run_with(
+                &mut ReaderSource::new("hi\n".as_bytes(), Format::Ascii),
+                &mut output,
+            )
+            .unwrap();
+        assert_eq!(output.into_inner(), b"hi\n1000\n");
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+
+const PAGE_SIZE: usize = 1024;
+
+pub trait Memory {
+    fn get(&self, address: usize) -> i64;
+    fn set(&mut self, address: usize, value: i64);
+    // one past the highest address that has been written
+    fn len(&self) -> usize;
+    // number of words held in memory after a write to address
+    fn size_with(&self, address: usize) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Memory for Vec<i64> {
+    #[inline]
+    fn get(&self, address: usize) -> i64 {
+        *self.as_slice().get(address).unwrap_or(&0)
+    }
+
+    #[inline]
+    fn set(&mut self, address: usize, value: i64) {
+        if address >= self.len() {
+            self.resize(address + 1, 0);
+        }
+        self[address] = value;
+    }
+
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn size_with(&self, address: usize) -> usize {
+        Vec::len(self).max(address + 1)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PagedMemory {
+    pages: HashMap<usize, Box<[i64; PAGE_SIZE]>>,
+    len: usize,
+}
+
+impl PagedMemory {
+    pub fn new() -> PagedMemory {
+        PagedMemory::default()
+    }
+}
+
+impl From<Vec<i64>> for PagedMemory {
+    fn from(program: Vec<i64>) -> PagedMemory {
+        let mut memory = PagedMemory::new();
+        for (address, value) in program.into_iter().enumerate() {
+            memory.set(address, value);
+        }
+        memory
+    }
+}
+
+impl Memory for PagedMemory {
+    #[inline]
+    fn get(&self, address: usize) -> i64 {
+        match self.pages.get(&(address / PAGE_SIZE)) {
+            Some(page) => page[address % PAGE_SIZE],
+            None => 0,
+        }
+    }
+
+    #[inline]
+    fn set(&mut self, address: usize, value: i64) {
+        let page = self
+            .pages
+            .entry(address / PAGE_SIZE)
+            .or_insert_with(|| Box::new([0; PAGE_SIZE]));
+        page[address % PAGE_SIZE] = value;
+        self.len = self.len.max(address + 1);
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn size_with(&self, address: usize) -> usize {
+        let pages = self.pages.len() + !self.pages.contains_key(&(address / PAGE_SIZE)) as usize;
+        pages * PAGE_SIZE
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SparseMemory {
+    cells: HashMap<usize, i64>,
+    len: usize,
+}
+
+impl SparseMemory {
+    pub fn new() -> SparseMemory {
+        SparseMemory::default()
+    }
+}
+
+impl From<Vec<i64>> for SparseMemory {
+    fn from(program: Vec<i64>) -> SparseMemory {
+        let mut memory = SparseMemory::new();
+        for (address, value) in program.into_iter().enumerate() {
+            memory.set(address, value);
+        }
+        memory
+    }
+}
+
+impl Memory for SparseMemory {
+    #[inline]
+    fn get(&self, address: usize) -> i64 {
+        *self.cells.get(&address).unwrap_or(&0)
+    }
+
+    #[inline]
+    fn set(&mut self, address: usize, value: i64) {
+        self.cells.insert(address, value);
+        self.len = self.len.max(address + 1);
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn size_with(&self, address: usize) -> usize {
+        self.cells.len() + !self.cells.contains_key(&address) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(memory: &mut impl Memory) {
+        memory.set(3, 7);
+        memory.set(1 << 40, -5);
+        assert_eq!(memory.get(3), 7);
+        assert_eq!(memory.get(1 << 40), -5);
+        assert_eq!(memory.get(4), 0);
+        assert_eq!(memory.get((1 << 40) + 1), 0);
+        assert_eq!(memory.len(), (1 << 40) + 1);
+    }
+
+    #[test]
+    fn test_paged() {
+        let mut memory = PagedMemory::from(vec![1, 2, 3]);
+        check(&mut memory);
+        assert_eq!(memory.get(1), 2);
+        assert_eq!(memory.size_with(5), 2 * PAGE_SIZE);
+        assert_eq!(memory.size_with(5000), 3 * PAGE_SIZE);
+    }
+
+    #[test]
+    fn test_sparse() {
+        let mut memory = SparseMemory::from(vec![1, 2, 3]);
+        check(&mut memory);
+        assert_eq!(memory.get(1), 2);
+        assert_eq!(memory.size_with(3), 5);
+        assert_eq!(memory.size_with(5), 6);
+    }
+}
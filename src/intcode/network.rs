@@ -0,0 +1,338 @@
+use std::any::Any;
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::intcode::{IntcodeError, Machine, StepResult};
+
+// The ports a network reads from and writes to when it is used as a single machine.
+pub const INPUT: &str = "input";
+pub const OUTPUT: &str = "output";
+
+pub type NodeId = usize;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum NetworkState {
+    Running,
+    // every machine that hasn't halted is waiting for input
+    Quiescent,
+    Halted,
+}
+
+// A machine that can be looked at as its own type again.
+trait NodeMachine: Machine {
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<M: Machine + 'static> NodeMachine for M {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+struct Node {
+    name: String,
+    machine: Box<dyn NodeMachine>,
+    targets: Vec<NodeId>,
+    ports: Vec<String>,
+    halted: bool,
+}
+
+// Builds a network of machines, where every output of a machine is sent to the input of each
+// machine it has an edge to. Outside values come in and go out through named ports.
+#[derive(Default)]
+pub struct NetworkBuilder {
+    nodes: Vec<Node>,
+    inputs: BTreeMap<String, Vec<NodeId>>,
+    halt_on: Option<NodeId>,
+}
+
+impl NetworkBuilder {
+    pub fn new() -> NetworkBuilder {
+        NetworkBuilder::default()
+    }
+
+    pub fn node(&mut self, name: &str, machine: impl Machine + 'static) -> NodeId {
+        assert!(
+            self.nodes.iter().all(|node| node.name != name),
+            "duplicate node {}",
+            name
+        );
+        self.nodes.push(Node {
+            name: name.into(),
+            machine: Box::new(machine),
+            targets: Vec::new(),
+            ports: Vec::new(),
+            halted: false,
+        });
+        self.nodes.len() - 1
+    }
+
+    pub fn edge(&mut self, from: NodeId, to: NodeId) -> &mut NetworkBuilder {
+        assert!(to < self.nodes.len(), "unknown node {}", to);
+        self.nodes[from].targets.push(to);
+        self
+    }
+
+    // Values sent to the input port go to every node connected to it.
+    pub fn input(&mut self, port: &str, to: NodeId) -> &mut NetworkBuilder {
+        assert!(to < self.nodes.len(), "unknown node {}", to);
+        self.inputs.entry(port.into()).or_default().push(to);
+        self
+    }
+
+    pub fn output(&mut self, from: NodeId, port: &str) -> &mut NetworkBuilder {
+        self.nodes[from].ports.push(port.into());
+        self
+    }
+
+    // The network halts as soon as this node halts, even if others are still running.
+    pub fn halt_on(&mut self, node: NodeId) -> &mut NetworkBuilder {
+        assert!(node < self.nodes.len(), "unknown node {}", node);
+        self.halt_on = Some(node);
+        self
+    }
+
+    pub fn build(self) -> Network {
+        let outputs = self
+            .nodes
+            .iter()
+            .flat_map(|node| node.ports.iter())
+            .map(|port| (port.clone(), VecDeque::new()))
+            .collect();
+        Network {
+            nodes: self.nodes,
+            inputs: self.inputs,
+            outputs,
+            halt_on: self.halt_on,
+        }
+    }
+}
+
+pub struct Network {
+    nodes: Vec<Node>,
+    inputs: BTreeMap<String, Vec<NodeId>>,
+    outputs: BTreeMap<String, VecDeque<i64>>,
+    halt_on: Option<NodeId>,
+}
+
+impl Network {
+    pub fn node(&self, name: &str) -> Option<NodeId> {
+        self.nodes.iter().position(|node| node.name == name)
+    }
+
+    // The machine of a node, if it has type `M`, such as to look at its final state.
+    pub fn machine<M: Machine + 'static>(&self, id: NodeId) -> Option<&M> {
+        self.nodes[id].machine.as_any().downcast_ref()
+    }
+
+    pub fn is_halted(&self, id: NodeId) -> bool {
+        self.nodes[id].halted
+    }
+
+    pub fn send(&mut self, port: &str, value: i64) {
+        let targets = self
+            .inputs
+            .get(port)
+            .unwrap_or_else(|| panic!("unknown input port {}", port));
+        for &target in targets {
+            self.nodes[target].machine.add_input(value);
+        }
+    }
+
+    pub fn receive(&mut self, port: &str) -> Option<i64> {
+        self.port(port).pop_front()
+    }
+
+    pub fn drain(&mut self, port: &str) -> Vec<i64> {
+        self.port(port).drain(..).collect()
+    }
+
+    fn port(&mut self, port: &str) -> &mut VecDeque<i64> {
+        self.outputs
+            .get_mut(port)
+            .unwrap_or_else(|| panic!("unknown output port {}", port))
+    }
+
+    // Steps every machine that hasn't halted once, in the order they were added.
+    pub fn try_tick(&mut self) -> Result<NetworkState, IntcodeError> {
+        let mut progress = false;
+        for id in 0..self.nodes.len() {
+            if self.nodes[id].halted {
+                continue;
+            }
+            match self.nodes[id].machine.try_step()? {
+                StepResult::Ok => progress = true,
+                StepResult::NeedInput => {}
+                StepResult::Output(value) => {
+                    progress = true;
+                    for target in self.nodes[id].targets.clone() {
+                        // a halted machine doesn't read its input anymore
+                        if !self.nodes[target].halted {
+                            self.nodes[target].machine.add_input(value);
+                        }
+                    }
+                    for port in &self.nodes[id].ports {
+                        self.outputs.get_mut(port).unwrap().push_back(value);
+                    }
+                }
+                StepResult::Halt => {
+                    progress = true;
+                    self.nodes[id].halted = true;
+                }
+            }
+        }
+        let halted = match self.halt_on {
+            Some(id) => self.nodes[id].halted,
+            None => false,
+        };
+        Ok(if halted || self.nodes.iter().all(|node| node.halted) {
+            NetworkState::Halted
+        } else if progress {
+            NetworkState::Running
+        } else {
+            NetworkState::Quiescent
+        })
+    }
+
+    pub fn tick(&mut self) -> NetworkState {
+        self.try_tick().unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    // Runs until every machine has halted, or until the network needs more input.
+    pub fn try_settle(&mut self) -> Result<NetworkState, IntcodeError> {
+        loop {
+            match self.try_tick()? {
+                NetworkState::Running => {}
+                state => return Ok(state),
+            }
+        }
+    }
+
+    pub fn settle(&mut self) -> NetworkState {
+        self.try_settle().unwrap_or_else(|err| panic!("{}", err))
+    }
+}
+
+// Reads from the INPUT port and writes to the OUTPUT port.
+impl Machine for Network {
+    fn add_input(&mut self, value: i64) {
+        self.send(INPUT, value);
+    }
+
+    fn try_step(&mut self) -> Result<StepResult, IntcodeError> {
+        if let Some(value) = self.receive(OUTPUT) {
+            return Ok(StepResult::Output(value));
+        }
+        let state = self.try_tick()?;
+        Ok(match (self.receive(OUTPUT), state) {
+            (Some(value), _) => StepResult::Output(value),
+            (None, NetworkState::Running) => StepResult::Ok,
+            (None, NetworkState::Quiescent) => StepResult::NeedInput,
+            (None, NetworkState::Halted) => StepResult::Halt,
+        })
+    }
+}
+
+// A pipeline where every machine's output goes to the next machine's input. It halts once
+// the last machine halts, since the others can't produce any more output.
+pub fn chain<M: Machine + 'static>(machines: impl IntoIterator<Item = M>) -> Network {
+    let mut builder = NetworkBuilder::new();
+    let nodes = machines
+        .into_iter()
+        .enumerate()
+        .map(|(index, machine)| builder.node(&index.to_string(), machine))
+        .collect::<Vec<_>>();
+    assert!(!nodes.is_empty(), "expected at least one machine");
+    for pair in nodes.windows(2) {
+        builder.edge(pair[0], pair[1]);
+    }
+    let last = nodes[nodes.len() - 1];
+    builder
+        .input(INPUT, nodes[0])
+        .output(last, OUTPUT)
+        .halt_on(last);
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::ProgramMachine;
+
+    // adds one to every input until it reads a zero
+    fn incrementer() -> ProgramMachine {
+        let program = vec![
+            3, 15, 1006, 15, 14, 1001, 15, 1, 15, 4, 15, 1105, 1, 0, 99, 0,
+        ];
+        ProgramMachine::new(program, vec![])
+    }
+
+    #[test]
+    fn test_fan_out_and_fan_in() {
+        let mut builder = NetworkBuilder::new();
+        let source = builder.node("source", incrementer());
+        let left = builder.node("left", incrementer());
+        let right = builder.node("right", incrementer());
+        builder
+            .input("in", source)
+            .edge(source, left)
+            .edge(source, right)
+            .output(left, "out")
+            .output(right, "out")
+            .output(source, "tap");
+        let mut network = builder.build();
+        network.send("in", 10);
+        assert_eq!(network.settle(), NetworkState::Quiescent);
+        assert_eq!(network.drain("out"), vec![12, 12]);
+        assert_eq!(network.drain("tap"), vec![11]);
+        assert_eq!(network.node("right"), Some(right));
+
+        network.send("in", 0);
+        assert_eq!(network.settle(), NetworkState::Quiescent);
+        assert!(network.is_halted(source));
+        assert!(!network.is_halted(left));
+    }
+
+    #[test]
+    fn test_feedback_loop() {
+        // the second day 7 example, with phase settings 9,8,7,6,5
+        let program = vec![
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ];
+        let mut builder = NetworkBuilder::new();
+        let amplifiers = [9, 8, 7, 6, 5]
+            .iter()
+            .enumerate()
+            .map(|(index, &phase)| {
+                builder.node(
+                    &index.to_string(),
+                    ProgramMachine::new(program.clone(), vec![phase]),
+                )
+            })
+            .collect::<Vec<_>>();
+        for (index, &amplifier) in amplifiers.iter().enumerate() {
+            builder.edge(amplifier, amplifiers[(index + 1) % amplifiers.len()]);
+        }
+        builder
+            .input(INPUT, amplifiers[0])
+            .output(amplifiers[4], OUTPUT);
+        let mut network = builder.build();
+        network.add_input(0);
+        assert_eq!(Machine::run(&mut network).last(), Some(&139629729));
+    }
+
+    #[test]
+    fn test_chain_halts_with_last_machine() {
+        // the first machine echoes forever, the second halts after one value
+        let echo = ProgramMachine::new(vec![3, 0, 4, 0, 1105, 1, 0], vec![]);
+        let once = ProgramMachine::new(vec![3, 0, 4, 0, 99], vec![]);
+        let mut network = chain(vec![echo, once]);
+        network.add_input(7);
+        assert_eq!(Machine::run(&mut network), vec![7]);
+        assert!(!network.is_halted(0));
+        assert!(network.is_halted(1));
+        let once = network.machine::<ProgramMachine>(1).unwrap();
+        assert_eq!(once.pc(), 4);
+        assert!(network.machine::<Network>(1).is_none());
+    }
+}
@@ -0,0 +1,474 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::intcode::{IntcodeError, Machine, StepResult};
+
+// a node that computes for this long without blocking or sending yields to the others
+const MAX_STEPS_UNTIL_BLOCKED: u64 = 100_000;
+// special addresses may forward a packet to each other at most this many times
+const MAX_FORWARDS: usize = 1_000;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Packet {
+    pub source: i64,
+    pub destination: i64,
+    pub payload: Vec<i64>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Schedule {
+    // every node executes one instruction per tick, in address order
+    RoundRobin,
+    // every node executes one instruction per tick, in a shuffled order
+    Random { seed: u64 },
+    // every node runs until it waits for input or sends a packet
+    UntilBlocked,
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub nodes: usize,
+    // number of words in a packet after the destination address
+    pub arity: usize,
+    // what a node reads when it asks for input and has no packets
+    pub idle_input: i64,
+    pub schedule: Schedule,
+    pub log: bool,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            nodes: 50,
+            arity: 2,
+            idle_input: -1,
+            schedule: Schedule::RoundRobin,
+            log: false,
+        }
+    }
+}
+
+pub enum Action {
+    Continue,
+    Send(Packet),
+    Stop(i64),
+}
+
+// Handles the packets sent to a special address, such as a NAT or a monitor.
+pub trait Nat {
+    fn receive(&mut self, packet: &Packet) -> Action;
+
+    // Called whenever every node is waiting for packets.
+    fn idle(&mut self) -> Action {
+        Action::Continue
+    }
+}
+
+// Stops at the first packet sent to it, with the packet's last payload word, such as the
+// Y value of day 23's packets.
+pub struct FirstPacket;
+
+impl Nat for FirstPacket {
+    fn receive(&mut self, packet: &Packet) -> Action {
+        match packet.payload.last() {
+            Some(&value) => Action::Stop(value),
+            // nothing to report
+            None => Action::Continue,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum NetworkError {
+    Intcode { node: usize, error: IntcodeError },
+    Halted { node: usize },
+    UnknownAddress { packet: Packet },
+    // special addresses kept sending a packet on to each other
+    ForwardingLoop { packet: Packet },
+    // every node is idle, and no NAT woke the network up again
+    Deadlock,
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NetworkError::Intcode { node, error } => write!(f, "node {}: {}", node, error),
+            NetworkError::Halted { node } => write!(f, "node {} halted unexpectedly", node),
+            NetworkError::UnknownAddress { packet } => write!(
+                f,
+                "packet from {} sent to unknown address {}",
+                packet.source, packet.destination
+            ),
+            NetworkError::ForwardingLoop { packet } => write!(
+                f,
+                "packet from {} to {} forwarded too many times",
+                packet.source, packet.destination
+            ),
+            NetworkError::Deadlock => write!(f, "network is idle"),
+        }
+    }
+}
+
+impl Error for NetworkError {}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LogEntry {
+    pub tick: u64,
+    pub packet: Packet,
+}
+
+// xorshift64*, good enough to shuffle the schedule reproducibly. The seed must not be 0.
+pub(crate) struct Rng(pub(crate) u64);
+
+impl Rng {
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn shuffle<T>(&mut self, values: &mut [T]) {
+        for i in (1..values.len()).rev() {
+            values.swap(i, (self.next() % (i as u64 + 1)) as usize);
+        }
+    }
+}
+
+enum Event {
+    Busy,
+    Blocked,
+    Sent,
+}
+
+struct Node<M: Machine> {
+    machine: M,
+    queue: VecDeque<i64>,
+    outbox: Vec<i64>,
+    // idle inputs read since the node last sent or received anything
+    idle_reads: u32,
+}
+
+pub struct PacketNetwork<M: Machine> {
+    config: Config,
+    nodes: Vec<Node<M>>,
+    nats: BTreeMap<i64, Box<dyn Nat>>,
+    rng: Rng,
+    tick: u64,
+    log: Vec<LogEntry>,
+}
+
+impl<M: Machine> PacketNetwork<M> {
+    // Boots every node, and sends it its own address.
+    pub fn new(config: Config, mut boot: impl FnMut(usize) -> M) -> PacketNetwork<M> {
+        let nodes = (0..config.nodes)
+            .map(|address| Node {
+                machine: boot(address),
+                queue: VecDeque::from(vec![address as i64]),
+                outbox: Vec::new(),
+                idle_reads: 0,
+            })
+            .collect();
+        let seed = match config.schedule {
+            Schedule::Random { seed } => seed.max(1),
+            _ => 1,
+        };
+        PacketNetwork {
+            config,
+            nodes,
+            nats: BTreeMap::new(),
+            rng: Rng(seed),
+            tick: 0,
+            log: Vec::new(),
+        }
+    }
+
+    pub fn attach(&mut self, address: i64, nat: impl Nat + 'static) {
+        self.nats.insert(address, Box::new(nat));
+    }
+
+    pub fn log(&self) -> &[LogEntry] {
+        &self.log
+    }
+
+    // Writes the packet log as CSV, with one column per payload word.
+    pub fn export_log(&self, mut writer: impl Write) -> io::Result<()> {
+        write!(writer, "tick,source,destination")?;
+        for index in 0..self.config.arity {
+            write!(writer, ",payload{}", index)?;
+        }
+        writeln!(writer)?;
+        for entry in &self.log {
+            let packet = &entry.packet;
+            write!(
+                writer,
+                "{},{},{}",
+                entry.tick, packet.source, packet.destination
+            )?;
+            for value in &packet.payload {
+                write!(writer, ",{}", value)?;
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+
+    pub fn send(&mut self, mut packet: Packet) -> Result<Option<i64>, NetworkError> {
+        // a special address may answer with another packet, so deliver until one reaches a node
+        for _ in 0..MAX_FORWARDS {
+            if self.config.log {
+                self.log.push(LogEntry {
+                    tick: self.tick,
+                    packet: packet.clone(),
+                });
+            }
+            if packet.destination >= 0 && (packet.destination as usize) < self.nodes.len() {
+                let node = &mut self.nodes[packet.destination as usize];
+                node.queue.extend(&packet.payload);
+                node.idle_reads = 0;
+                return Ok(None);
+            }
+            let action = match self.nats.get_mut(&packet.destination) {
+                Some(nat) => nat.receive(&packet),
+                None => return Err(NetworkError::UnknownAddress { packet }),
+            };
+            match action {
+                Action::Continue => return Ok(None),
+                Action::Send(next) => packet = next,
+                Action::Stop(value) => return Ok(Some(value)),
+            }
+        }
+        Err(NetworkError::ForwardingLoop { packet })
+    }
+
+    fn act(&mut self, action: Action) -> Result<Option<i64>, NetworkError> {
+        match action {
+            Action::Continue => Ok(None),
+            Action::Send(packet) => self.send(packet),
+            Action::Stop(value) => Ok(Some(value)),
+        }
+    }
+
+    fn step_node(&mut self, address: usize) -> Result<(Event, Option<i64>), NetworkError> {
+        let node = &mut self.nodes[address];
+        let result = node.machine.try_step();
+        match result.map_err(|error| NetworkError::Intcode {
+            node: address,
+            error,
+        })? {
+            StepResult::Ok => Ok((Event::Busy, None)),
+            StepResult::NeedInput => match node.queue.pop_front() {
+                Some(value) => {
+                    node.machine.add_input(value);
+                    Ok((Event::Busy, None))
+                }
+                None => {
+                    node.machine.add_input(self.config.idle_input);
+                    node.idle_reads += 1;
+                    Ok((Event::Blocked, None))
+                }
+            },
+            StepResult::Output(value) => {
+                node.idle_reads = 0;
+                node.outbox.push(value);
+                if node.outbox.len() <= self.config.arity {
+                    return Ok((Event::Busy, None));
+                }
+                let mut words = node.outbox.drain(..);
+                let destination = words.next().unwrap();
+                let packet = Packet {
+                    source: address as i64,
+                    destination,
+                    payload: words.collect(),
+                };
+                Ok((Event::Sent, self.send(packet)?))
+            }
+            StepResult::Halt => Err(NetworkError::Halted { node: address }),
+        }
+    }
+
+    // A node that went round its polling loop without sending anything is waiting for packets.
+    fn is_idle(&self) -> bool {
+        self.nodes
+            .iter()
+            .all(|node| node.idle_reads >= 2 && node.queue.is_empty() && node.outbox.is_empty())
+    }
+
+    // Runs one round of the schedule. Returns a value once a NAT stops the simulation.
+    pub fn try_tick(&mut self) -> Result<Option<i64>, NetworkError> {
+        self.tick += 1;
+        let mut order = (0..self.nodes.len()).collect::<Vec<_>>();
+        if let Schedule::Random { .. } = self.config.schedule {
+            self.rng.shuffle(&mut order);
+        }
+        for address in order {
+            if let Schedule::UntilBlocked = self.config.schedule {
+                for _ in 0..MAX_STEPS_UNTIL_BLOCKED {
+                    match self.step_node(address)? {
+                        (_, Some(value)) => return Ok(Some(value)),
+                        (Event::Busy, None) => {}
+                        (Event::Blocked, None) | (Event::Sent, None) => break,
+                    }
+                }
+            } else if let (_, Some(value)) = self.step_node(address)? {
+                return Ok(Some(value));
+            }
+        }
+        if !self.is_idle() {
+            return Ok(None);
+        }
+        let mut woken = false;
+        let addresses = self.nats.keys().cloned().collect::<Vec<_>>();
+        for address in addresses {
+            let action = self.nats.get_mut(&address).unwrap().idle();
+            woken |= matches!(action, Action::Send(_) | Action::Stop(_));
+            if let Some(value) = self.act(action)? {
+                return Ok(Some(value));
+            }
+        }
+        if woken {
+            Ok(None)
+        } else {
+            Err(NetworkError::Deadlock)
+        }
+    }
+
+    pub fn run(&mut self) -> Result<i64, NetworkError> {
+        loop {
+            if let Some(value) = self.try_tick()? {
+                return Ok(value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::parse_list;
+    use crate::intcode::ProgramMachine;
+
+    // resends the last packet to node 0 whenever the network goes idle
+    struct Resend {
+        last: Option<Packet>,
+        sent: Vec<i64>,
+    }
+
+    impl Nat for Resend {
+        fn receive(&mut self, packet: &Packet) -> Action {
+            self.last = Some(packet.clone());
+            Action::Continue
+        }
+
+        fn idle(&mut self) -> Action {
+            let packet = match &self.last {
+                Some(packet) => packet,
+                None => return Action::Continue,
+            };
+            let y = packet.payload[1];
+            if self.sent.last() == Some(&y) {
+                return Action::Stop(y);
+            }
+            self.sent.push(y);
+            Action::Send(Packet {
+                source: 255,
+                destination: 0,
+                payload: packet.payload.clone(),
+            })
+        }
+    }
+
+    fn network(schedule: Schedule, log: bool) -> PacketNetwork<ProgramMachine> {
+        let program: Vec<i64> = parse_list(include_str!("../bin/day23/input"), ',');
+        let config = Config {
+            schedule,
+            log,
+            ..Config::default()
+        };
+        PacketNetwork::new(config, |_| ProgramMachine::new(program.clone(), vec![]))
+    }
+
+    #[test]
+    fn test_schedules() {
+        for &schedule in &[
+            Schedule::RoundRobin,
+            Schedule::Random { seed: 42 },
+            Schedule::UntilBlocked,
+        ] {
+            let mut first = network(schedule, false);
+            first.attach(255, FirstPacket);
+            assert_eq!(first.run(), Ok(22829));
+
+            let mut resend = network(schedule, false);
+            resend.attach(
+                255,
+                Resend {
+                    last: None,
+                    sent: Vec::new(),
+                },
+            );
+            assert_eq!(resend.run(), Ok(15678));
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut unattached = network(Schedule::UntilBlocked, false);
+        assert!(matches!(
+            unattached.run(),
+            Err(NetworkError::UnknownAddress { .. })
+        ));
+
+        // a NAT that never wakes the network up
+        struct Sink;
+        impl Nat for Sink {
+            fn receive(&mut self, _: &Packet) -> Action {
+                Action::Continue
+            }
+        }
+        let mut sink = network(Schedule::UntilBlocked, false);
+        sink.attach(255, Sink);
+        assert_eq!(sink.run(), Err(NetworkError::Deadlock));
+
+        // two special addresses that keep passing packets back and forth
+        struct Bounce(i64);
+        impl Nat for Bounce {
+            fn receive(&mut self, packet: &Packet) -> Action {
+                Action::Send(Packet {
+                    source: packet.destination,
+                    destination: self.0,
+                    payload: packet.payload.clone(),
+                })
+            }
+        }
+        let mut bounce = network(Schedule::UntilBlocked, false);
+        bounce.attach(255, Bounce(256));
+        bounce.attach(256, Bounce(255));
+        assert!(matches!(
+            bounce.run(),
+            Err(NetworkError::ForwardingLoop { .. })
+        ));
+    }
+
+    #[test]
+    fn test_log() {
+        let mut logged = network(Schedule::UntilBlocked, true);
+        logged.attach(255, FirstPacket);
+        logged.run().unwrap();
+        let last = logged.log().last().unwrap();
+        assert_eq!(last.packet.destination, 255);
+        let mut csv = Vec::new();
+        logged.export_log(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "tick,source,destination,payload0,payload1");
+        assert_eq!(lines.len(), logged.log().len() + 1);
+        let expected = format!(
+            "{},{},255,{},22829",
+            last.tick, last.packet.source, last.packet.payload[0]
+        );
+        assert_eq!(lines[lines.len() - 1], expected);
+    }
+}
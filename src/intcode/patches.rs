@@ -0,0 +1,301 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+// A write to a word that already ran as part of an instruction.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Patch {
+    pub writer: usize,
+    pub address: usize,
+    pub old: i64,
+    pub new: i64,
+}
+
+// Tracks which words ran as instructions, and every write to them afterwards.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Patches {
+    // the pc of every instruction that ran
+    starts: HashSet<usize>,
+    // every word of those instructions
+    code: HashSet<usize>,
+    // the words of the instruction that is running, which only count as code once it ran
+    running: Option<(usize, usize)>,
+    pub events: Vec<Patch>,
+    // taken when the program halts
+    halt_summary: Option<Summary>,
+}
+
+impl Patches {
+    pub fn new() -> Patches {
+        Patches::default()
+    }
+
+    pub fn execute(&mut self, pc: usize, length: usize) {
+        if self.starts.insert(pc) {
+            self.code.extend(pc..pc + length);
+        }
+    }
+
+    // Marks an instruction as about to run, so it can catch itself patching its own words.
+    pub fn begin(&mut self, pc: usize, length: usize) {
+        self.running = Some((pc, length));
+    }
+
+    // Ends the instruction from `begin`. It only counts as having run if it didn't fail or
+    // wait for input.
+    pub fn finish(&mut self, ran: bool) {
+        if let (Some((pc, length)), true) = (self.running.take(), ran) {
+            self.execute(pc, length);
+        }
+    }
+
+    pub fn halt(&mut self) {
+        self.halt_summary = Some(self.summary());
+    }
+
+    // The summary from when the program halted, if it did.
+    pub fn halt_summary(&self) -> Option<&Summary> {
+        self.halt_summary.as_ref()
+    }
+
+    pub fn write(&mut self, writer: usize, address: usize, old: i64, new: i64) {
+        let running = match self.running {
+            Some((pc, length)) => (pc..pc + length).contains(&address),
+            None => false,
+        };
+        if running || self.code.contains(&address) {
+            self.events.push(Patch {
+                writer,
+                address,
+                old,
+                new,
+            });
+        }
+    }
+
+    pub fn is_code(&self, address: usize) -> bool {
+        self.code.contains(&address)
+    }
+
+    // Whether the patch changed the instruction word itself, rather than an operand.
+    pub fn is_instruction(&self, patch: &Patch) -> bool {
+        self.starts.contains(&patch.address)
+    }
+
+    pub fn summary(&self) -> Summary {
+        let mut summary = Summary::default();
+        for patch in &self.events {
+            summary.patches += 1;
+            if patch.old == patch.new {
+                summary.unchanged += 1;
+            }
+            if self.is_instruction(patch) {
+                summary.instructions += 1;
+            }
+            *summary
+                .writers
+                .entry(patch.writer)
+                .or_default()
+                .entry(patch.address)
+                .or_insert(0) += 1;
+        }
+        summary
+    }
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Summary {
+    pub patches: usize,
+    // writes of the value that was already there
+    pub unchanged: usize,
+    // patches of an instruction word, rather than an operand
+    pub instructions: usize,
+    // how often each instruction wrote to each address
+    pub writers: BTreeMap<usize, BTreeMap<usize, usize>>,
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} writes to code that already ran, {} to instruction words, {} unchanged",
+            self.patches, self.instructions, self.unchanged
+        )?;
+        for (writer, addresses) in &self.writers {
+            let targets = addresses.iter().map(|(address, &count)| match count {
+                1 => format!("[{}]", address),
+                _ => format!("[{}] x{}", address, count),
+            });
+            writeln!(
+                f,
+                "{:>6}: {}",
+                writer,
+                targets.collect::<Vec<_>>().join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Patch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} patched [{}]: {} -> {}",
+            self.writer, self.address, self.old, self.new
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::parse_list;
+    use crate::intcode::asm::assemble;
+    use crate::intcode::{Machine, ProgramMachine, StepResult};
+
+    fn run(program: Vec<i64>, input: Vec<i64>) -> Patches {
+        let mut machine = ProgramMachine::new(program, input);
+        machine.set_patch_detection(true);
+        machine.run();
+        machine.take_patches().unwrap()
+    }
+
+    #[test]
+    fn test_patches() {
+        // counts down, then turns its last output into an immediate one
+        let program = assemble(
+            "
+                in [counter]
+            loop:
+                out [counter]
+                add [counter], #-1, [counter]
+                jt [counter], #loop
+                add #104, #0, [patch]
+                add #7, #0, [patch+1]
+            patch:
+                out [7]
+                hlt
+            counter: db 0
+            ",
+        )
+        .unwrap();
+        let patches = run(program, vec![2]);
+        // the output hasn't run yet when it gets patched, and the counter is data
+        assert!(patches.events.is_empty());
+
+        // the same, but patching the output after it ran in the loop
+        let program = assemble(
+            "
+                in [counter]
+            loop:
+                out [counter]
+                add [counter], #-1, [counter]
+                jt [counter], #loop
+                add #104, #0, [loop]
+                add #counter, #0, [loop+1]
+                jt #1, #loop
+            counter: db 0
+            ",
+        )
+        .unwrap();
+        let mut machine = ProgramMachine::new(program, vec![2]);
+        machine.set_patch_detection(true);
+        assert_eq!(machine.run_to_output(), Some(2));
+        assert_eq!(machine.run_to_output(), Some(1));
+        assert_eq!(machine.run_to_output(), Some(22));
+        let patches = machine.patches().unwrap();
+        assert_eq!(
+            patches.events,
+            vec![
+                Patch {
+                    writer: 11,
+                    address: 2,
+                    old: 4,
+                    new: 104
+                },
+                Patch {
+                    writer: 15,
+                    address: 3,
+                    old: 22,
+                    new: 22
+                },
+            ]
+        );
+        assert!(patches.is_instruction(&patches.events[0]));
+        assert!(!patches.is_instruction(&patches.events[1]));
+        assert_eq!(
+            patches.summary().to_string(),
+            "2 writes to code that already ran, 1 to instruction words, 1 unchanged\n    \
+             11: [2]\n    \
+             15: [3]\n"
+        );
+    }
+
+    #[test]
+    fn test_only_instructions_that_ran() {
+        // waits for input, but is then sent to overwrite the input instruction instead
+        let program = assemble(
+            "
+            wait:
+                in [value]
+                hlt
+            patch:
+                add #0, #0, [wait]
+                hlt
+            value: db 0
+            ",
+        )
+        .unwrap();
+        let mut machine = ProgramMachine::new(program, vec![]);
+        machine.set_patch_detection(true);
+        assert_eq!(machine.try_step(), Ok(StepResult::NeedInput));
+        machine.set_pc(3);
+        machine.run();
+        let patches = machine.take_patches().unwrap();
+        assert!(patches.events.is_empty());
+        assert_eq!(
+            patches.halt_summary().unwrap().to_string(),
+            "0 writes to code that already ran, 0 to instruction words, 0 unchanged\n"
+        );
+
+        // an instruction that overwrites its own opcode with the same value
+        let mut machine = ProgramMachine::new(vec![1101, 1100, 1, 0, 99], vec![]);
+        machine.set_patch_detection(true);
+        assert!(machine.patches().unwrap().halt_summary().is_none());
+        machine.run();
+        let patches = machine.take_patches().unwrap();
+        assert_eq!(
+            patches.events,
+            vec![Patch {
+                writer: 0,
+                address: 0,
+                old: 1101,
+                new: 1101
+            }]
+        );
+        assert_eq!(patches.halt_summary().unwrap().patches, 1);
+    }
+
+    #[test]
+    fn test_day19() {
+        // the tractor beam probe negates one of its jump targets, and the routine at 225
+        // patches the target of the call it makes
+        let program: Vec<i64> = parse_list(include_str!("../bin/day19/input"), ',');
+        let patches = run(program, vec![3, 4]);
+        assert_eq!(
+            patches.events[3],
+            Patch {
+                writer: 176,
+                address: 132,
+                old: 303,
+                new: -303
+            }
+        );
+        assert_eq!(
+            patches.summary().to_string(),
+            "5 writes to code that already ran, 0 to instruction words, 1 unchanged\n   \
+             176: [132]\n   \
+             227: [249] x4\n"
+        );
+    }
+}
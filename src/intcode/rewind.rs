@@ -0,0 +1,265 @@
+use std::collections::{BTreeSet, VecDeque};
+
+use crate::intcode::trace::{trace_step, MemoryWrite};
+use crate::intcode::{Instruction, IntcodeError, Machine, ProgramMachine, Snapshot, StepResult};
+
+const CHECKPOINT_INTERVAL: u64 = 1000;
+const MAX_CHECKPOINTS: usize = 100;
+
+// What it takes to undo one step.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Undo {
+    pub pc: usize,
+    pub base: i64,
+    pub write: Option<MemoryWrite>,
+    pub input: Option<i64>,
+    pub output: Option<i64>,
+}
+
+// Records every step, so the machine can run backwards.
+//
+// Only the steps since the last checkpoint are kept in the undo log. Stepping back past a
+// checkpoint restores the one before it and replays up to where the machine was, with the
+// input it consumed back then. Only the last few checkpoints are kept, which bounds how far
+// back the machine can go.
+pub struct RecordingMachine {
+    machine: ProgramMachine,
+    interval: u64,
+    max_checkpoints: usize,
+    step: u64,
+    log: Vec<Undo>,
+    checkpoints: VecDeque<(u64, Snapshot)>,
+    // input consumed since the oldest checkpoint, by step
+    inputs: VecDeque<(u64, i64)>,
+}
+
+impl RecordingMachine {
+    pub fn new(machine: ProgramMachine) -> RecordingMachine {
+        RecordingMachine::with_checkpoints(machine, CHECKPOINT_INTERVAL, MAX_CHECKPOINTS)
+    }
+
+    pub fn with_checkpoints(
+        machine: ProgramMachine,
+        interval: u64,
+        max_checkpoints: usize,
+    ) -> RecordingMachine {
+        let mut checkpoints = VecDeque::new();
+        checkpoints.push_back((0, machine.snapshot()));
+        RecordingMachine {
+            machine,
+            interval: interval.max(1),
+            max_checkpoints: max_checkpoints.max(1),
+            step: 0,
+            log: Vec::new(),
+            checkpoints,
+            inputs: VecDeque::new(),
+        }
+    }
+
+    pub fn machine(&self) -> &ProgramMachine {
+        &self.machine
+    }
+
+    pub fn into_inner(self) -> ProgramMachine {
+        self.machine
+    }
+
+    // The number of steps executed so far, minus the ones undone.
+    pub fn steps(&self) -> u64 {
+        self.step
+    }
+
+    // How far back the machine can go.
+    pub fn oldest(&self) -> u64 {
+        self.checkpoints.front().unwrap().0
+    }
+
+    fn record(&mut self, checkpoint: bool) -> Result<StepResult, IntcodeError> {
+        let trace = trace_step(&mut self.machine)?;
+        if let StepResult::NeedInput = trace.result {
+            return Ok(trace.result);
+        }
+        let input = match trace.instruction {
+            Instruction::Read(_) => trace.write.map(|write| write.new),
+            _ => None,
+        };
+        if let Some(value) = input {
+            self.inputs.push_back((self.step, value));
+        }
+        self.log.push(Undo {
+            pc: trace.pc,
+            base: trace.base,
+            write: trace.write,
+            input,
+            output: match trace.result {
+                StepResult::Output(value) => Some(value),
+                _ => None,
+            },
+        });
+        self.step += 1;
+        if checkpoint && self.step.is_multiple_of(self.interval) {
+            self.checkpoints
+                .push_back((self.step, self.machine.snapshot()));
+            self.log.clear();
+            if self.checkpoints.len() > self.max_checkpoints {
+                self.checkpoints.pop_front();
+                let oldest = self.oldest();
+                while matches!(self.inputs.front(), Some(&(step, _)) if step < oldest) {
+                    self.inputs.pop_front();
+                }
+            }
+        }
+        Ok(trace.result)
+    }
+
+    // Rebuilds the undo log from the last checkpoint before the current step.
+    fn replay(&mut self) {
+        let (start, mut snapshot) = self
+            .checkpoints
+            .iter()
+            .rev()
+            .find(|(step, _)| *step < self.step)
+            .cloned()
+            .unwrap();
+        let end = self.step;
+        let pending = self.machine.input.clone();
+        snapshot.input = self
+            .inputs
+            .iter()
+            .filter(|(step, _)| *step >= start)
+            .map(|&(_, value)| value)
+            .collect();
+        while matches!(self.inputs.back(), Some(&(step, _)) if step >= start) {
+            self.inputs.pop_back();
+        }
+        self.machine.restore(&snapshot);
+        self.step = start;
+        self.log.clear();
+        while self.step < end {
+            // these steps succeeded before, with the same memory and input
+            self.record(false).expect("replay failed");
+        }
+        self.machine.input.extend(pending);
+    }
+
+    // Undoes the last step. Returns None at the start of the recorded history.
+    pub fn step_back(&mut self) -> Option<Undo> {
+        if self.step == self.oldest() {
+            return None;
+        }
+        if self.log.is_empty() {
+            self.replay();
+        }
+        let undo = self.log.pop().unwrap();
+        if let Some(write) = undo.write {
+            self.machine.poke(write.address, write.old);
+        }
+        if let Some(value) = undo.input {
+            self.machine.input.push_front(value);
+            self.inputs.pop_back();
+        }
+        self.machine.set_pc(undo.pc);
+        self.machine.set_base(undo.base);
+        self.step -= 1;
+        // the machine may take a different path from here
+        while matches!(self.checkpoints.back(), Some(&(step, _)) if step > self.step) {
+            self.checkpoints.pop_back();
+        }
+        Some(undo)
+    }
+
+    // Steps back until the machine is about to execute one of the breakpoints again.
+    // Returns false if it reached the start of the recorded history instead.
+    pub fn run_back_to_breakpoint(&mut self, breakpoints: &BTreeSet<usize>) -> bool {
+        while self.step_back().is_some() {
+            if breakpoints.contains(&self.machine.pc()) {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+impl Machine for RecordingMachine {
+    fn add_input(&mut self, value: i64) {
+        self.machine.add_input(value);
+    }
+
+    fn try_step(&mut self) -> Result<StepResult, IntcodeError> {
+        self.record(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::parse_list;
+
+    fn day5() -> ProgramMachine {
+        let program: Vec<i64> = parse_list(include_str!("../bin/day5/input"), ',');
+        ProgramMachine::new(program, vec![])
+    }
+
+    #[test]
+    fn test_step_back() {
+        let mut machine = RecordingMachine::with_checkpoints(day5(), 7, 100);
+        machine.add_input(5);
+        let mut history = vec![machine.machine().snapshot()];
+        let mut outputs = Vec::new();
+        loop {
+            let result = machine.step();
+            history.push(machine.machine().snapshot());
+            match result {
+                StepResult::Output(value) => outputs.push(value),
+                StepResult::Halt => break,
+                _ => {}
+            }
+        }
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(machine.steps(), history.len() as u64 - 1);
+
+        let mut undone_outputs = Vec::new();
+        while let Some(undo) = machine.step_back() {
+            undone_outputs.extend(undo.output);
+            assert_eq!(
+                machine.machine().snapshot(),
+                history[machine.steps() as usize]
+            );
+        }
+        assert_eq!(machine.steps(), 0);
+        assert_eq!(undone_outputs, outputs);
+
+        // and forwards again, with the input it consumed back in the queue
+        assert_eq!(Machine::run(&mut machine), outputs);
+    }
+
+    #[test]
+    fn test_bounded_history() {
+        let mut machine = RecordingMachine::with_checkpoints(day5(), 10, 3);
+        machine.add_input(5);
+        for _ in 0..45 {
+            machine.step();
+        }
+        assert_eq!(machine.oldest(), 20);
+        let steps = std::iter::from_fn(|| machine.step_back()).count();
+        assert_eq!(steps, 25);
+        assert_eq!(machine.steps(), 20);
+    }
+
+    #[test]
+    fn test_run_back_to_breakpoint() {
+        // counts down from 3, printing each value
+        let program = vec![4, 10, 1001, 10, -1, 10, 1005, 10, 0, 99, 3];
+        let mut machine = RecordingMachine::new(ProgramMachine::new(program, vec![]));
+        assert_eq!(Machine::run(&mut machine), vec![3, 2, 1]);
+        let breakpoints = [0].iter().cloned().collect();
+        assert!(machine.run_back_to_breakpoint(&breakpoints));
+        assert_eq!(machine.machine().peek(10), 1);
+        assert!(machine.run_back_to_breakpoint(&breakpoints));
+        assert_eq!(machine.machine().peek(10), 2);
+        assert!(machine.run_back_to_breakpoint(&breakpoints));
+        assert_eq!(machine.machine().peek(10), 3);
+        assert!(!machine.run_back_to_breakpoint(&breakpoints));
+        assert_eq!(machine.steps(), 0);
+    }
+}
@@ -0,0 +1,189 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::intcode::ProgramMachine;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Snapshot {
+    pub program: Vec<i64>,
+    pub pc: usize,
+    pub base: i64,
+    pub input: Vec<i64>,
+}
+
+impl ProgramMachine {
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            program: self.program.clone(),
+            pc: self.pc,
+            base: self.base,
+            input: self.input.iter().copied().collect(),
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.program.clone_from(&snapshot.program);
+        self.pc = snapshot.pc;
+        self.base = snapshot.base;
+        self.input.clear();
+        self.input.extend(&snapshot.input);
+        self.fill_cache();
+    }
+}
+
+impl From<Snapshot> for ProgramMachine {
+    fn from(snapshot: Snapshot) -> ProgramMachine {
+        let mut machine = ProgramMachine::new(snapshot.program, snapshot.input);
+        machine.pc = snapshot.pc;
+        machine.base = snapshot.base;
+        machine
+    }
+}
+
+impl Snapshot {
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Snapshot> {
+        fs::read_to_string(path)?
+            .parse()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+fn join(values: &[i64]) -> String {
+    values
+        .iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "pc {}", self.pc)?;
+        writeln!(f, "base {}", self.base)?;
+        writeln!(f, "input {}", join(&self.input))?;
+        writeln!(f, "program {}", join(&self.program))
+    }
+}
+
+impl FromStr for Snapshot {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Snapshot, String> {
+        let mut pc = None;
+        let mut base = None;
+        let mut input = None;
+        let mut program = None;
+        for line in s.lines().filter(|line| !line.trim().is_empty()) {
+            let (key, value) = match line.find(' ') {
+                Some(index) => (&line[..index], line[index + 1..].trim()),
+                None => (line.trim(), ""),
+            };
+            let parse_number = |value: &str| {
+                value
+                    .trim()
+                    .parse::<i64>()
+                    .map_err(|err| format!("invalid {}: {}", key, err))
+            };
+            // an empty list is written as the key on its own
+            let parse_numbers = |value: &str| match value {
+                "" => Ok(Vec::new()),
+                value => value
+                    .split(',')
+                    .map(parse_number)
+                    .collect::<Result<Vec<_>, _>>(),
+            };
+            match key {
+                "pc" => match parse_number(value)? {
+                    value if value < 0 => return Err(format!("invalid pc: {}", value)),
+                    value => pc = Some(value as usize),
+                },
+                "base" => base = Some(parse_number(value)?),
+                "input" => input = Some(parse_numbers(value)?),
+                "program" => program = Some(parse_numbers(value)?),
+                _ => return Err(format!("unexpected key {}", key)),
+            }
+        }
+        Ok(Snapshot {
+            program: program.ok_or("missing program")?,
+            pc: pc.ok_or("missing pc")?,
+            base: base.ok_or("missing base")?,
+            input: input.ok_or("missing input")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::Machine;
+
+    #[test]
+    fn test_restore() {
+        // outputs its input plus a running counter
+        let program = vec![3, 20, 1001, 21, 1, 21, 1, 20, 21, 22, 4, 22, 1105, 1, 0];
+        let mut machine = ProgramMachine::new(program, vec![10, 20]);
+        assert_eq!(machine.run_to_output(), Some(11));
+        let snapshot = machine.snapshot();
+        assert_eq!(machine.run_to_output(), Some(22));
+        machine.restore(&snapshot);
+        assert_eq!(machine.run_to_output(), Some(22));
+        let mut resumed = ProgramMachine::from(snapshot);
+        assert_eq!(resumed.run_to_output(), Some(22));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let snapshot = Snapshot {
+            program: vec![3, 5, 4, 5, 99, -7],
+            pc: 2,
+            base: -3,
+            input: vec![],
+        };
+        let text = snapshot.to_string();
+        assert_eq!(text, "pc 2\nbase -3\ninput \nprogram 3,5,4,5,99,-7\n");
+        assert_eq!(text.parse(), Ok(snapshot.clone()));
+        let empty = Snapshot {
+            program: vec![],
+            pc: 0,
+            base: 0,
+            input: vec![],
+        };
+        assert_eq!(empty.to_string(), "pc 0\nbase 0\ninput \nprogram \n");
+        assert_eq!(empty.to_string().parse(), Ok(empty));
+
+        // unique to this test and process, so concurrent runs don't share the file
+        let name = format!("intcode-snapshot-save-and-load-{}.txt", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        snapshot.save(&path).unwrap();
+        assert_eq!(Snapshot::load(&path).unwrap(), snapshot);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_invalid() {
+        let parse = |text: &str| text.parse::<Snapshot>();
+        assert_eq!(
+            parse("pc 0\nbase 0\ninput 1,x\nprogram 99\n"),
+            Err("invalid input: invalid digit found in string".to_string())
+        );
+        assert_eq!(
+            parse("pc 0\nbase 0\ninput \nprogram 99,,1\n"),
+            Err("invalid program: cannot parse integer from empty string".to_string())
+        );
+        assert_eq!(
+            parse("pc -1\nbase 0\ninput \nprogram 99\n"),
+            Err("invalid pc: -1".to_string())
+        );
+        assert_eq!(
+            parse("pc 0\ninput \nprogram 99\n"),
+            Err("missing base".to_string())
+        );
+    }
+}
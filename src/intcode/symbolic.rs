@@ -0,0 +1,934 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::rc::Rc;
+
+use crate::intcode::{InputValue, Instruction, OutputValue};
+
+const MAX_STEPS: usize = 100_000;
+const MAX_PATHS: usize = 100_000;
+// the most values a symbolic address or operand may be split into
+const MAX_FORKS: usize = 10_000;
+// the most values the solver tries for a single symbol
+const MAX_VALUES: usize = 1_000_000;
+
+// A linear combination of symbols, plus a constant.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Linear {
+    pub constant: i64,
+    pub terms: BTreeMap<usize, i64>,
+}
+
+impl Linear {
+    pub fn constant(value: i64) -> Linear {
+        Linear {
+            constant: value,
+            terms: BTreeMap::new(),
+        }
+    }
+
+    pub fn symbol(symbol: usize) -> Linear {
+        let mut terms = BTreeMap::new();
+        terms.insert(symbol, 1);
+        Linear { constant: 0, terms }
+    }
+
+    pub fn as_constant(&self) -> Option<i64> {
+        if self.terms.is_empty() {
+            Some(self.constant)
+        } else {
+            None
+        }
+    }
+
+    fn add(&self, other: &Linear) -> Option<Linear> {
+        let mut sum = self.clone();
+        sum.constant = sum.constant.checked_add(other.constant)?;
+        for (&symbol, &coefficient) in &other.terms {
+            let term = sum.terms.entry(symbol).or_insert(0);
+            *term = term.checked_add(coefficient)?;
+            if *term == 0 {
+                sum.terms.remove(&symbol);
+            }
+        }
+        Some(sum)
+    }
+
+    fn scale(&self, factor: i64) -> Option<Linear> {
+        if factor == 0 {
+            return Some(Linear::constant(0));
+        }
+        let mut terms = BTreeMap::new();
+        for (&symbol, &coefficient) in &self.terms {
+            terms.insert(symbol, coefficient.checked_mul(factor)?);
+        }
+        Some(Linear {
+            constant: self.constant.checked_mul(factor)?,
+            terms,
+        })
+    }
+
+    fn sub(&self, other: &Linear) -> Option<Linear> {
+        self.add(&other.scale(-1)?)
+    }
+
+    // Replaces the symbols whose value is known.
+    fn substitute(&self, fixed: &BTreeMap<usize, i64>) -> Option<Linear> {
+        let mut result = Linear::constant(self.constant);
+        for (&symbol, &coefficient) in &self.terms {
+            let term = match fixed.get(&symbol) {
+                Some(&value) => Linear::constant(coefficient.checked_mul(value)?),
+                None => Linear::symbol(symbol).scale(coefficient)?,
+            };
+            result = result.add(&term)?;
+        }
+        Some(result)
+    }
+
+    // The smallest and largest value, with each symbol either assigned or anywhere in its
+    // domain.
+    fn bounds(&self, domains: &[(i64, i64)], assigned: &[Option<i64>]) -> (i128, i128) {
+        let (mut min, mut max) = (self.constant as i128, self.constant as i128);
+        for (&symbol, &coefficient) in &self.terms {
+            let coefficient = coefficient as i128;
+            let (low, high) = match assigned[symbol] {
+                Some(value) => (value, value),
+                None => domains[symbol],
+            };
+            let (a, b) = (coefficient * low as i128, coefficient * high as i128);
+            min += a.min(b);
+            max += a.max(b);
+        }
+        (min, max)
+    }
+}
+
+impl fmt::Display for Linear {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut first = true;
+        for (&symbol, &coefficient) in &self.terms {
+            let sign = if coefficient < 0 { "-" } else { "+" };
+            match (first, coefficient.abs()) {
+                (true, 1) if coefficient < 0 => write!(f, "-s{}", symbol)?,
+                (true, 1) => write!(f, "s{}", symbol)?,
+                (true, _) => write!(f, "{}*s{}", coefficient, symbol)?,
+                (false, 1) => write!(f, " {} s{}", sign, symbol)?,
+                (false, abs) => write!(f, " {} {}*s{}", sign, abs, symbol)?,
+            }
+            first = false;
+        }
+        match (first, self.constant) {
+            (true, constant) => write!(f, "{}", constant),
+            (false, 0) => Ok(()),
+            (false, constant) if constant < 0 => write!(f, " - {}", -constant),
+            (false, constant) => write!(f, " + {}", constant),
+        }
+    }
+}
+
+// A value that isn't worked out until it's needed: a read through a symbolic address, or
+// anything computed from one, and products of symbols.
+#[derive(Debug)]
+pub enum Deferred {
+    Read {
+        address: Value,
+        memory: Rc<Vec<Value>>,
+    },
+    Add(Value, Value),
+    Mul(Value, Value),
+}
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    Linear(Linear),
+    Deferred(Rc<Deferred>),
+}
+
+impl Value {
+    fn constant(value: i64) -> Value {
+        Value::Linear(Linear::constant(value))
+    }
+
+    fn add(&self, other: &Value) -> Option<Value> {
+        match (self, other) {
+            (Value::Linear(a), Value::Linear(b)) => a.add(b).map(Value::Linear),
+            _ => Some(Value::Deferred(Rc::new(Deferred::Add(
+                self.clone(),
+                other.clone(),
+            )))),
+        }
+    }
+
+    fn mul(&self, other: &Value) -> Option<Value> {
+        match (self, other) {
+            (Value::Linear(a), Value::Linear(b)) => match (a.as_constant(), b.as_constant()) {
+                (Some(factor), _) => b.scale(factor).map(Value::Linear),
+                (_, Some(factor)) => a.scale(factor).map(Value::Linear),
+                _ => Some(Value::Deferred(Rc::new(Deferred::Mul(
+                    self.clone(),
+                    other.clone(),
+                )))),
+            },
+            _ => Some(Value::Deferred(Rc::new(Deferred::Mul(
+                self.clone(),
+                other.clone(),
+            )))),
+        }
+    }
+
+    // Folds in the symbols whose value is known. Returns None if that makes the value
+    // invalid, such as a read from a negative address.
+    fn simplify(&self, fixed: &BTreeMap<usize, i64>) -> Option<Value> {
+        let deferred = match self {
+            Value::Linear(linear) => return linear.substitute(fixed).map(Value::Linear),
+            Value::Deferred(deferred) => deferred,
+        };
+        match &**deferred {
+            Deferred::Read { address, memory } => match address.simplify(fixed)? {
+                Value::Linear(linear) => match linear.as_constant() {
+                    Some(address) if address < 0 => None,
+                    Some(address) => {
+                        let value = memory.get(address as usize).cloned();
+                        value.unwrap_or_else(|| Value::constant(0)).simplify(fixed)
+                    }
+                    None => Some(Value::Deferred(Rc::new(Deferred::Read {
+                        address: Value::Linear(linear),
+                        memory: memory.clone(),
+                    }))),
+                },
+                address => Some(Value::Deferred(Rc::new(Deferred::Read {
+                    address,
+                    memory: memory.clone(),
+                }))),
+            },
+            Deferred::Add(a, b) => a.simplify(fixed)?.add(&b.simplify(fixed)?),
+            Deferred::Mul(a, b) => a.simplify(fixed)?.mul(&b.simplify(fixed)?),
+        }
+    }
+
+    // The symbols to split on to get any further with a simplified value.
+    fn blocking(&self) -> BTreeSet<usize> {
+        let deferred = match self {
+            Value::Linear(_) => return BTreeSet::new(),
+            Value::Deferred(deferred) => deferred,
+        };
+        match &**deferred {
+            Deferred::Read {
+                address: Value::Linear(linear),
+                ..
+            } => linear.terms.keys().cloned().collect(),
+            Deferred::Read { address, .. } => address.blocking(),
+            Deferred::Add(a, b) => a.blocking().union(&b.blocking()).cloned().collect(),
+            Deferred::Mul(Value::Linear(a), Value::Linear(b)) => {
+                // splitting on either side makes the product linear
+                let side = if a.terms.len() <= b.terms.len() { a } else { b };
+                side.terms.keys().cloned().collect()
+            }
+            Deferred::Mul(a, b) => a.blocking().union(&b.blocking()).cloned().collect(),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Relation {
+    Zero,
+    NonZero,
+    Negative,
+    NonNegative,
+}
+
+// A linear expression and how it relates to 0.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Constraint {
+    pub expr: Linear,
+    pub relation: Relation,
+}
+
+impl Constraint {
+    fn possible(&self, (min, max): (i128, i128)) -> bool {
+        match self.relation {
+            Relation::Zero => min <= 0 && 0 <= max,
+            Relation::NonZero => !(min == 0 && max == 0),
+            Relation::Negative => min < 0,
+            Relation::NonNegative => max >= 0,
+        }
+    }
+}
+
+// How many values a domain holds, if that fits in a usize.
+fn domain_size((low, high): (i64, i64)) -> Option<usize> {
+    let size = (high as i128).checked_sub(low as i128)?.checked_add(1)?;
+    usize::try_from(size.max(0)).ok()
+}
+
+// Finds values for every symbol, within its domain, that satisfy all constraints. Each
+// symbol is tried in turn, and a partial assignment is given up on as soon as some
+// constraint can't be satisfied by any values of the remaining symbols.
+pub fn solve(
+    domains: &[(i64, i64)],
+    constraints: &[Constraint],
+) -> Result<Option<Vec<i64>>, SymbolicError> {
+    fn search(
+        symbol: usize,
+        domains: &[(i64, i64)],
+        constraints: &[Constraint],
+        assigned: &mut Vec<Option<i64>>,
+    ) -> Result<bool, SymbolicError> {
+        let feasible = constraints
+            .iter()
+            .all(|constraint| constraint.possible(constraint.expr.bounds(domains, assigned)));
+        if !feasible {
+            return Ok(false);
+        }
+        if symbol == domains.len() {
+            return Ok(true);
+        }
+        let (low, high) = domains[symbol];
+        if !constraints
+            .iter()
+            .any(|constraint| constraint.expr.terms.contains_key(&symbol))
+        {
+            // nothing depends on it
+            assigned[symbol] = Some(low);
+            return search(symbol + 1, domains, constraints, assigned);
+        }
+        if domain_size(domains[symbol]).is_none_or(|size| size > MAX_VALUES) {
+            return Err(SymbolicError::DomainTooLarge { symbol });
+        }
+        for value in low..=high {
+            assigned[symbol] = Some(value);
+            if search(symbol + 1, domains, constraints, assigned)? {
+                return Ok(true);
+            }
+        }
+        assigned[symbol] = None;
+        Ok(false)
+    }
+
+    if domains.iter().any(|&(low, high)| low > high) {
+        return Ok(None);
+    }
+    let mut assigned = vec![None; domains.len()];
+    if search(0, domains, constraints, &mut assigned)? {
+        Ok(Some(assigned.into_iter().map(Option::unwrap).collect()))
+    } else {
+        Ok(None)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SymbolicError {
+    Unsupported { pc: usize, reason: String },
+    TooManyPaths,
+    // a symbol has too many values for the solver to try them all
+    DomainTooLarge { symbol: usize },
+}
+
+impl fmt::Display for SymbolicError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SymbolicError::Unsupported { pc, reason } => {
+                write!(f, "unsupported at pc {}: {}", pc, reason)
+            }
+            SymbolicError::TooManyPaths => write!(f, "too many paths to explore"),
+            SymbolicError::DomainTooLarge { symbol } => {
+                write!(f, "too many values to try for symbol s{}", symbol)
+            }
+        }
+    }
+}
+
+impl Error for SymbolicError {}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Goal {
+    // the program gets to this instruction
+    Pc(usize),
+    // the program outputs this value
+    Output(i64),
+    // the program halts with this value at this address
+    Halt { address: usize, value: i64 },
+}
+
+#[derive(Debug, Clone)]
+struct State {
+    memory: Rc<Vec<Value>>,
+    pc: usize,
+    base: i64,
+    // how many of the inputs were read
+    input: usize,
+    steps: usize,
+    constraints: Vec<Constraint>,
+    // symbols that were split on
+    fixed: BTreeMap<usize, i64>,
+}
+
+impl State {
+    fn read(&self, address: &Value) -> Value {
+        match address {
+            Value::Linear(linear) => match linear.as_constant() {
+                Some(address) if address >= 0 => self
+                    .memory
+                    .get(address as usize)
+                    .cloned()
+                    .unwrap_or_else(|| Value::constant(0)),
+                _ => Value::Deferred(Rc::new(Deferred::Read {
+                    address: address.clone(),
+                    memory: self.memory.clone(),
+                })),
+            },
+            Value::Deferred(_) => Value::Deferred(Rc::new(Deferred::Read {
+                address: address.clone(),
+                memory: self.memory.clone(),
+            })),
+        }
+    }
+
+    fn write(&mut self, address: usize, value: Value) {
+        let memory = Rc::make_mut(&mut self.memory);
+        if address >= memory.len() {
+            memory.resize(address + 1, Value::constant(0));
+        }
+        memory[address] = value;
+    }
+
+    fn assume(&self, expr: Linear, relation: Relation) -> State {
+        let mut state = self.clone();
+        state.constraints.push(Constraint { expr, relation });
+        state
+    }
+}
+
+enum Step {
+    Continue(Vec<State>),
+    Output(State, Value),
+    Halt(State),
+}
+
+// Runs a program on symbolic values, following both ways at every branch that depends on
+// them, to find values that make the program reach a goal.
+//
+// Memory cells and inputs can be made symbols, each with a small range of values. Values
+// are linear expressions over the symbols, and each branch adds a linear constraint to its
+// path. Reads through a symbolic address and products of symbols are only worked out
+// once something depends on them, by trying every value of the symbols involved.
+pub struct Symbolic {
+    program: Vec<i64>,
+    domains: Vec<(i64, i64)>,
+    cells: Vec<(usize, usize)>,
+    inputs: Vec<Value>,
+    pub max_steps: usize,
+    pub max_paths: usize,
+}
+
+impl Symbolic {
+    pub fn new(program: &[i64]) -> Symbolic {
+        Symbolic {
+            program: program.to_vec(),
+            domains: Vec::new(),
+            cells: Vec::new(),
+            inputs: Vec::new(),
+            max_steps: MAX_STEPS,
+            max_paths: MAX_PATHS,
+        }
+    }
+
+    fn symbol(&mut self, range: RangeInclusive<i64>) -> usize {
+        self.domains.push((*range.start(), *range.end()));
+        self.domains.len() - 1
+    }
+
+    // Makes the memory cell at `address` a symbol. Returns the symbol's index.
+    pub fn symbol_at(&mut self, address: usize, range: RangeInclusive<i64>) -> usize {
+        let symbol = self.symbol(range);
+        self.cells.push((address, symbol));
+        symbol
+    }
+
+    // Queues an input value that is a symbol. Returns the symbol's index.
+    pub fn symbolic_input(&mut self, range: RangeInclusive<i64>) -> usize {
+        let symbol = self.symbol(range);
+        self.inputs.push(Value::Linear(Linear::symbol(symbol)));
+        symbol
+    }
+
+    pub fn add_input(&mut self, value: i64) {
+        self.inputs.push(Value::constant(value));
+    }
+
+    // Finds a value for every symbol that makes the program reach `goal`, or None if no
+    // path within the step limit does.
+    pub fn solve(&self, goal: Goal) -> Result<Option<Vec<i64>>, SymbolicError> {
+        let mut memory = self
+            .program
+            .iter()
+            .map(|&word| Value::constant(word))
+            .collect::<Vec<_>>();
+        for &(address, symbol) in &self.cells {
+            if address >= memory.len() {
+                memory.resize(address + 1, Value::constant(0));
+            }
+            memory[address] = Value::Linear(Linear::symbol(symbol));
+        }
+        let mut pending = vec![State {
+            memory: Rc::new(memory),
+            pc: 0,
+            base: 0,
+            input: 0,
+            steps: 0,
+            constraints: Vec::new(),
+            fixed: BTreeMap::new(),
+        }];
+        let mut paths = 0;
+        while let Some(state) = pending.pop() {
+            if state.steps > self.max_steps {
+                continue;
+            }
+            if goal == Goal::Pc(state.pc) {
+                if let Some(solution) = solve(&self.domains, &state.constraints)? {
+                    return Ok(Some(solution));
+                }
+            }
+            let (state, value) = match self.step(state)? {
+                Step::Continue(states) => {
+                    paths += states.len().saturating_sub(1);
+                    if paths > self.max_paths {
+                        return Err(SymbolicError::TooManyPaths);
+                    }
+                    // depth first, taking the first state next
+                    pending.extend(states.into_iter().rev());
+                    continue;
+                }
+                Step::Output(state, value) => match goal {
+                    Goal::Output(target) => (state, (value, target)),
+                    _ => {
+                        pending.push(state);
+                        continue;
+                    }
+                },
+                Step::Halt(state) => match goal {
+                    Goal::Halt { address, value } => {
+                        let cell = state.read(&Value::constant(address as i64));
+                        (state, (cell, value))
+                    }
+                    _ => continue,
+                },
+            };
+            let (value, target) = value;
+            let pc = state.pc;
+            for (reached, linear) in self.resolve(state.clone(), &value, pc)? {
+                let expr = match linear.sub(&Linear::constant(target)) {
+                    Some(expr) => expr,
+                    None => continue,
+                };
+                let reached = reached.assume(expr, Relation::Zero);
+                if let Some(solution) = solve(&self.domains, &reached.constraints)? {
+                    return Ok(Some(solution));
+                }
+            }
+            if let Goal::Output(_) = goal {
+                pending.push(state);
+            }
+        }
+        Ok(None)
+    }
+
+    fn feasible(&self, state: &State) -> Result<bool, SymbolicError> {
+        Ok(solve(&self.domains, &state.constraints)?.is_some())
+    }
+
+    // Every combination of values for `symbols` that the state's constraints allow.
+    fn split(
+        &self,
+        state: &State,
+        symbols: &BTreeSet<usize>,
+        pc: usize,
+    ) -> Result<Vec<State>, SymbolicError> {
+        let count = symbols.iter().try_fold(1usize, |count, &symbol| {
+            count.checked_mul(domain_size(self.domains[symbol])?)
+        });
+        if count.is_none_or(|count| count > MAX_FORKS) {
+            return Err(SymbolicError::Unsupported {
+                pc,
+                reason: format!("too many values to try for symbols {:?}", symbols),
+            });
+        }
+        let mut states = vec![state.clone()];
+        for &symbol in symbols {
+            let (low, high) = self.domains[symbol];
+            let mut next = Vec::new();
+            for state in &states {
+                for value in low..=high {
+                    let expr = Linear::symbol(symbol)
+                        .sub(&Linear::constant(value))
+                        .unwrap();
+                    let mut fork = state.assume(expr, Relation::Zero);
+                    fork.fixed.insert(symbol, value);
+                    if self.feasible(&fork)? {
+                        next.push(fork);
+                    }
+                }
+            }
+            states = next;
+        }
+        Ok(states)
+    }
+
+    // Works out a value as a linear expression, splitting the state on whatever symbols
+    // that takes.
+    fn resolve(
+        &self,
+        state: State,
+        value: &Value,
+        pc: usize,
+    ) -> Result<Vec<(State, Linear)>, SymbolicError> {
+        let mut resolved = Vec::new();
+        let mut pending = vec![state];
+        while let Some(state) = pending.pop() {
+            match value.simplify(&state.fixed) {
+                None => {}
+                Some(Value::Linear(linear)) => resolved.push((state, linear)),
+                Some(deferred) => pending.extend(self.split(&state, &deferred.blocking(), pc)?),
+            }
+        }
+        Ok(resolved)
+    }
+
+    // Works out a value as a number, splitting the state on every symbol it depends on.
+    fn concrete(
+        &self,
+        state: State,
+        value: &Value,
+        pc: usize,
+    ) -> Result<Vec<(State, i64)>, SymbolicError> {
+        let mut concrete = Vec::new();
+        for (state, linear) in self.resolve(state, value, pc)? {
+            match linear.as_constant() {
+                Some(value) => concrete.push((state, value)),
+                None => {
+                    let symbols = linear.terms.keys().cloned().collect();
+                    for state in self.split(&state, &symbols, pc)? {
+                        if let Some(value) = linear.substitute(&state.fixed) {
+                            concrete.push((state, value.as_constant().unwrap()));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(concrete)
+    }
+
+    fn decode(&self, state: &State) -> Result<Option<Instruction>, SymbolicError> {
+        let pc = state.pc;
+        let word = match state.read(&Value::constant(pc as i64)) {
+            Value::Linear(linear) => linear.as_constant(),
+            Value::Deferred(_) => None,
+        };
+        let word = match word {
+            Some(word) => word,
+            None => {
+                return Err(SymbolicError::Unsupported {
+                    pc,
+                    reason: "symbolic instruction".to_string(),
+                })
+            }
+        };
+        // operands are read separately, since they may be symbolic
+        let mut words = vec![word];
+        words.extend((1..4).map(|index| index as i64));
+        Ok(Instruction::try_decode(0, |address| words.get(address).cloned().unwrap_or(0)).ok())
+    }
+
+    // The value of a parameter, given the symbolic word it is encoded in.
+    fn input(&self, state: &State, index: usize, value: &InputValue) -> Value {
+        let word = state.read(&Value::constant((state.pc + index) as i64));
+        match value {
+            InputValue::Immediate(_) => word,
+            InputValue::Position(_) => state.read(&word),
+            InputValue::Relative(_) => state.read(
+                &word
+                    .add(&Value::constant(state.base))
+                    .unwrap_or_else(|| Value::constant(-1)),
+            ),
+        }
+    }
+
+    fn output(&self, state: &State, index: usize, result: &OutputValue) -> Value {
+        let word = state.read(&Value::constant((state.pc + index) as i64));
+        match result {
+            OutputValue::Position(_) => word,
+            OutputValue::Relative(_) => word
+                .add(&Value::constant(state.base))
+                .unwrap_or_else(|| Value::constant(-1)),
+        }
+    }
+
+    // Stores the value in every state the result address splits into.
+    fn store(
+        &self,
+        state: State,
+        index: usize,
+        result: &OutputValue,
+        value: Value,
+        next: usize,
+    ) -> Result<Vec<State>, SymbolicError> {
+        let pc = state.pc;
+        let address = self.output(&state, index, result);
+        let mut states = Vec::new();
+        for (mut state, address) in self.concrete(state, &address, pc)? {
+            if address >= 0 {
+                state.write(address as usize, value.clone());
+                state.pc = next;
+                states.push(state);
+            }
+        }
+        Ok(states)
+    }
+
+    fn step(&self, mut state: State) -> Result<Step, SymbolicError> {
+        state.steps += 1;
+        let pc = state.pc;
+        let instruction = match self.decode(&state)? {
+            Some(instruction) => instruction,
+            // the path ends in an invalid instruction
+            None => return Ok(Step::Continue(vec![])),
+        };
+        let next = pc + instruction.length();
+        let states = match &instruction {
+            Instruction::Add(left, right, result) | Instruction::Multiply(left, right, result) => {
+                let left = self.input(&state, 1, left);
+                let right = self.input(&state, 2, right);
+                let value = match instruction {
+                    Instruction::Add(_, _, _) => left.add(&right),
+                    _ => left.mul(&right),
+                };
+                match value {
+                    Some(value) => self.store(state, 3, result, value, next)?,
+                    None => vec![],
+                }
+            }
+            Instruction::LessThan(left, right, result)
+            | Instruction::Equals(left, right, result) => {
+                let left = self.input(&state, 1, left);
+                let right = self.input(&state, 2, right);
+                let difference = left.add(&right.mul(&Value::constant(-1)).unwrap());
+                let difference = match difference {
+                    Some(difference) => difference,
+                    None => return Ok(Step::Continue(vec![])),
+                };
+                let (is, is_not) = match instruction {
+                    Instruction::LessThan(_, _, _) => (Relation::Negative, Relation::NonNegative),
+                    _ => (Relation::Zero, Relation::NonZero),
+                };
+                let mut states = Vec::new();
+                for (state, difference) in self.resolve(state, &difference, pc)? {
+                    for &(relation, value) in &[(is, 1), (is_not, 0)] {
+                        let fork = state.assume(difference.clone(), relation);
+                        if self.feasible(&fork)? {
+                            states.extend(self.store(
+                                fork,
+                                3,
+                                result,
+                                Value::constant(value),
+                                next,
+                            )?);
+                        }
+                    }
+                }
+                states
+            }
+            Instruction::Read(result) => match self.inputs.get(state.input) {
+                Some(value) => {
+                    state.input += 1;
+                    self.store(state, 1, result, value.clone(), next)?
+                }
+                // out of input, so the path ends here
+                None => vec![],
+            },
+            Instruction::Write(value) => {
+                let value = self.input(&state, 1, value);
+                state.pc = next;
+                return Ok(Step::Output(state, value));
+            }
+            Instruction::JumpIfTrue(test, target) | Instruction::JumpIfFalse(test, target) => {
+                let test = self.input(&state, 1, test);
+                let target = self.input(&state, 2, target);
+                let (jump, fall) = match instruction {
+                    Instruction::JumpIfTrue(_, _) => (Relation::NonZero, Relation::Zero),
+                    _ => (Relation::Zero, Relation::NonZero),
+                };
+                let mut states = Vec::new();
+                for (state, test) in self.resolve(state, &test, pc)? {
+                    let mut fork = state.assume(test.clone(), fall);
+                    if self.feasible(&fork)? {
+                        fork.pc = next;
+                        states.push(fork);
+                    }
+                    let fork = state.assume(test, jump);
+                    if self.feasible(&fork)? {
+                        for (mut state, target) in self.concrete(fork, &target, pc)? {
+                            if target >= 0 {
+                                state.pc = target as usize;
+                                states.push(state);
+                            }
+                        }
+                    }
+                }
+                states
+            }
+            Instruction::RelativeBaseOffset(offset) => {
+                let offset = self.input(&state, 1, offset);
+                let mut states = Vec::new();
+                for (mut state, offset) in self.concrete(state, &offset, pc)? {
+                    if let Some(base) = state.base.checked_add(offset) {
+                        state.base = base;
+                        state.pc = next;
+                        states.push(state);
+                    }
+                }
+                states
+            }
+            Instruction::Halt => return Ok(Step::Halt(state)),
+        };
+        Ok(Step::Continue(states))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::parse_list;
+    use crate::intcode::asm::assemble;
+    use crate::intcode::{Machine, ProgramMachine};
+
+    #[test]
+    fn test_solve() {
+        // 3a + 2b == 12, a < b
+        let a = Linear::symbol(0).scale(3).unwrap();
+        let b = Linear::symbol(1).scale(2).unwrap();
+        let constraints = vec![
+            Constraint {
+                expr: a.add(&b).unwrap().sub(&Linear::constant(12)).unwrap(),
+                relation: Relation::Zero,
+            },
+            Constraint {
+                expr: Linear::symbol(0).sub(&Linear::symbol(1)).unwrap(),
+                relation: Relation::Negative,
+            },
+        ];
+        assert_eq!(
+            solve(&[(0, 10), (0, 10)], &constraints),
+            Ok(Some(vec![0, 6]))
+        );
+        assert_eq!(solve(&[(1, 10), (1, 2)], &constraints), Ok(None));
+        // unconstrained symbols can have any range
+        assert_eq!(
+            solve(&[(0, 10), (0, 10), (i64::MIN, i64::MAX)], &constraints),
+            Ok(Some(vec![0, 6, i64::MIN]))
+        );
+        assert_eq!(
+            solve(&[(0, 10), (i64::MIN, i64::MAX)], &constraints),
+            Err(SymbolicError::DomainTooLarge { symbol: 1 })
+        );
+        assert_eq!(a.add(&b).unwrap().to_string(), "3*s0 + 2*s1");
+    }
+
+    #[test]
+    fn test_branches() {
+        // prints 1 only if the input is 42
+        let program = assemble(
+            "
+                in [value]
+                eq [value], #42, [test]
+                jt [test], #win
+                out #0
+                hlt
+            win:
+                out #1
+                hlt
+            value: db 0
+            test: db 0
+            ",
+        )
+        .unwrap();
+        let mut symbolic = Symbolic::new(&program);
+        let input = symbolic.symbolic_input(0..=99);
+        let solution = symbolic.solve(Goal::Output(1)).unwrap().unwrap();
+        assert_eq!(solution[input], 42);
+        let solution = symbolic.solve(Goal::Pc(12)).unwrap().unwrap();
+        assert_eq!(solution[input], 42);
+        assert_eq!(symbolic.solve(Goal::Output(2)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_products_and_indirect_reads() {
+        // outputs whether table[a] * b is 36, patching the address of its read of the table
+        let program = assemble(
+            "
+                in [a]
+                in [b]
+                add [a], #table, [patch+1]
+            patch:
+                mul [0], [b], [product]
+                eq [product], #36, [product]
+                out [product]
+                hlt
+            a: db 0
+            b: db 0
+            product: db 0
+            table: db 5, 7, 4, 11
+            ",
+        )
+        .unwrap();
+        let mut symbolic = Symbolic::new(&program);
+        let a = symbolic.symbolic_input(0..=3);
+        let b = symbolic.symbolic_input(0..=20);
+        let solution = symbolic.solve(Goal::Output(1)).unwrap().unwrap();
+        assert_eq!((solution[a], solution[b]), (2, 9));
+        let output = ProgramMachine::new(program, vec![2, 9]).run();
+        assert_eq!(output, vec![1]);
+    }
+
+    #[test]
+    fn test_wide_domains() {
+        // reads through an input address, so the input has to be split
+        let program = assemble("in [ptr+1]\nptr: out [0]\nhlt").unwrap();
+        let mut symbolic = Symbolic::new(&program);
+        symbolic.symbolic_input(i64::MIN..=i64::MAX);
+        assert!(matches!(
+            symbolic.solve(Goal::Output(1)),
+            Err(SymbolicError::Unsupported { .. })
+        ));
+
+        // compares the input, so the solver has to search its domain
+        let program =
+            assemble("in [value]\neq [value], #5, [value]\nout [value]\nhlt\nvalue: db 0").unwrap();
+        let mut symbolic = Symbolic::new(&program);
+        symbolic.symbolic_input(i64::MIN..=i64::MAX);
+        assert_eq!(
+            symbolic.solve(Goal::Output(1)),
+            Err(SymbolicError::DomainTooLarge { symbol: 0 })
+        );
+    }
+
+    #[test]
+    fn test_day2() {
+        let program: Vec<i64> = parse_list(include_str!("../bin/day2/input"), ',');
+        let mut symbolic = Symbolic::new(&program);
+        let noun = symbolic.symbol_at(1, 0..=99);
+        let verb = symbolic.symbol_at(2, 0..=99);
+        let goal = Goal::Halt {
+            address: 0,
+            value: 19690720,
+        };
+        let solution = symbolic.solve(goal).unwrap().unwrap();
+        let mut program = program;
+        program[1] = solution[noun];
+        program[2] = solution[verb];
+        let mut machine = ProgramMachine::new(program, vec![]);
+        machine.run();
+        assert_eq!(machine.program()[0], 19690720);
+    }
+}
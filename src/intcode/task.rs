@@ -0,0 +1,359 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+
+use crate::intcode::{IntcodeError, Machine, StepResult};
+
+// a machine that computes without doing any I/O still lets other tasks run now and then
+const YIELD_INTERVAL: u64 = 1000;
+
+struct Shared<T> {
+    queue: VecDeque<T>,
+    waker: Option<Waker>,
+    senders: usize,
+}
+
+// The sending half of an unbounded channel between tasks on the same executor.
+pub struct AsyncSender<T> {
+    shared: Rc<RefCell<Shared<T>>>,
+}
+
+pub struct AsyncReceiver<T> {
+    shared: Rc<RefCell<Shared<T>>>,
+}
+
+pub fn channel<T>() -> (AsyncSender<T>, AsyncReceiver<T>) {
+    let shared = Rc::new(RefCell::new(Shared {
+        queue: VecDeque::new(),
+        waker: None,
+        senders: 1,
+    }));
+    (
+        AsyncSender {
+            shared: shared.clone(),
+        },
+        AsyncReceiver { shared },
+    )
+}
+
+impl<T> AsyncSender<T> {
+    pub fn send(&self, value: T) {
+        let mut shared = self.shared.borrow_mut();
+        shared.queue.push_back(value);
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Clone for AsyncSender<T> {
+    fn clone(&self) -> AsyncSender<T> {
+        self.shared.borrow_mut().senders += 1;
+        AsyncSender {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for AsyncSender<T> {
+    fn drop(&mut self) {
+        let mut shared = self.shared.borrow_mut();
+        shared.senders -= 1;
+        if shared.senders == 0 {
+            if let Some(waker) = shared.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl<T> AsyncReceiver<T> {
+    pub fn try_recv(&self) -> Option<T> {
+        self.shared.borrow_mut().queue.pop_front()
+    }
+
+    // Waits for the next value, or returns None once every sender is gone.
+    pub fn recv(&self) -> Recv<'_, T> {
+        Recv { receiver: self }
+    }
+
+    pub fn len(&self) -> usize {
+        self.shared.borrow().queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+pub struct Recv<'a, T> {
+    receiver: &'a AsyncReceiver<T>,
+}
+
+impl<T> Future for Recv<'_, T> {
+    type Output = Option<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<T>> {
+        let mut shared = self.receiver.shared.borrow_mut();
+        match shared.queue.pop_front() {
+            Some(value) => Poll::Ready(Some(value)),
+            None if shared.senders == 0 => Poll::Ready(None),
+            None => {
+                shared.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+struct YieldNow {
+    yielded: bool,
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        if self.yielded {
+            return Poll::Ready(());
+        }
+        self.yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+pub fn yield_now() -> impl Future<Output = ()> {
+    YieldNow { yielded: false }
+}
+
+// Runs a machine as an async task, suspending whenever it needs input that hasn't arrived yet.
+// Day 23 now runs on `packet::PacketNetwork`, which only models fixed-size packets sent to
+// numbered addresses. This stays for machines wired together some other way, like the
+// pipeline in the tests, which would otherwise need a hand-written scheduler.
+pub struct AsyncMachine<M: Machine> {
+    machine: M,
+    input: AsyncReceiver<i64>,
+    idle_input: Option<i64>,
+}
+
+impl<M: Machine> AsyncMachine<M> {
+    pub fn new(machine: M, input: AsyncReceiver<i64>) -> AsyncMachine<M> {
+        AsyncMachine {
+            machine,
+            input,
+            idle_input: None,
+        }
+    }
+
+    // For programs that poll for input: when nothing has arrived, the machine reads this value
+    // once before it suspends.
+    pub fn set_idle_input(&mut self, value: Option<i64>) {
+        self.idle_input = value;
+    }
+
+    pub fn machine(&self) -> &M {
+        &self.machine
+    }
+
+    pub fn machine_mut(&mut self) -> &mut M {
+        &mut self.machine
+    }
+
+    pub fn into_inner(self) -> M {
+        self.machine
+    }
+
+    pub async fn try_run_to_output(&mut self) -> Result<Option<i64>, IntcodeError> {
+        let mut idle = false;
+        let mut steps = 0u64;
+        loop {
+            match self.machine.try_step()? {
+                StepResult::Ok => {
+                    steps += 1;
+                    if steps.is_multiple_of(YIELD_INTERVAL) {
+                        yield_now().await;
+                    }
+                }
+                StepResult::NeedInput => {
+                    let value = match (self.input.try_recv(), self.idle_input) {
+                        (Some(value), _) => value,
+                        (None, Some(value)) if !idle => {
+                            idle = true;
+                            self.machine.add_input(value);
+                            continue;
+                        }
+                        (None, _) => match self.input.recv().await {
+                            Some(value) => value,
+                            None => return Err(IntcodeError::MissingInput),
+                        },
+                    };
+                    idle = false;
+                    self.machine.add_input(value);
+                }
+                StepResult::Output(value) => return Ok(Some(value)),
+                StepResult::Halt => return Ok(None),
+            }
+        }
+    }
+
+    pub async fn try_run(&mut self, output: &AsyncSender<i64>) -> Result<(), IntcodeError> {
+        while let Some(value) = self.try_run_to_output().await? {
+            output.send(value);
+        }
+        Ok(())
+    }
+}
+
+struct TaskWaker {
+    id: usize,
+    ready: Arc<Mutex<VecDeque<usize>>>,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.ready.lock().unwrap().push_back(self.id);
+    }
+}
+
+type Task<'a> = Pin<Box<dyn Future<Output = ()> + 'a>>;
+
+// A single-threaded executor that polls its tasks in the order they are woken.
+#[derive(Default)]
+pub struct Executor<'a> {
+    tasks: Vec<Option<Task<'a>>>,
+    ready: Arc<Mutex<VecDeque<usize>>>,
+}
+
+impl<'a> Executor<'a> {
+    pub fn new() -> Executor<'a> {
+        Executor::default()
+    }
+
+    pub fn spawn(&mut self, task: impl Future<Output = ()> + 'a) {
+        self.ready.lock().unwrap().push_back(self.tasks.len());
+        self.tasks.push(Some(Box::pin(task)));
+    }
+
+    // Polls tasks until none of them can make progress, and returns how many are unfinished.
+    // If that isn't zero, they are all waiting for something that only the caller can send.
+    pub fn run_until_stalled(&mut self) -> usize {
+        loop {
+            let id = match self.ready.lock().unwrap().pop_front() {
+                Some(id) => id,
+                None => break,
+            };
+            if let Some(task) = &mut self.tasks[id] {
+                let waker = Waker::from(Arc::new(TaskWaker {
+                    id,
+                    ready: self.ready.clone(),
+                }));
+                if task
+                    .as_mut()
+                    .poll(&mut Context::from_waker(&waker))
+                    .is_ready()
+                {
+                    self.tasks[id] = None;
+                }
+            }
+        }
+        self.pending()
+    }
+
+    pub fn pending(&self) -> usize {
+        self.tasks.iter().filter(|task| task.is_some()).count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::ProgramMachine;
+
+    // doubles every input until it reads a zero
+    fn doubler(input: AsyncReceiver<i64>) -> AsyncMachine<ProgramMachine> {
+        let program = vec![
+            3, 15, 1006, 15, 14, 1002, 15, 2, 15, 4, 15, 1105, 1, 0, 99, 0,
+        ];
+        AsyncMachine::new(ProgramMachine::new(program, vec![]), input)
+    }
+
+    #[test]
+    fn test_pipeline() {
+        let (input, first_input) = channel();
+        let (first_output, second_input) = channel();
+        let (second_output, output) = channel();
+        let mut first = doubler(first_input);
+        let mut second = doubler(second_input);
+        let result = Rc::new(RefCell::new(None));
+        let slot = result.clone();
+        let mut executor = Executor::new();
+        executor.spawn(async move { first.try_run(&first_output).await.unwrap() });
+        executor.spawn(async move {
+            *slot.borrow_mut() = Some(second.try_run(&second_output).await);
+        });
+
+        input.send(1);
+        input.send(5);
+        assert_eq!(executor.run_until_stalled(), 2);
+        assert_eq!(output.try_recv(), Some(4));
+        assert_eq!(output.try_recv(), Some(20));
+        assert_eq!(output.try_recv(), None);
+
+        // the first machine halts, which closes the second one's input
+        input.send(0);
+        assert_eq!(executor.run_until_stalled(), 0);
+        assert_eq!(*result.borrow(), Some(Err(IntcodeError::MissingInput)));
+    }
+
+    #[test]
+    fn test_idle_input() {
+        // counts how many times it reads -1 before a real value arrives, then outputs the count
+        let program = vec![
+            3, 20, 1008, 20, -1, 21, 1006, 21, 16, 1001, 22, 1, 22, 1105, 1, 0, 4, 22, 99, 0, 0, 0,
+            0,
+        ];
+        let (input, receiver) = channel();
+        let mut machine = AsyncMachine::new(ProgramMachine::new(program, vec![]), receiver);
+        machine.set_idle_input(Some(-1));
+        let (output, results) = channel();
+        let mut executor = Executor::new();
+        executor.spawn(async move { machine.try_run(&output).await.unwrap() });
+        assert_eq!(executor.run_until_stalled(), 1);
+        input.send(7);
+        assert_eq!(executor.run_until_stalled(), 0);
+        assert_eq!(results.try_recv(), Some(1));
+    }
+
+    #[test]
+    fn test_yield() {
+        // a machine that never does I/O doesn't starve the others
+        let (_sender, receiver) = channel();
+        let mut spinner =
+            AsyncMachine::new(ProgramMachine::new(vec![1105, 1, 0], vec![]), receiver);
+        let done = Rc::new(RefCell::new(false));
+        let flag = done.clone();
+        let mut executor = Executor::new();
+        executor.spawn(async move {
+            spinner.try_run_to_output().await.unwrap();
+        });
+        executor.spawn(async move {
+            *flag.borrow_mut() = true;
+        });
+        // run by hand, since the spinner never stalls
+        for _ in 0..2 {
+            let id = executor.ready.lock().unwrap().pop_front().unwrap();
+            let waker = Waker::from(Arc::new(TaskWaker {
+                id,
+                ready: executor.ready.clone(),
+            }));
+            let task = executor.tasks[id].as_mut().unwrap();
+            let _ = task.as_mut().poll(&mut Context::from_waker(&waker));
+        }
+        assert!(*done.borrow());
+    }
+}
@@ -0,0 +1,313 @@
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TryRecvError, TrySendError};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+
+use crate::intcode::{IntcodeError, Machine, StepResult};
+
+// how often a busy machine checks whether the runtime is shutting down
+const STOP_CHECK_INTERVAL: u64 = 4096;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum RuntimeError {
+    Machine { id: usize, error: IntcodeError },
+    Deadlock { waiting: Vec<usize> },
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuntimeError::Machine { id, error } => write!(f, "machine {}: {}", id, error),
+            RuntimeError::Deadlock { waiting } => {
+                let ids = waiting.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+                write!(f, "deadlock: machines {} are all blocked", ids.join(", "))
+            }
+        }
+    }
+}
+
+impl Error for RuntimeError {}
+
+// Runs every machine on its own thread. A machine's outputs go to the input of the machine it
+// is connected to, through a bounded channel.
+pub struct Runtime<M: Machine + Send> {
+    machines: Vec<M>,
+    links: Vec<Option<usize>>,
+    capacity: usize,
+}
+
+impl<M: Machine + Send> Runtime<M> {
+    pub fn new(capacity: usize) -> Runtime<M> {
+        Runtime {
+            machines: Vec::new(),
+            links: Vec::new(),
+            // a rendezvous channel never accepts a non-blocking send
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn add(&mut self, machine: M) -> usize {
+        self.machines.push(machine);
+        self.links.push(None);
+        self.machines.len() - 1
+    }
+
+    pub fn machine_mut(&mut self, id: usize) -> &mut M {
+        &mut self.machines[id]
+    }
+
+    pub fn connect(&mut self, from: usize, to: usize) {
+        assert!(to < self.machines.len(), "unknown machine {}", to);
+        self.links[from] = Some(to);
+    }
+
+    // Runs until every machine has halted, and returns what each of them output.
+    // A machine whose input is not connected fails when it runs out of input.
+    pub fn run(self) -> Result<Vec<Vec<i64>>, RuntimeError> {
+        let (senders, receivers): (Vec<_>, Vec<_>) = (0..self.machines.len())
+            .map(|_| sync_channel(self.capacity))
+            .unzip();
+        let shared = Shared {
+            state: Mutex::new(State {
+                alive: self.machines.len(),
+                waiting: BTreeSet::new(),
+                epoch: 0,
+                error: None,
+            }),
+            changed: Condvar::new(),
+            stop: AtomicBool::new(false),
+        };
+        let outputs = thread::scope(|scope| {
+            let handles = self
+                .machines
+                .into_iter()
+                .zip(receivers)
+                .zip(&self.links)
+                .enumerate()
+                .map(|(id, ((machine, input), link))| {
+                    let output = link.map(|to| senders[to].clone());
+                    let shared = &shared;
+                    scope.spawn(move || run_machine(id, machine, input, output, shared))
+                })
+                .collect::<Vec<_>>();
+            // only the machines hold senders now, so inputs close when their writers halt
+            drop(senders);
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        match shared.state.into_inner().unwrap().error {
+            Some(error) => Err(error),
+            None => Ok(outputs),
+        }
+    }
+}
+
+struct State {
+    alive: usize,
+    // machines that have been waiting since the last time any machine made progress
+    waiting: BTreeSet<usize>,
+    epoch: u64,
+    error: Option<RuntimeError>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    changed: Condvar,
+    stop: AtomicBool,
+}
+
+impl Shared {
+    fn epoch(&self) -> u64 {
+        self.state.lock().unwrap().epoch
+    }
+
+    fn progress(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.epoch += 1;
+        state.waiting.clear();
+        self.changed.notify_all();
+    }
+
+    // Blocks until some machine makes progress after `seen`. Returns false if the runtime is
+    // shutting down instead, which is also what happens when every live machine is waiting.
+    fn wait(&self, id: usize, seen: u64) -> bool {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if state.error.is_some() {
+                return false;
+            }
+            if state.epoch != seen {
+                state.waiting.remove(&id);
+                return true;
+            }
+            state.waiting.insert(id);
+            if state.waiting.len() == state.alive {
+                let waiting = state.waiting.iter().cloned().collect();
+                self.stop(&mut state, RuntimeError::Deadlock { waiting });
+                return false;
+            }
+            state = self.changed.wait(state).unwrap();
+        }
+    }
+
+    fn fail(&self, error: RuntimeError) {
+        let mut state = self.state.lock().unwrap();
+        self.stop(&mut state, error);
+    }
+
+    fn stop(&self, state: &mut State, error: RuntimeError) {
+        if state.error.is_none() {
+            state.error = Some(error);
+        }
+        self.stop.store(true, Ordering::Relaxed);
+        self.changed.notify_all();
+    }
+
+    fn finish(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.alive -= 1;
+        state.epoch += 1;
+        state.waiting.clear();
+        self.changed.notify_all();
+    }
+}
+
+fn run_machine<M: Machine>(
+    id: usize,
+    mut machine: M,
+    input: Receiver<i64>,
+    output: Option<SyncSender<i64>>,
+    shared: &Shared,
+) -> Vec<i64> {
+    let mut outputs = Vec::new();
+    let mut steps = 0u64;
+    'run: loop {
+        steps += 1;
+        if steps.is_multiple_of(STOP_CHECK_INTERVAL) && shared.stop.load(Ordering::Relaxed) {
+            break;
+        }
+        match machine.try_step() {
+            Ok(StepResult::Ok) => {}
+            Ok(StepResult::NeedInput) => loop {
+                let seen = shared.epoch();
+                match input.try_recv() {
+                    Ok(value) => {
+                        machine.add_input(value);
+                        shared.progress();
+                        break;
+                    }
+                    Err(TryRecvError::Empty) => {
+                        if !shared.wait(id, seen) {
+                            break 'run;
+                        }
+                    }
+                    Err(TryRecvError::Disconnected) => {
+                        let error = IntcodeError::MissingInput;
+                        shared.fail(RuntimeError::Machine { id, error });
+                        break 'run;
+                    }
+                }
+            },
+            Ok(StepResult::Output(value)) => {
+                outputs.push(value);
+                if let Some(sender) = &output {
+                    let mut value = value;
+                    loop {
+                        let seen = shared.epoch();
+                        match sender.try_send(value) {
+                            Ok(()) => {
+                                shared.progress();
+                                break;
+                            }
+                            Err(TrySendError::Full(unsent)) => {
+                                value = unsent;
+                                if !shared.wait(id, seen) {
+                                    break 'run;
+                                }
+                            }
+                            // the receiving machine has halted, so nobody needs the value
+                            Err(TrySendError::Disconnected(_)) => break,
+                        }
+                    }
+                }
+            }
+            Ok(StepResult::Halt) => break,
+            Err(error) => {
+                shared.fail(RuntimeError::Machine { id, error });
+                break;
+            }
+        }
+    }
+    // close the channel before waking up the machine that reads from it
+    drop(output);
+    shared.finish();
+    outputs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode::ProgramMachine;
+
+    // adds one to every input until it reads a zero
+    fn incrementer(input: Vec<i64>) -> ProgramMachine {
+        let program = vec![
+            3, 15, 1006, 15, 14, 1001, 15, 1, 15, 4, 15, 1105, 1, 0, 99, 0,
+        ];
+        ProgramMachine::new(program, input)
+    }
+
+    #[test]
+    fn test_pipeline() {
+        let mut runtime = Runtime::new(1);
+        let first = runtime.add(incrementer(vec![1, 2, 3, 0]));
+        let second = runtime.add(incrementer(vec![]));
+        runtime.connect(first, second);
+        // the first machine halts without passing on the zero, so the second one runs dry
+        assert_eq!(
+            runtime.run(),
+            Err(RuntimeError::Machine {
+                id: 1,
+                error: IntcodeError::MissingInput
+            })
+        );
+    }
+
+    #[test]
+    fn test_ring() {
+        // passes its input on minus one, and halts after passing on a zero
+        let program = vec![
+            3, 15, 1006, 15, 9, 1001, 15, -1, 15, 4, 15, 1005, 15, 0, 99, 0,
+        ];
+        let mut runtime = Runtime::new(2);
+        let ids = [vec![7], vec![], vec![]]
+            .iter()
+            .map(|input| runtime.add(ProgramMachine::new(program.clone(), input.clone())))
+            .collect::<Vec<_>>();
+        for i in 0..3 {
+            runtime.connect(ids[i], ids[(i + 1) % 3]);
+        }
+        let outputs = runtime.run().unwrap();
+        assert_eq!(outputs, vec![vec![6, 3, 0], vec![5, 2, 0], vec![4, 1, 0]]);
+    }
+
+    #[test]
+    fn test_deadlock() {
+        let mut runtime = Runtime::new(4);
+        let first = runtime.add(incrementer(vec![]));
+        let second = runtime.add(incrementer(vec![]));
+        runtime.connect(first, second);
+        runtime.connect(second, first);
+        assert_eq!(
+            runtime.run(),
+            Err(RuntimeError::Deadlock {
+                waiting: vec![0, 1]
+            })
+        );
+    }
+}
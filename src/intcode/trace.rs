@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
+
+use crate::intcode::{InputValue, Instruction, IntcodeError, Machine, ProgramMachine, StepResult};
+
+const HOTSPOTS: usize = 10;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Operand {
+    pub address: Option<usize>,
+    pub value: i64,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct MemoryWrite {
+    pub address: usize,
+    pub old: i64,
+    pub new: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct StepTrace {
+    pub pc: usize,
+    pub base: i64,
+    pub instruction: Instruction,
+    pub operands: Vec<Operand>,
+    pub write: Option<MemoryWrite>,
+    pub result: StepResult,
+    pub next_pc: usize,
+}
+
+// Executes a single instruction, recording what it read and wrote.
+pub fn trace_step(machine: &mut ProgramMachine) -> Result<StepTrace, IntcodeError> {
+    let pc = machine.pc();
+    let base = machine.base();
+    let instruction = machine.instruction()?;
+    let operands = instruction
+        .inputs()
+        .iter()
+        .map(|value| match (*value, value.address(base)) {
+            (InputValue::Immediate(value), _) => Operand {
+                address: None,
+                value,
+            },
+            (_, Some(address)) if address >= 0 => Operand {
+                address: Some(address as usize),
+                value: machine.peek(address as usize),
+            },
+            // negative and overflowing addresses make the step below fail
+            _ => Operand {
+                address: None,
+                value: 0,
+            },
+        })
+        .collect();
+    let target = instruction
+        .output()
+        .and_then(|result| result.address(base))
+        .filter(|&address| address >= 0)
+        .map(|address| (address as usize, machine.peek(address as usize)));
+    let result = machine.try_step()?;
+    let write = match (target, result) {
+        (Some(_), StepResult::NeedInput) | (None, _) => None,
+        (Some((address, old)), _) => Some(MemoryWrite {
+            address,
+            old,
+            new: machine.peek(address),
+        }),
+    };
+    Ok(StepTrace {
+        pc,
+        base,
+        instruction,
+        operands,
+        write,
+        result,
+        next_pc: machine.pc(),
+    })
+}
+
+impl fmt::Display for StepTrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:>6}: {:<36} rb={}",
+            self.pc,
+            self.instruction.to_string(),
+            self.base
+        )?;
+        for operand in &self.operands {
+            match operand.address {
+                Some(address) => write!(f, " [{}]={}", address, operand.value)?,
+                None => write!(f, " #{}", operand.value)?,
+            }
+        }
+        if let Some(write) = self.write {
+            write!(f, " ; [{}] {} -> {}", write.address, write.old, write.new)?;
+        }
+        match self.result {
+            StepResult::Output(value) => write!(f, " ; out {}", value)?,
+            StepResult::NeedInput => write!(f, " ; need input")?,
+            StepResult::Halt => write!(f, " ; halt")?,
+            StepResult::Ok => {}
+        }
+        if self.next_pc != self.pc + self.instruction.length() {
+            write!(f, " ; -> {}", self.next_pc)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    pub steps: u64,
+    pub pcs: HashMap<usize, (u64, Instruction)>,
+    pub opcodes: HashMap<&'static str, u64>,
+    pub reads: HashMap<usize, u64>,
+    pub writes: HashMap<usize, u64>,
+}
+
+impl Profile {
+    pub fn new() -> Profile {
+        Profile::default()
+    }
+
+    pub fn record(&mut self, trace: &StepTrace) {
+        if let StepResult::NeedInput = trace.result {
+            // the instruction did not execute yet
+            return;
+        }
+        self.steps += 1;
+        self.pcs.entry(trace.pc).or_insert((0, trace.instruction)).0 += 1;
+        *self
+            .opcodes
+            .entry(trace.instruction.mnemonic())
+            .or_insert(0) += 1;
+        for address in trace.operands.iter().filter_map(|operand| operand.address) {
+            *self.reads.entry(address).or_insert(0) += 1;
+        }
+        if let Some(write) = trace.write {
+            *self.writes.entry(write.address).or_insert(0) += 1;
+        }
+    }
+
+    fn percentage(&self, count: u64) -> f64 {
+        100.0 * count as f64 / self.steps.max(1) as f64
+    }
+}
+
+fn top<K: Copy + Ord>(counts: impl Iterator<Item = (K, u64)>) -> Vec<(K, u64)> {
+    let mut counts = counts.collect::<Vec<_>>();
+    counts.sort_by(|(left_key, left), (right_key, right)| {
+        right.cmp(left).then(left_key.cmp(right_key))
+    });
+    counts.truncate(HOTSPOTS);
+    counts
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} instructions executed", self.steps)?;
+        writeln!(f, "hottest instructions:")?;
+        for (pc, count) in top(self.pcs.iter().map(|(&pc, &(count, _))| (pc, count))) {
+            let instruction = self.pcs[&pc].1;
+            writeln!(
+                f,
+                "  {:>6}: {:>10} {:>5.1}%  {}",
+                pc,
+                count,
+                self.percentage(count),
+                instruction
+            )?;
+        }
+        writeln!(f, "instructions by opcode:")?;
+        for (mnemonic, count) in top(self.opcodes.iter().map(|(&key, &count)| (key, count))) {
+            writeln!(
+                f,
+                "  {:>6}: {:>10} {:>5.1}%",
+                mnemonic,
+                count,
+                self.percentage(count)
+            )?;
+        }
+        writeln!(f, "most read cells:")?;
+        for (address, count) in top(self.reads.iter().map(|(&key, &count)| (key, count))) {
+            writeln!(f, "  {:>6}: {:>10}", address, count)?;
+        }
+        writeln!(f, "most written cells:")?;
+        for (address, count) in top(self.writes.iter().map(|(&key, &count)| (key, count))) {
+            writeln!(f, "  {:>6}: {:>10}", address, count)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct TracingMachine<W: Write> {
+    machine: ProgramMachine,
+    trace: Option<W>,
+    profile: Profile,
+}
+
+impl<W: Write> TracingMachine<W> {
+    pub fn new(machine: ProgramMachine) -> TracingMachine<W> {
+        TracingMachine {
+            machine,
+            trace: None,
+            profile: Profile::new(),
+        }
+    }
+
+    pub fn with_trace(machine: ProgramMachine, trace: W) -> TracingMachine<W> {
+        TracingMachine {
+            machine,
+            trace: Some(trace),
+            profile: Profile::new(),
+        }
+    }
+
+    pub fn set_trace(&mut self, trace: Option<W>) {
+        self.trace = trace;
+    }
+
+    pub fn machine(&self) -> &ProgramMachine {
+        &self.machine
+    }
+
+    pub fn machine_mut(&mut self) -> &mut ProgramMachine {
+        &mut self.machine
+    }
+
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    pub fn into_profile(self) -> Profile {
+        self.profile
+    }
+}
+
+impl<W: Write> Machine for TracingMachine<W> {
+    fn add_input(&mut self, value: i64) {
+        self.machine.add_input(value);
+    }
+
+    fn try_step(&mut self) -> Result<StepResult, IntcodeError> {
+        let trace = trace_step(&mut self.machine)?;
+        if let Some(writer) = &mut self.trace {
+            // tracing is best effort, a full disk should not stop the program
+            if writeln!(writer, "{}", trace).is_err() {
+                self.trace = None;
+            }
+        }
+        self.profile.record(&trace);
+        Ok(trace.result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    #[test]
+    fn test_trace() {
+        let program = vec![3, 9, 1002, 9, 2, 10, 4, 10, 99, 0, 0];
+        let mut trace = Vec::new();
+        let mut machine =
+            TracingMachine::with_trace(ProgramMachine::new(program, vec![21]), &mut trace);
+        assert_eq!(machine.run(), vec![42]);
+        assert_eq!(machine.profile().steps, 4);
+        assert_eq!(machine.profile().reads[&9], 1);
+        assert_eq!(machine.profile().writes[&10], 1);
+        assert_eq!(machine.profile().opcodes["mul"], 1);
+        drop(machine);
+        let trace = String::from_utf8(trace).unwrap();
+        let lines = trace.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[1].starts_with("     2: mul [9], #2, [10]"));
+        assert!(lines[1].ends_with("rb=0 [9]=21 #2 ; [10] 0 -> 42"));
+        assert!(lines[2].ends_with("rb=0 [10]=42 ; out 42"));
+    }
+
+    #[test]
+    fn test_hotspots() {
+        // counts down from 5
+        let program = vec![1001, 8, -1, 8, 1005, 8, 0, 99, 5];
+        let mut machine: TracingMachine<io::Sink> =
+            TracingMachine::new(ProgramMachine::new(program, vec![]));
+        machine.run();
+        let profile = machine.into_profile();
+        assert_eq!(profile.steps, 11);
+        assert_eq!(profile.pcs[&0].0, 5);
+        let summary = profile.to_string();
+        assert!(summary.starts_with(
+            "11 instructions executed\n\
+             hottest instructions:\n       \
+             0:          5  45.5%  add [8], #-1, [8]\n       \
+             4:          5  45.5%  jt [8], #0\n"
+        ));
+    }
+}